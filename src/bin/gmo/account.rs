@@ -0,0 +1,70 @@
+use anyhow::Result;
+use gmo_coin::private::api::ExecutionsParam;
+use gmo_coin::{GmoCoinClient, Symbol};
+
+use crate::output::{self, Format};
+
+pub fn balance(client: &GmoCoinClient, format: Format) -> Result<()> {
+    output::print(format, &client.assets()?.data)
+}
+
+pub fn margin(client: &GmoCoinClient, format: Format) -> Result<()> {
+    output::print(format, &[client.margin()?.data])
+}
+
+pub fn orders(
+    client: &GmoCoinClient,
+    format: Format,
+    symbol: Option<Symbol>,
+    ids: Vec<usize>,
+) -> Result<()> {
+    match symbol {
+        Some(symbol) => output::print(format, &client.active_orders(symbol, None, None)?.data.list),
+        None => {
+            let ids = ids
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+            output::print(format, &client.orders(ids)?.data.list)
+        }
+    }
+}
+
+pub fn executions(
+    client: &GmoCoinClient,
+    format: Format,
+    symbol: Option<Symbol>,
+    order_id: Option<usize>,
+    execution_id: Option<usize>,
+) -> Result<()> {
+    let param = match (symbol, order_id, execution_id) {
+        (_, Some(order_id), _) => ExecutionsParam::order_id(order_id),
+        (_, None, Some(execution_id)) => ExecutionsParam::execution_id(execution_id),
+        (Some(symbol), None, None) => {
+            return output::print(
+                format,
+                &client.latest_executions(symbol, None, None)?.data.list,
+            );
+        }
+        (None, None, None) => unreachable!("clap requires a symbol or an id"),
+    };
+
+    output::print(format, &client.executions(param)?.data.list)
+}
+
+pub fn positions(
+    client: &GmoCoinClient,
+    format: Format,
+    symbol: Symbol,
+    summary: bool,
+) -> Result<()> {
+    if summary {
+        return output::print(format, &client.position_summary(symbol)?.data.list);
+    }
+
+    output::print(
+        format,
+        &client.open_positions(symbol, None, None)?.data.list,
+    )
+}
@@ -0,0 +1,622 @@
+use anyhow::{Result, bail};
+use gmo_coin::orderbook::OrderBook;
+use gmo_coin::private::api::{ActiveOrders, ExecutionType, Margin, OpenPositions, SettlePosition};
+use gmo_coin::public::api::Trade;
+use gmo_coin::public::websock_api::Channel;
+use gmo_coin::{GmoCoinClient, LeverageSymbol, Side, Symbol};
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Clear, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::watch::{Event, Feed};
+
+/// 画面に残す約定の数
+const TRADES_SHOWN: usize = 100;
+/// キー入力を待つ時間
+const TICK: Duration = Duration::from_millis(200);
+
+/// 別スレッドから届く更新
+enum Update {
+    Market(Event),
+    Account(Box<Account>),
+    Notice(String),
+}
+
+struct Account {
+    orders: Vec<ActiveOrders>,
+    positions: Vec<OpenPositions>,
+    margin: Margin,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Orders,
+    Positions,
+}
+
+/// 確認待ちの操作
+enum Action {
+    Cancel(ActiveOrders),
+    Close(OpenPositions),
+}
+
+struct App {
+    client: Arc<GmoCoinClient>,
+    /// 注文の結果を送り返す
+    updates: Sender<Update>,
+    symbol: Symbol,
+    depth: usize,
+    book: OrderBook,
+    trades: VecDeque<Trade>,
+    account: Option<Account>,
+    focus: Focus,
+    orders: TableState,
+    positions: TableState,
+    pending: Option<Action>,
+    status: String,
+}
+
+/// ### run
+/// 板・約定・注文・建玉・余力を1画面に表示する
+///
+/// 板と約定は`watch`と同じくWebSocket(使えなければREST)から、
+/// 注文・建玉・余力は`refresh`秒ごとにRESTから取得する。
+pub fn run(
+    client: GmoCoinClient,
+    symbol: Symbol,
+    depth: usize,
+    poll: bool,
+    refresh: u64,
+) -> Result<()> {
+    let client = Arc::new(client);
+    let (tx, rx) = mpsc::channel();
+    let (refresh_tx, refresh_rx) = mpsc::channel();
+    spawn_market(client.clone(), symbol, poll, tx.clone());
+    spawn_account(
+        client.clone(),
+        symbol,
+        Duration::from_secs(refresh),
+        refresh_rx,
+        tx.clone(),
+    );
+
+    let mut app = App {
+        client,
+        updates: tx,
+        symbol,
+        depth,
+        book: OrderBook::new(),
+        trades: VecDeque::new(),
+        account: None,
+        focus: Focus::Orders,
+        orders: TableState::default(),
+        positions: TableState::default(),
+        pending: None,
+        status: String::new(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal, &rx, &refresh_tx);
+    ratatui::restore();
+    result
+}
+
+fn spawn_market(client: Arc<GmoCoinClient>, symbol: Symbol, poll: bool, tx: Sender<Update>) {
+    thread::spawn(move || {
+        let mut feed = Feed::open(
+            client,
+            symbol,
+            &[Channel::OrderBooks, Channel::Trades],
+            poll,
+            Duration::from_secs(1),
+        );
+        loop {
+            let events = feed.next();
+            let mut updates: Vec<Update> = feed
+                .take_notices()
+                .into_iter()
+                .map(Update::Notice)
+                .collect();
+            match events {
+                Ok(events) => updates.extend(events.into_iter().map(Update::Market)),
+                Err(e) => updates.push(Update::Notice(format!("error: {:#}", e))),
+            }
+            for update in updates {
+                if tx.send(update).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// `refresh_rx`に通知が来るか`interval`が経つたびに取得し直す
+fn spawn_account(
+    client: Arc<GmoCoinClient>,
+    symbol: Symbol,
+    interval: Duration,
+    refresh_rx: Receiver<()>,
+    tx: Sender<Update>,
+) {
+    thread::spawn(move || {
+        loop {
+            let update = match fetch_account(&client, symbol) {
+                Ok(account) => Update::Account(Box::new(account)),
+                Err(e) => Update::Notice(format!("error: {:#}", e)),
+            };
+            if tx.send(update).is_err() {
+                return;
+            }
+
+            match refresh_rx.recv_timeout(interval) {
+                Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    });
+}
+
+fn fetch_account(client: &GmoCoinClient, symbol: Symbol) -> Result<Account> {
+    let orders = client.active_orders(symbol, None, None)?.data.list;
+    // 現物の銘柄には建玉が無い
+    let positions = match LeverageSymbol::from_str(&symbol.to_string()) {
+        Ok(_) => client.open_positions(symbol, None, None)?.data.list,
+        Err(_) => Vec::new(),
+    };
+    let margin = client.margin()?.data;
+
+    Ok(Account {
+        orders,
+        positions,
+        margin,
+    })
+}
+
+impl App {
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        rx: &Receiver<Update>,
+        refresh_tx: &Sender<()>,
+    ) -> Result<()> {
+        loop {
+            while let Ok(update) = rx.try_recv() {
+                self.apply(update);
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(TICK)? {
+                continue;
+            }
+            let TermEvent::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if let Some(action) = self.pending.take() {
+                if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                    self.status = "sending...".to_string();
+                    spawn_action(
+                        self.client.clone(),
+                        action,
+                        self.updates.clone(),
+                        refresh_tx.clone(),
+                    );
+                } else {
+                    self.status = "canceled".to_string();
+                }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(());
+                }
+                KeyCode::Tab => {
+                    self.focus = match self.focus {
+                        Focus::Orders => Focus::Positions,
+                        Focus::Positions => Focus::Orders,
+                    }
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+                KeyCode::Char('c') => self.pending = self.selected_order().map(Action::Cancel),
+                KeyCode::Char('x') => self.pending = self.selected_position().map(Action::Close),
+                KeyCode::Char('r') => {
+                    let _ = refresh_tx.send(());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn apply(&mut self, update: Update) {
+        let result = match update {
+            Update::Market(Event::BookUpdate(update)) => self.book.apply_update(&update).map(drop),
+            Update::Market(Event::BookSnapshot(resp)) => self.book.apply_response(&resp).map(drop),
+            Update::Market(Event::Trade(trade)) => {
+                self.trades.push_front(trade);
+                self.trades.truncate(TRADES_SHOWN);
+                Ok(())
+            }
+            Update::Market(Event::Ticker(_)) => Ok(()),
+            Update::Account(account) => {
+                clamp(&mut self.orders, account.orders.len());
+                clamp(&mut self.positions, account.positions.len());
+                self.account = Some(*account);
+                Ok(())
+            }
+            Update::Notice(notice) => {
+                self.status = notice;
+                Ok(())
+            }
+        };
+
+        if let Err(e) = result {
+            self.status = format!("error: {}", e);
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (state, len) = match self.focus {
+            Focus::Orders => (
+                &mut self.orders,
+                self.account.as_ref().map_or(0, |a| a.orders.len()),
+            ),
+            Focus::Positions => (
+                &mut self.positions,
+                self.account.as_ref().map_or(0, |a| a.positions.len()),
+            ),
+        };
+        if len == 0 {
+            state.select(None);
+            return;
+        }
+
+        let current = state.selected().unwrap_or(0) as isize;
+        state.select(Some((current + delta).clamp(0, len as isize - 1) as usize));
+    }
+
+    fn selected_order(&self) -> Option<ActiveOrders> {
+        if self.focus != Focus::Orders {
+            return None;
+        }
+        self.account
+            .as_ref()?
+            .orders
+            .get(self.orders.selected()?)
+            .cloned()
+    }
+
+    fn selected_position(&self) -> Option<OpenPositions> {
+        if self.focus != Focus::Positions {
+            return None;
+        }
+        self.account
+            .as_ref()?
+            .positions
+            .get(self.positions.selected()?)
+            .cloned()
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [book, tape, account] = Layout::horizontal([
+            Constraint::Length(30),
+            Constraint::Length(40),
+            Constraint::Min(0),
+        ])
+        .areas(body);
+        let [orders, positions] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(account);
+
+        frame.render_widget(Paragraph::new(self.header()), header);
+        self.draw_book(frame, book);
+        self.draw_trades(frame, tape);
+        self.draw_orders(frame, orders);
+        self.draw_positions(frame, positions);
+        frame.render_widget(Paragraph::new(self.footer()), footer);
+
+        if let Some(action) = &self.pending {
+            draw_confirm(frame, action);
+        }
+    }
+
+    fn header(&self) -> Line<'static> {
+        let show = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        let mut text = format!(
+            "{}  bid {}  ask {}  spread {}",
+            self.symbol,
+            show(self.book.best_bid().map(|level| level.price.to_string())),
+            show(self.book.best_ask().map(|level| level.price.to_string())),
+            show(self.book.spread().map(|spread| spread.to_string())),
+        );
+        if let Some(account) = &self.account {
+            text = format!(
+                "{}  |  margin {}  available {}  P/L {}",
+                text,
+                account.margin.margin,
+                account.margin.available_amount,
+                account.margin.profit_loss
+            );
+        }
+
+        Line::from(text).style(Style::new().add_modifier(Modifier::BOLD))
+    }
+
+    fn footer(&self) -> Line<'static> {
+        let keys =
+            "q:quit  tab:orders/positions  j/k:select  c:cancel order  x:close position  r:refresh";
+        if self.status.is_empty() {
+            Line::from(keys)
+        } else {
+            Line::from(format!("{}  |  {}", self.status, keys))
+        }
+    }
+
+    fn draw_book(&self, frame: &mut Frame, area: Rect) {
+        // 枠の分を除いた行数の半分ずつを売り板と買い板に使う
+        let depth = self.depth.min((area.height.saturating_sub(3) / 2) as usize);
+        let mut asks: Vec<Row> = self
+            .book
+            .asks()
+            .take(depth)
+            .map(|level| {
+                Row::new([level.price.to_string(), level.size.to_string()])
+                    .style(Style::new().fg(Color::Red))
+            })
+            .collect();
+        asks.reverse();
+        let bids = self.book.bids().take(depth).map(|level| {
+            Row::new([level.price.to_string(), level.size.to_string()])
+                .style(Style::new().fg(Color::Green))
+        });
+
+        let table = Table::new(
+            asks.into_iter().chain(bids),
+            [Constraint::Length(14), Constraint::Min(0)],
+        )
+        .header(header_row(["price", "size"]))
+        .block(Block::bordered().title("book"));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_trades(&self, frame: &mut Frame, area: Rect) {
+        let rows = self.trades.iter().map(|trade| {
+            Row::new([
+                // 2024-01-01T12:34:56.789Z の時刻部分
+                trade.timestamp.get(11..19).unwrap_or("").to_string(),
+                trade.side.clone(),
+                trade.price.clone(),
+                trade.size.clone(),
+            ])
+            .style(Style::new().fg(side_color(&trade.side)))
+        });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(4),
+                Constraint::Length(12),
+                Constraint::Min(0),
+            ],
+        )
+        .header(header_row(["time", "side", "price", "size"]))
+        .block(Block::bordered().title("trades"));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_orders(&mut self, frame: &mut Frame, area: Rect) {
+        let orders = self.account.as_ref().map_or(&[][..], |a| &a.orders[..]);
+        let rows = orders.iter().map(|order| {
+            Row::new([
+                order.order_id.to_string(),
+                order.side.clone(),
+                order.execution_type.clone(),
+                order.price.clone(),
+                format!("{}/{}", order.executed_size, order.size),
+                order.status.clone(),
+            ])
+            .style(Style::new().fg(side_color(&order.side)))
+        });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(12),
+                Constraint::Length(4),
+                Constraint::Length(6),
+                Constraint::Length(12),
+                Constraint::Length(16),
+                Constraint::Min(0),
+            ],
+        )
+        .header(header_row([
+            "orderId", "side", "type", "price", "executed", "status",
+        ]))
+        .block(focused_block("orders", self.focus == Focus::Orders))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut self.orders);
+    }
+
+    fn draw_positions(&mut self, frame: &mut Frame, area: Rect) {
+        let positions = self.account.as_ref().map_or(&[][..], |a| &a.positions[..]);
+        let rows = positions.iter().map(|position| {
+            Row::new([
+                position.position_id.to_string(),
+                position.side.clone(),
+                position.size.clone(),
+                position.price.clone(),
+                position.loss_gain.clone(),
+                position.losscut_price.clone(),
+            ])
+            .style(Style::new().fg(side_color(&position.side)))
+        });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(12),
+                Constraint::Length(4),
+                Constraint::Length(10),
+                Constraint::Length(12),
+                Constraint::Length(12),
+                Constraint::Min(0),
+            ],
+        )
+        .header(header_row([
+            "positionId",
+            "side",
+            "size",
+            "price",
+            "lossGain",
+            "losscut",
+        ]))
+        .block(focused_block("positions", self.focus == Focus::Positions))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut self.positions);
+    }
+}
+
+/// 画面を止めないよう、確認された操作を別スレッドで実行して結果を`tx`に送る
+fn spawn_action(
+    client: Arc<GmoCoinClient>,
+    action: Action,
+    tx: Sender<Update>,
+    refresh_tx: Sender<()>,
+) {
+    thread::spawn(move || {
+        let notice = match execute(&client, &action) {
+            Ok(message) => message,
+            Err(e) => format!("error: {:#}", e),
+        };
+        let _ = tx.send(Update::Notice(notice));
+        let _ = refresh_tx.send(());
+    });
+}
+
+/// 確認された操作を実行する
+fn execute(client: &GmoCoinClient, action: &Action) -> Result<String> {
+    match action {
+        Action::Cancel(order) => {
+            client.cancel_order(order.order_id)?;
+            Ok(format!("canceled order {}", order.order_id))
+        }
+        Action::Close(position) => {
+            let size = closable(position)?;
+            if size <= Decimal::ZERO {
+                bail!("position {} is already being closed", position.position_id);
+            }
+            let symbol = LeverageSymbol::from_str(&position.symbol)?;
+            let side = match Side::from_str(&position.side)? {
+                Side::BUY => Side::SELL,
+                Side::SELL => Side::BUY,
+            };
+            let resp = client.close_order(
+                symbol,
+                side,
+                ExecutionType::MARKET,
+                None,
+                SettlePosition {
+                    position_id: position.position_id,
+                    size: size.to_string(),
+                },
+            )?;
+            Ok(format!(
+                "closing position {} (order {})",
+                position.position_id, resp.data
+            ))
+        }
+    }
+}
+
+/// 決済注文中の数量を除いた、まだ決済できる数量
+fn closable(position: &OpenPositions) -> Result<Decimal> {
+    Ok((Decimal::from_str(&position.size)? - Decimal::from_str(&position.order_size)?).normalize())
+}
+
+fn draw_confirm(frame: &mut Frame, action: &Action) {
+    let text = match action {
+        Action::Cancel(order) => format!(
+            "cancel order {}\n{} {} {} @ {}\n\n[y] yes  [n] no",
+            order.order_id, order.side, order.size, order.symbol, order.price
+        ),
+        Action::Close(position) => format!(
+            "close position {} at MARKET\n{} {} {} @ {}  P/L {}\n\n[y] yes  [n] no",
+            position.position_id,
+            position.side,
+            closable(position)
+                .map(|size| size.to_string())
+                .unwrap_or_else(|_| position.size.clone()),
+            position.symbol,
+            position.price,
+            position.loss_gain
+        ),
+    };
+
+    let area = frame.area();
+    let width = 50.min(area.width);
+    let height = 7.min(area.height);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(
+        Paragraph::new(text).block(Block::bordered().title("confirm")),
+        popup,
+    );
+}
+
+fn header_row<const N: usize>(names: [&'static str; N]) -> Row<'static> {
+    Row::new(names.map(Cell::from)).style(Style::new().add_modifier(Modifier::BOLD))
+}
+
+fn focused_block(title: &'static str, focused: bool) -> Block<'static> {
+    let block = Block::bordered().title(title);
+    if focused {
+        block.border_style(Style::new().fg(Color::Yellow))
+    } else {
+        block
+    }
+}
+
+fn side_color(side: &str) -> Color {
+    match side {
+        "BUY" => Color::Green,
+        "SELL" => Color::Red,
+        _ => Color::Reset,
+    }
+}
+
+/// 一覧が短くなったときに選択位置を範囲内に戻す
+fn clamp(state: &mut TableState, len: usize) {
+    match (state.selected(), len) {
+        (_, 0) => state.select(None),
+        (None, _) => state.select(Some(0)),
+        (Some(selected), _) if selected >= len => state.select(Some(len - 1)),
+        _ => {}
+    }
+}
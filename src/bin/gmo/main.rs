@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use clap::{ArgGroup, Parser, Subcommand};
+use gmo_coin::config::Config;
+use gmo_coin::private::api::ExecutionType;
+use gmo_coin::public::api::{KlineDate, KlineInterval};
+use gmo_coin::{GmoCoinClient, LeverageSymbol, Side, Symbol};
+use std::path::PathBuf;
+
+mod account;
+#[cfg(feature = "tui")]
+mod dashboard;
+mod output;
+mod public;
+#[cfg(unix)]
+mod signer;
+mod trade;
+mod watch;
+
+use output::Format;
+use trade::Confirm;
+use watch::WatchCommand;
+
+/// GMOコインのAPIを叩くCLI
+#[derive(Debug, Parser)]
+#[command(name = "gmo", version, about)]
+struct Cli {
+    /// 出力形式
+    #[arg(long, global = true, value_enum, default_value_t)]
+    format: Format,
+    /// 設定ファイルのプロファイル。省略すると$GMO_COIN_PROFILEかdefault_profile
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// 注文・キャンセル・決済を送らない。省略するとプロファイルのread_only
+    #[arg(
+        long,
+        global = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    read_only: Option<bool>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 取引所の稼動状態
+    Status,
+    /// 最新レート。銘柄を省略すると全銘柄
+    Ticker { symbol: Option<Symbol> },
+    /// 板情報
+    Book {
+        symbol: Option<Symbol>,
+        /// 表示する価格帯の数
+        #[arg(long, default_value_t = 10)]
+        depth: usize,
+    },
+    /// 取引履歴
+    Trades {
+        symbol: Option<Symbol>,
+        #[arg(long)]
+        page: Option<usize>,
+        #[arg(long)]
+        count: Option<usize>,
+    },
+    /// ローソク足。DATEは1hour以下ならYYYYMMDD、4hour以上ならYYYY
+    Klines {
+        symbol: Symbol,
+        interval: KlineInterval,
+        date: KlineDate,
+        /// 指定するとDATEからこの日までをまとめて取得する
+        #[arg(long)]
+        to: Option<KlineDate>,
+        /// まとめて取得するときのキャッシュの保存先
+        #[arg(long, requires = "to")]
+        cache_dir: Option<PathBuf>,
+    },
+    /// 取引ルール
+    Symbols,
+    /// 板・約定・注文・建玉・余力を1画面に表示する
+    #[cfg(feature = "tui")]
+    Dashboard {
+        symbol: Option<Symbol>,
+        /// 表示する価格帯の数
+        #[arg(long, default_value_t = 10)]
+        depth: usize,
+        /// WebSocketを使わずにRESTをポーリングする
+        #[arg(long)]
+        poll: bool,
+        /// 注文・建玉・余力を取得し直す間隔(秒)
+        #[arg(long, default_value_t = 3)]
+        refresh: u64,
+    },
+    /// WebSocketで受け取ったマーケット情報を表示し続ける
+    Watch {
+        #[command(subcommand)]
+        command: WatchCommand,
+    },
+    /// 資産残高
+    Balance,
+    /// 余力情報
+    Margin,
+    /// 有効注文(SYMBOL)または指定した注文(--id)
+    #[command(group(ArgGroup::new("target").required(true).args(["symbol", "ids"])))]
+    Orders {
+        symbol: Option<Symbol>,
+        /// 注文ID。カンマ区切りで複数指定できる
+        #[arg(long = "id", value_delimiter = ',')]
+        ids: Vec<usize>,
+    },
+    /// 最新の約定(SYMBOL)または指定した注文・約定の約定
+    #[command(group(ArgGroup::new("target").required(true).args(["symbol", "order_id", "execution_id"])))]
+    Executions {
+        symbol: Option<Symbol>,
+        #[arg(long, conflicts_with = "execution_id")]
+        order_id: Option<usize>,
+        #[arg(long)]
+        execution_id: Option<usize>,
+    },
+    /// 建玉
+    Positions {
+        symbol: Option<Symbol>,
+        /// 建玉サマリを表示する
+        #[arg(long)]
+        summary: bool,
+    },
+    /// 注文の発注・キャンセル・変更
+    Order {
+        #[command(subcommand)]
+        command: OrderCommand,
+    },
+    /// 建玉の決済
+    Close {
+        symbol: LeverageSymbol,
+        /// 決済注文の売買区分
+        side: Side,
+        execution_type: ExecutionType,
+        position_id: usize,
+        size: String,
+        #[arg(long)]
+        price: Option<String>,
+        #[command(flatten)]
+        confirm: Confirm,
+    },
+    /// シークレットキーを持ち、Unixソケットで署名を引き受けるプロセスとして動く
+    #[cfg(unix)]
+    Signer {
+        /// 待ち受けるソケットのパス
+        #[arg(long)]
+        socket: PathBuf,
+    },
+    /// 指定した銘柄の注文をすべてキャンセルする
+    CancelAll {
+        #[arg(required = true)]
+        symbols: Vec<Symbol>,
+        #[command(flatten)]
+        confirm: Confirm,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum OrderCommand {
+    /// 新規注文
+    Place {
+        symbol: Symbol,
+        side: Side,
+        execution_type: ExecutionType,
+        size: String,
+        /// LIMITとSTOPでは必須
+        #[arg(long)]
+        price: Option<String>,
+        #[command(flatten)]
+        confirm: Confirm,
+    },
+    /// 注文キャンセル
+    Cancel {
+        #[arg(required = true)]
+        order_ids: Vec<usize>,
+        #[command(flatten)]
+        confirm: Confirm,
+    },
+    /// 注文変更
+    Change {
+        order_id: usize,
+        price: String,
+        #[arg(long)]
+        losscut_price: Option<String>,
+        #[command(flatten)]
+        confirm: Confirm,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli) {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let config = Config::load_default()?;
+    let mut client = GmoCoinClient::from_config(&config, cli.profile.as_deref())?;
+    if let Some(read_only) = cli.read_only {
+        client = client.read_only(read_only);
+    }
+    let format = cli.format;
+    let symbol = |symbol: Option<Symbol>| {
+        symbol
+            .or(client.get_default_symbol())
+            .context("SYMBOL is required unless the profile has a default_symbol")
+    };
+
+    match cli.command {
+        Command::Status => public::status(&client, format),
+        Command::Ticker { symbol } => public::ticker(&client, format, symbol),
+        Command::Book { symbol: s, depth } => public::book(&client, format, symbol(s)?, depth),
+        Command::Trades {
+            symbol: s,
+            page,
+            count,
+        } => public::trades(&client, format, symbol(s)?, page, count),
+        Command::Klines {
+            symbol,
+            interval,
+            date,
+            to,
+            cache_dir,
+        } => public::klines(&client, format, symbol, interval, date, to, cache_dir),
+        Command::Symbols => public::symbols(&client, format),
+        Command::Watch { command } => watch::run(client, format, command),
+        #[cfg(feature = "tui")]
+        Command::Dashboard {
+            symbol: s,
+            depth,
+            poll,
+            refresh,
+        } => {
+            let symbol = symbol(s)?;
+            dashboard::run(client, symbol, depth, poll, refresh)
+        }
+        Command::Balance => account::balance(&client, format),
+        Command::Margin => account::margin(&client, format),
+        Command::Orders { symbol, ids } => account::orders(&client, format, symbol, ids),
+        Command::Executions {
+            symbol,
+            order_id,
+            execution_id,
+        } => account::executions(&client, format, symbol, order_id, execution_id),
+        Command::Positions { symbol: s, summary } => {
+            account::positions(&client, format, symbol(s)?, summary)
+        }
+        Command::Order { command } => match command {
+            OrderCommand::Place {
+                symbol,
+                side,
+                execution_type,
+                size,
+                price,
+                confirm,
+            } => trade::place(
+                &client,
+                format,
+                symbol,
+                side,
+                execution_type,
+                size,
+                price,
+                confirm,
+            ),
+            OrderCommand::Cancel { order_ids, confirm } => {
+                trade::cancel(&client, format, order_ids, confirm)
+            }
+            OrderCommand::Change {
+                order_id,
+                price,
+                losscut_price,
+                confirm,
+            } => trade::change(&client, format, order_id, price, losscut_price, confirm),
+        },
+        Command::Close {
+            symbol,
+            side,
+            execution_type,
+            position_id,
+            size,
+            price,
+            confirm,
+        } => trade::close(
+            &client,
+            format,
+            symbol,
+            side,
+            execution_type,
+            position_id,
+            size,
+            price,
+            confirm,
+        ),
+        #[cfg(unix)]
+        Command::Signer { socket } => {
+            signer::run(&config.profile(cli.profile.as_deref())?, &socket)
+        }
+        Command::CancelAll { symbols, confirm } => {
+            trade::cancel_all(&client, format, symbols, confirm)
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// 出力形式
+///
+/// 列名はレスポンスの構造体のフィールド名(APIと同じcamelCase)をそのまま使う。
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum Format {
+    /// 列幅を揃えた表
+    #[default]
+    Table,
+    /// レコードの配列
+    Json,
+    /// 1行1レコードのJSON
+    Ndjson,
+    /// ヘッダ付きのCSV
+    Csv,
+}
+
+/// レコードを指定した形式で出力する
+pub fn print<T: Serialize>(format: Format, records: &[T]) -> Result<()> {
+    print!("{}", render(format, records)?);
+    Ok(())
+}
+
+/// 1レコードをヘッダ無しの1行で出力する
+///
+/// `json`と`ndjson`はどちらも1行のJSONにする。
+pub fn line<T: Serialize>(format: Format, record: &T) -> Result<()> {
+    println!("{}", render_line(format, record)?);
+    Ok(())
+}
+
+/// `print`が出力する文字列
+///
+/// レコードが無ければ列も分からないので、`csv`と`table`は何も出力しない。
+fn render<T: Serialize>(format: Format, records: &[T]) -> Result<String> {
+    let records = records.iter().map(to_record).collect::<Result<Vec<_>>>()?;
+    let mut out = String::new();
+
+    match format {
+        Format::Json => {
+            out.push_str(&serde_json::to_string_pretty(&records)?);
+            out.push('\n');
+        }
+        Format::Ndjson => {
+            for record in &records {
+                out.push_str(&serde_json::to_string(record)?);
+                out.push('\n');
+            }
+        }
+        Format::Csv if records.is_empty() => {}
+        Format::Csv => {
+            let columns = columns(&records);
+            out.push_str(&csv_line(columns.iter().map(String::as_str)));
+            out.push('\n');
+            for record in &records {
+                let cells: Vec<String> = columns.iter().map(|c| cell(record.get(c))).collect();
+                out.push_str(&csv_line(cells.iter().map(String::as_str)));
+                out.push('\n');
+            }
+        }
+        Format::Table if records.is_empty() => {}
+        Format::Table => {
+            let columns = columns(&records);
+            let rows: Vec<Vec<String>> = records
+                .iter()
+                .map(|record| columns.iter().map(|c| cell(record.get(c))).collect())
+                .collect();
+            out.push_str(&table(&columns, &rows));
+        }
+    }
+
+    Ok(out)
+}
+
+/// `line`が出力する1行(改行を除く)
+fn render_line<T: Serialize>(format: Format, record: &T) -> Result<String> {
+    let record = to_record(record)?;
+    let cells = || -> Vec<String> { record.values().map(|v| cell(Some(v))).collect() };
+    Ok(match format {
+        Format::Json | Format::Ndjson => serde_json::to_string(&record)?,
+        Format::Csv => csv_line(cells().iter().map(String::as_str)),
+        Format::Table => cells().join("  "),
+    })
+}
+
+fn to_record<T: Serialize>(record: &T) -> Result<Map<String, Value>> {
+    Ok(match serde_json::to_value(record)? {
+        Value::Object(map) => map,
+        value => {
+            let mut map = Map::new();
+            map.insert("value".to_string(), value);
+            map
+        }
+    })
+}
+
+/// 全レコードに現れる列を、最初に現れた順に並べる
+fn columns(records: &[Map<String, Value>]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for key in records.iter().flat_map(Map::keys) {
+        if !columns.contains(key) {
+            columns.push(key.clone());
+        }
+    }
+
+    columns
+}
+
+/// 文字列はそのまま、数値はJSONの表記、値が無ければ空にする
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+fn csv_line<'a>(cells: impl Iterator<Item = &'a str>) -> String {
+    cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 列幅を揃えた表
+fn table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: &[String]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+
+    std::iter::once(headers)
+        .chain(rows.iter().map(Vec::as_slice))
+        .map(line)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Order {
+        order_id: usize,
+        side: &'static str,
+        price: Option<&'static str>,
+    }
+
+    fn orders() -> Vec<Order> {
+        vec![
+            Order {
+                order_id: 1,
+                side: "BUY",
+                price: Some("5000000"),
+            },
+            Order {
+                order_id: 12,
+                side: "SELL",
+                price: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn table_aligns_columns_in_field_order() {
+        assert_eq!(
+            render(Format::Table, &orders()).unwrap(),
+            "orderId  side  price\n1        BUY   5000000\n12       SELL\n"
+        );
+    }
+
+    #[test]
+    fn json_is_an_array_and_ndjson_one_line_per_record() {
+        let json: Value = serde_json::from_str(&render(Format::Json, &orders()).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            json!([
+                {"orderId": 1, "side": "BUY", "price": "5000000"},
+                {"orderId": 12, "side": "SELL", "price": null}
+            ])
+        );
+        assert_eq!(
+            render(Format::Ndjson, &orders()).unwrap(),
+            "{\"orderId\":1,\"side\":\"BUY\",\"price\":\"5000000\"}\n\
+             {\"orderId\":12,\"side\":\"SELL\",\"price\":null}\n"
+        );
+    }
+
+    #[test]
+    fn csv_quotes_commas_quotes_and_newlines() {
+        let records = [json!({
+            "plain": "BTC",
+            "comma": "1,000",
+            "quote": "say \"hi\"",
+            "newline": "a\nb"
+        })];
+        assert_eq!(
+            render(Format::Csv, &records).unwrap(),
+            "plain,comma,quote,newline\nBTC,\"1,000\",\"say \"\"hi\"\"\",\"a\nb\"\n"
+        );
+    }
+
+    #[test]
+    fn columns_cover_every_record_and_nested_values_are_json() {
+        let records = [
+            json!({"symbol": "BTC"}),
+            json!({"symbol": "ETH", "levels": [{"price": "1"}], "open": true}),
+        ];
+        assert_eq!(
+            render(Format::Csv, &records).unwrap(),
+            "symbol,levels,open\nBTC,,\nETH,\"[{\"\"price\"\":\"\"1\"\"}]\",true\n"
+        );
+    }
+
+    #[test]
+    fn scalars_become_a_value_column() {
+        assert_eq!(
+            render(Format::Csv, &["token-1"]).unwrap(),
+            "value\ntoken-1\n"
+        );
+    }
+
+    #[test]
+    fn empty_records_print_nothing_but_an_empty_json_array() {
+        let empty: [Value; 0] = [];
+        assert_eq!(render(Format::Table, &empty).unwrap(), "");
+        assert_eq!(render(Format::Csv, &empty).unwrap(), "");
+        assert_eq!(render(Format::Ndjson, &empty).unwrap(), "");
+        assert_eq!(render(Format::Json, &empty).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn line_has_no_header() {
+        let order = &orders()[0];
+        assert_eq!(render_line(Format::Csv, order).unwrap(), "1,BUY,5000000");
+        assert_eq!(
+            render_line(Format::Table, order).unwrap(),
+            "1  BUY  5000000"
+        );
+        assert_eq!(
+            render_line(Format::Json, order).unwrap(),
+            "{\"orderId\":1,\"side\":\"BUY\",\"price\":\"5000000\"}"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Result, bail};
+use gmo_coin::orderbook::OrderBook;
+use gmo_coin::public::api::{KlineDate, KlineInterval};
+use gmo_coin::public::history::{KlineDownloader, find_gaps};
+use gmo_coin::{GmoCoinClient, Symbol};
+use serde::Serialize;
+use std::path::PathBuf;
+use time::Date;
+
+use crate::output::{self, Format};
+
+/// 板の1行
+#[derive(Debug, Serialize)]
+pub struct BookRow {
+    side: &'static str,
+    price: String,
+    size: String,
+}
+
+pub fn status(client: &GmoCoinClient, format: Format) -> Result<()> {
+    output::print(format, &[client.status()?.data])
+}
+
+pub fn ticker(client: &GmoCoinClient, format: Format, symbol: Option<Symbol>) -> Result<()> {
+    output::print(format, &client.ticker(symbol)?.data)
+}
+
+pub fn book(client: &GmoCoinClient, format: Format, symbol: Symbol, depth: usize) -> Result<()> {
+    let book = OrderBook::from_response(&client.orderbooks(symbol)?)?;
+    output::print(format, &book_rows(&book, depth))
+}
+
+/// 売り板を高い順に、続けて買い板を高い順に並べる
+pub fn book_rows(book: &OrderBook, depth: usize) -> Vec<BookRow> {
+    let mut rows: Vec<BookRow> = book
+        .asks()
+        .take(depth)
+        .map(|level| BookRow {
+            side: "SELL",
+            price: level.price.to_string(),
+            size: level.size.to_string(),
+        })
+        .collect();
+    rows.reverse();
+    rows.extend(book.bids().take(depth).map(|level| BookRow {
+        side: "BUY",
+        price: level.price.to_string(),
+        size: level.size.to_string(),
+    }));
+
+    rows
+}
+
+pub fn trades(
+    client: &GmoCoinClient,
+    format: Format,
+    symbol: Symbol,
+    page: Option<usize>,
+    count: Option<usize>,
+) -> Result<()> {
+    output::print(format, &client.trades(symbol, page, count)?.data.list)
+}
+
+pub fn klines(
+    client: &GmoCoinClient,
+    format: Format,
+    symbol: Symbol,
+    interval: KlineInterval,
+    date: KlineDate,
+    to: Option<KlineDate>,
+    cache_dir: Option<PathBuf>,
+) -> Result<()> {
+    let klines = match to {
+        None => client.klines(symbol, interval, date)?.data,
+        Some(to) => {
+            let mut downloader = KlineDownloader::new(symbol, interval);
+            if let Some(cache_dir) = cache_dir {
+                downloader = downloader.cache_dir(cache_dir);
+            }
+            let klines = downloader.download(client, first_day(date)?, first_day(to)?)?;
+            for gap in find_gaps(&klines, interval)? {
+                eprintln!("gap: no klines between {} and {}", gap.from, gap.to);
+            }
+            klines
+        }
+    };
+
+    output::print(format, &klines)
+}
+
+pub fn symbols(client: &GmoCoinClient, format: Format) -> Result<()> {
+    output::print(format, &client.symbols()?.data)
+}
+
+/// 期間指定の両端を日付にする。年の場合はその年の1月1日
+fn first_day(date: KlineDate) -> Result<Date> {
+    match date {
+        KlineDate::Day(date) => Ok(date),
+        KlineDate::Year(year) => match Date::from_ordinal_date(year, 1) {
+            Ok(date) => Ok(date),
+            Err(_) => bail!("invalid year: {}", year),
+        },
+    }
+}
@@ -0,0 +1,19 @@
+use anyhow::{Context, Result};
+use gmo_coin::config::Profile;
+use gmo_coin::signer::{self, HmacSigner};
+use std::path::Path;
+
+/// ### run
+/// プロファイルのシークレットキーで署名するプロセスとして待ち受ける
+///
+/// 取引側のプロファイルには`signer`にこのソケットを指定し、`secret_key`は書かない。
+pub fn run(profile: &Profile, socket: &Path) -> Result<()> {
+    // 署名にはシークレットキーしか使わないので、APIキーは読まない
+    let secret_key = profile.credential_provider().secret_key.resolve()?;
+    let listener =
+        signer::bind(socket).with_context(|| format!("can't listen on {}", socket.display()))?;
+    eprintln!("signing requests on {}", socket.display());
+
+    signer::serve(&listener, &HmacSigner::new(secret_key))?;
+    Ok(())
+}
@@ -0,0 +1,288 @@
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use gmo_coin::private::api::{self, ExecutionType, SettlePosition, SignedRequest, UnsignedRequest};
+use gmo_coin::{GmoCoinClient, GmoCoinError, LeverageSymbol, Side, Symbol};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use crate::output::{self, Format};
+
+/// 注文を送るコマンドに共通のオプション
+#[derive(Debug, Args)]
+pub struct Confirm {
+    /// 確認せずに送信する
+    #[arg(long, short)]
+    yes: bool,
+    /// 署名したリクエストを表示するだけで送信しない
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// `--dry-run`で出力する署名済みのリクエスト
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DryRun {
+    method: &'static str,
+    url: String,
+    api_key: String,
+    api_timestamp: u64,
+    api_sign: String,
+    body: String,
+}
+
+impl From<SignedRequest> for DryRun {
+    fn from(request: SignedRequest) -> Self {
+        let masked: String = request.api_key.expose().chars().take(4).collect();
+        Self {
+            method: request.method,
+            url: request.url(),
+            api_key: format!("{}***", masked),
+            api_timestamp: request.timestamp,
+            api_sign: request.sign,
+            body: request.body,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn place(
+    client: &GmoCoinClient,
+    format: Format,
+    symbol: Symbol,
+    side: Side,
+    execution_type: ExecutionType,
+    size: String,
+    price: Option<String>,
+    confirm: Confirm,
+) -> Result<()> {
+    if execution_type != ExecutionType::MARKET && price.is_none() {
+        bail!("--price is required for {} orders", execution_type);
+    }
+
+    execute(
+        client,
+        format,
+        &confirm,
+        || {
+            let notional = notional(client, symbol, price.as_deref(), &size)?;
+            client.check_order(symbol, side, execution_type, price.as_deref(), &size)?;
+
+            Ok(vec![
+                ("symbol", symbol.to_string()),
+                ("side", side.to_string()),
+                ("executionType", execution_type.to_string()),
+                ("size", size.clone()),
+                ("price", price.clone().unwrap_or_else(|| "-".to_string())),
+                ("notional (JPY)", notional.to_string()),
+            ])
+        },
+        api::order_request(symbol, side, execution_type, price.clone(), size.clone()),
+    )
+}
+
+pub fn cancel(
+    client: &GmoCoinClient,
+    format: Format,
+    order_ids: Vec<usize>,
+    confirm: Confirm,
+) -> Result<()> {
+    let ids = order_ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let request = match order_ids.as_slice() {
+        [order_id] => api::cancel_order_request(*order_id),
+        _ => api::cancel_orders_request(order_ids.clone()),
+    };
+    execute(
+        client,
+        format,
+        &confirm,
+        || Ok(vec![("cancel orderId", ids.clone())]),
+        request,
+    )
+}
+
+pub fn change(
+    client: &GmoCoinClient,
+    format: Format,
+    order_id: usize,
+    price: String,
+    losscut_price: Option<String>,
+    confirm: Confirm,
+) -> Result<()> {
+    execute(
+        client,
+        format,
+        &confirm,
+        || {
+            let order = client
+                .orders(order_id)?
+                .data
+                .list
+                .into_iter()
+                .next()
+                .with_context(|| format!("order {} not found", order_id))?;
+            let remaining =
+                Decimal::from_str(&order.size)? - Decimal::from_str(&order.executed_size)?;
+            let notional = (Decimal::from_str(&price)? * remaining).normalize();
+            client.check_change_order(order_id, &price)?;
+
+            Ok(vec![
+                ("orderId", order_id.to_string()),
+                ("symbol", order.symbol),
+                ("side", order.side),
+                ("price", format!("{} -> {}", order.price, price)),
+                (
+                    "losscutPrice",
+                    losscut_price.clone().unwrap_or_else(|| "-".to_string()),
+                ),
+                ("remaining size", remaining.to_string()),
+                ("notional (JPY)", notional.to_string()),
+            ])
+        },
+        api::change_order_request(order_id, price.clone(), losscut_price.clone()),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn close(
+    client: &GmoCoinClient,
+    format: Format,
+    symbol: LeverageSymbol,
+    side: Side,
+    execution_type: ExecutionType,
+    position_id: usize,
+    size: String,
+    price: Option<String>,
+    confirm: Confirm,
+) -> Result<()> {
+    execute(
+        client,
+        format,
+        &confirm,
+        || {
+            let notional = notional(
+                client,
+                Symbol::from_str(&symbol.to_string())?,
+                price.as_deref(),
+                &size,
+            )?;
+            client.check_close_order(symbol, side, execution_type, price.as_deref(), &size)?;
+
+            Ok(vec![
+                ("symbol", symbol.to_string()),
+                ("side", side.to_string()),
+                ("executionType", execution_type.to_string()),
+                ("positionId", position_id.to_string()),
+                ("size", size.clone()),
+                ("price", price.clone().unwrap_or_else(|| "-".to_string())),
+                ("notional (JPY)", notional.to_string()),
+            ])
+        },
+        api::close_order_request(
+            symbol,
+            side,
+            execution_type,
+            price.clone(),
+            SettlePosition {
+                position_id,
+                size: size.clone(),
+            },
+        ),
+    )
+}
+
+pub fn cancel_all(
+    client: &GmoCoinClient,
+    format: Format,
+    symbols: Vec<Symbol>,
+    confirm: Confirm,
+) -> Result<()> {
+    let names = symbols
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+
+    execute(
+        client,
+        format,
+        &confirm,
+        || Ok(vec![("cancel all orders of", names.clone())]),
+        api::cancel_bulk_order_request(symbols.clone()),
+    )
+}
+
+/// 確認してからリクエストを送る
+///
+/// 署名には時刻が含まれるので、確認を待ってから署名する。
+fn execute<S>(
+    client: &GmoCoinClient,
+    format: Format,
+    confirm: &Confirm,
+    summary: S,
+    request: UnsignedRequest,
+) -> Result<()>
+where
+    S: FnOnce() -> Result<Vec<(&'static str, String)>>,
+{
+    if client.is_read_only() {
+        return Err(GmoCoinError::ReadOnly(request.path).into());
+    }
+
+    // dry-runでも約定代金とリスクの確認は本番と同じにする
+    let summary = summary()?;
+    let width = summary.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    for (key, value) in &summary {
+        eprintln!("{:<width$}  {}", key, value, width = width);
+    }
+
+    if confirm.dry_run {
+        return output::print(format, &[DryRun::from(client.sign(request)?)]);
+    }
+
+    if !confirm.yes && !ask("send? [y/N] ")? {
+        bail!("aborted");
+    }
+
+    let resp: Value = client.call(request)?;
+    output::print(format, &[resp])
+}
+
+/// 約定代金の目安。成行注文は最新の約定価格で計算する
+fn notional(
+    client: &GmoCoinClient,
+    symbol: Symbol,
+    price: Option<&str>,
+    size: &str,
+) -> Result<Decimal> {
+    let price = match price {
+        Some(price) => Decimal::from_str(price)?,
+        None => {
+            let rate = client
+                .ticker(Some(symbol))?
+                .data
+                .into_iter()
+                .next()
+                .with_context(|| format!("no ticker for {}", symbol))?;
+            Decimal::from_str(&rate.last)?
+        }
+    };
+
+    Ok((price * Decimal::from_str(size)?).normalize())
+}
+
+fn ask(prompt: &str) -> Result<bool> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
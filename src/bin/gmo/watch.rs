@@ -0,0 +1,502 @@
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use gmo_coin::orderbook::{OrderBook, OrderBookUpdate};
+use gmo_coin::public::api::{LatestRate, Snapshot, Trade};
+use gmo_coin::public::websock_api::{Channel, Message, PublicStream};
+use gmo_coin::stream::{Backoff, Resync, StreamEvent};
+use gmo_coin::{GmoCoinClient, Response, Symbol};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use time::format_description::well_known::Rfc3339;
+
+use crate::output::{self, Format};
+use crate::public::book_rows;
+
+/// 再接続の待ち時間の上限
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// この回数続けて再接続に失敗したらRESTのポーリングに切り替える
+const MAX_RECONNECTS: u32 = 5;
+/// RESTのポーリングに切り替えてからWebSocketを試し直すまでの時間
+const WEBSOCKET_RETRY: Duration = Duration::from_secs(60);
+/// 画面に残す約定の数
+const TRADES_SHOWN: usize = 20;
+/// ポーリングで1回に取得する約定の数(`trades`の上限)
+const TRADES_PAGE: usize = 100;
+/// 前回の約定までさかのぼるときに取得するページ数の上限
+const MAX_TRADE_PAGES: usize = 10;
+
+#[derive(Debug, Subcommand)]
+pub enum WatchCommand {
+    /// 最新レート
+    Ticker {
+        symbol: Option<Symbol>,
+        #[command(flatten)]
+        options: WatchOptions,
+    },
+    /// 約定
+    Trades {
+        symbol: Option<Symbol>,
+        #[command(flatten)]
+        options: WatchOptions,
+    },
+    /// 板情報
+    Book {
+        symbol: Option<Symbol>,
+        /// 表示する価格帯の数
+        #[arg(long, default_value_t = 10)]
+        depth: usize,
+        #[command(flatten)]
+        options: WatchOptions,
+    },
+}
+
+/// `watch`に共通のオプション
+#[derive(Debug, Args)]
+pub struct WatchOptions {
+    /// 画面を書き換えず、1イベント1行で出力する
+    #[arg(long)]
+    compact: bool,
+    /// WebSocketを使わずにRESTをポーリングする
+    #[arg(long)]
+    poll: bool,
+    /// ポーリングの間隔(秒)
+    #[arg(long, default_value_t = 1)]
+    interval: u64,
+}
+
+/// 板の最良気配
+#[derive(Debug, Serialize)]
+struct BookTop {
+    timestamp: String,
+    bid: String,
+    ask: String,
+    spread: String,
+    mid: String,
+}
+
+pub enum Event {
+    Ticker(LatestRate),
+    Trade(Trade),
+    BookUpdate(OrderBookUpdate),
+    BookSnapshot(Response<Snapshot>),
+}
+
+impl From<Message> for Event {
+    fn from(message: Message) -> Self {
+        match message {
+            Message::Ticker(update) => Event::Ticker(update.into()),
+            Message::Trade(update) => Event::Trade(update.into()),
+            Message::OrderBook(update) => Event::BookUpdate(update),
+        }
+    }
+}
+
+pub fn run(client: GmoCoinClient, format: Format, command: WatchCommand) -> Result<()> {
+    let (symbol, channel, depth, options) = match command {
+        WatchCommand::Ticker { symbol, options } => (symbol, Channel::Ticker, 0, options),
+        WatchCommand::Trades { symbol, options } => (symbol, Channel::Trades, 0, options),
+        WatchCommand::Book {
+            symbol,
+            depth,
+            options,
+        } => (symbol, Channel::OrderBooks, depth, options),
+    };
+
+    let symbol = symbol
+        .or(client.get_default_symbol())
+        .context("SYMBOL is required unless the profile has a default_symbol")?;
+    let mut feed = Feed::open(
+        Arc::new(client),
+        symbol,
+        &[channel],
+        options.poll,
+        Duration::from_secs(options.interval),
+    );
+    let mut view = View {
+        format,
+        compact: options.compact,
+        channel,
+        depth,
+        book: OrderBook::new(),
+        trades: VecDeque::new(),
+    };
+
+    loop {
+        let events = feed.next();
+        for notice in feed.take_notices() {
+            eprintln!("{}", notice);
+        }
+        match events {
+            Ok(events) => view.show(events)?,
+            Err(e) => eprintln!("error: {:#}", e),
+        }
+    }
+}
+
+enum Source {
+    Stream(Box<PublicStream>),
+    Poll,
+}
+
+/// WebSocketまたはRESTのポーリングから届くイベント
+pub struct Feed {
+    client: Arc<GmoCoinClient>,
+    symbol: Symbol,
+    channels: Vec<Channel>,
+    interval: Duration,
+    source: Source,
+    /// `Disconnected`のあと、まだ`Reconnected`が来ていない
+    disconnected: bool,
+    /// ポーリング中にWebSocketを試し直す時刻。`--poll`なら`None`
+    retry_at: Option<Instant>,
+    last_poll: Option<Instant>,
+    last_trade: Option<String>,
+    /// `last_trade`と同じ時刻に受け取った約定の数
+    seen_at_last: usize,
+    notices: Vec<String>,
+}
+
+impl Feed {
+    pub fn open(
+        client: Arc<GmoCoinClient>,
+        symbol: Symbol,
+        channels: &[Channel],
+        poll: bool,
+        interval: Duration,
+    ) -> Self {
+        let mut feed = Self {
+            client,
+            symbol,
+            channels: channels.to_vec(),
+            interval,
+            source: Source::Poll,
+            disconnected: false,
+            retry_at: None,
+            last_poll: None,
+            last_trade: None,
+            seen_at_last: 0,
+            notices: Vec::new(),
+        };
+
+        if !poll {
+            feed.connect();
+        }
+
+        feed
+    }
+
+    /// WebSocketに接続する。つながらなければしばらくRESTをポーリングする
+    fn connect(&mut self) {
+        match subscribe(&self.client, self.symbol, &self.channels) {
+            Ok(stream) => {
+                self.source = Source::Stream(Box::new(stream));
+                self.disconnected = false;
+                self.retry_at = None;
+            }
+            Err(e) => self.fall_back(format!("websocket unavailable ({})", e)),
+        }
+    }
+
+    fn fall_back(&mut self, reason: String) {
+        self.notices
+            .push(format!("{}, polling REST instead", reason));
+        self.source = Source::Poll;
+        self.retry_at = Some(Instant::now() + WEBSOCKET_RETRY);
+    }
+
+    /// 接続の切り替えなど、表示側に伝える出来事を取り出す
+    pub fn take_notices(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.notices)
+    }
+
+    pub fn next(&mut self) -> Result<Vec<Event>> {
+        if let Some(retry_at) = self.retry_at
+            && Instant::now() >= retry_at
+        {
+            self.connect();
+            if let Source::Stream(_) = self.source {
+                self.notices.push("websocket restored".to_string());
+            }
+        }
+
+        if let Source::Stream(stream) = &mut self.source {
+            match stream.next_event() {
+                Ok(StreamEvent::Message(message)) => return Ok(vec![message.into()]),
+                Ok(StreamEvent::Disconnected(e)) => {
+                    self.disconnected = true;
+                    self.notices
+                        .push(format!("websocket error ({}), reconnecting", e));
+                    return Ok(Vec::new());
+                }
+                Ok(StreamEvent::Reconnected { attempts }) => {
+                    self.disconnected = false;
+                    self.notices
+                        .push(format!("reconnected after {} attempt(s)", attempts));
+                    return Ok(Vec::new());
+                }
+                Ok(StreamEvent::Resync(Resync::OrderBook(_, resp))) => {
+                    return Ok(vec![Event::BookSnapshot(resp)]);
+                }
+                Ok(StreamEvent::Resync(_)) => return Ok(Vec::new()),
+                Ok(StreamEvent::ResyncFailed(e)) => {
+                    self.notices.push(format!("resync failed: {}", e));
+                    return Ok(Vec::new());
+                }
+                Ok(StreamEvent::Stale(stale)) => {
+                    self.notices.push(format!(
+                        "no {} for {}s",
+                        stale.channel,
+                        stale.silent.as_secs()
+                    ));
+                    return Ok(Vec::new());
+                }
+                Ok(StreamEvent::ActionFailed(e)) => {
+                    self.notices.push(format!("watchdog action failed: {}", e));
+                    return Ok(Vec::new());
+                }
+                // 再接続を使い切ったときだけRESTに切り替える
+                Err(e) if self.disconnected => {
+                    self.fall_back(format!("reconnect failed ({})", e));
+                    return Ok(Vec::new());
+                }
+                // 読めないメッセージやAPIのエラー(ERR-5003など)では接続はそのまま
+                Err(e) => {
+                    self.notices.push(format!("websocket error: {}", e));
+                    return Ok(Vec::new());
+                }
+            }
+        }
+
+        self.poll()
+    }
+
+    fn poll(&mut self) -> Result<Vec<Event>> {
+        if let Some(elapsed) = self.last_poll.map(|at| at.elapsed())
+            && elapsed < self.interval
+        {
+            thread::sleep(self.interval - elapsed);
+        }
+        self.last_poll = Some(Instant::now());
+
+        let mut events = Vec::new();
+        for channel in self.channels.clone() {
+            events.extend(self.poll_channel(channel)?);
+        }
+        Ok(events)
+    }
+
+    fn poll_channel(&mut self, channel: Channel) -> Result<Vec<Event>> {
+        Ok(match channel {
+            Channel::Ticker => self
+                .client
+                .ticker(Some(self.symbol))?
+                .data
+                .into_iter()
+                .map(Event::Ticker)
+                .collect(),
+            Channel::OrderBooks => vec![Event::BookSnapshot(self.client.orderbooks(self.symbol)?)],
+            Channel::Trades => self.poll_trades()?.into_iter().map(Event::Trade).collect(),
+        })
+    }
+
+    /// 前回から増えた約定を古い順に返す
+    ///
+    /// 前回の約定より古いものが見えるまでページをさかのぼる。
+    fn poll_trades(&mut self) -> Result<Vec<Trade>> {
+        let mut fetched: Vec<Trade> = Vec::new();
+        for page in 1..=MAX_TRADE_PAGES {
+            let list = self
+                .client
+                .trades(self.symbol, Some(page), Some(TRADES_PAGE))?
+                .data
+                .list;
+            let full = list.len() == TRADES_PAGE;
+            fetched.extend(list);
+            let reached = match &self.last_trade {
+                None => true,
+                Some(last) => fetched.last().is_some_and(|trade| trade.timestamp < *last),
+            };
+            if reached || !full {
+                break;
+            }
+            if page == MAX_TRADE_PAGES {
+                self.notices
+                    .push("trades: too many new trades, some were skipped".to_string());
+            }
+        }
+
+        let new = unseen(&fetched, self.last_trade.as_deref(), self.seen_at_last);
+        if let Some(newest) = fetched.first() {
+            let newest = newest.timestamp.clone();
+            self.seen_at_last = fetched
+                .iter()
+                .filter(|trade| trade.timestamp == newest)
+                .count();
+            self.last_trade = Some(newest);
+        }
+        fetched.truncate(new);
+        fetched.reverse();
+        Ok(fetched)
+    }
+}
+
+/// 新しい順の`trades`のうち、まだ受け取っていない先頭からの数
+///
+/// 同じ時刻の約定は中身が同じでも別の約定なので、`last`の時刻の約定は
+/// 前回受け取った`seen_at_last`件を古い方から除いて数える。
+/// 初回は画面に残す分だけにする。
+fn unseen(trades: &[Trade], last: Option<&str>, seen_at_last: usize) -> usize {
+    let Some(last) = last else {
+        return trades.len().min(TRADES_SHOWN);
+    };
+    let newer = trades
+        .iter()
+        .filter(|trade| trade.timestamp.as_str() > last)
+        .count();
+    let at_last = trades
+        .iter()
+        .filter(|trade| trade.timestamp == last)
+        .count();
+    newer + at_last.saturating_sub(seen_at_last)
+}
+
+fn subscribe(
+    client: &Arc<GmoCoinClient>,
+    symbol: Symbol,
+    channels: &[Channel],
+) -> gmo_coin::GmoCoinResult<PublicStream> {
+    let mut stream = PublicStream::connect_with(client.public_ws_url(), client.get_http_options())?
+        .metrics(client.get_metrics())
+        .backoff(Backoff {
+            max: MAX_BACKOFF,
+            max_attempts: Some(MAX_RECONNECTS),
+            ..Default::default()
+        })
+        .resync(client.clone());
+    for channel in channels {
+        stream.subscribe(symbol, *channel)?;
+    }
+    Ok(stream)
+}
+
+struct View {
+    format: Format,
+    compact: bool,
+    channel: Channel,
+    depth: usize,
+    book: OrderBook,
+    trades: VecDeque<Trade>,
+}
+
+impl View {
+    fn show(&mut self, events: Vec<Event>) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        for event in events {
+            match event {
+                Event::Ticker(rate) => {
+                    if self.compact {
+                        output::line(self.format, &rate)?;
+                    } else {
+                        clear()?;
+                        output::print(self.format, &[rate])?;
+                    }
+                }
+                Event::Trade(trade) => {
+                    if self.compact {
+                        output::line(self.format, &trade)?;
+                    } else {
+                        self.trades.push_front(trade);
+                        self.trades.truncate(TRADES_SHOWN);
+                    }
+                }
+                Event::BookUpdate(update) => {
+                    if self.book.apply_update(&update)? && self.compact {
+                        output::line(self.format, &self.top())?;
+                    }
+                }
+                Event::BookSnapshot(resp) => {
+                    if self.book.apply_response(&resp)? && self.compact {
+                        output::line(self.format, &self.top())?;
+                    }
+                }
+            }
+        }
+
+        if !self.compact {
+            match self.channel {
+                Channel::Ticker => {}
+                Channel::Trades => {
+                    clear()?;
+                    output::print(self.format, self.trades.make_contiguous())?;
+                }
+                Channel::OrderBooks => {
+                    clear()?;
+                    output::print(self.format, &book_rows(&self.book, self.depth))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn top(&self) -> BookTop {
+        let show = |value: Option<String>| value.unwrap_or_default();
+        BookTop {
+            timestamp: show(self.book.timestamp().and_then(|t| t.format(&Rfc3339).ok())),
+            bid: show(self.book.best_bid().map(|level| level.price.to_string())),
+            ask: show(self.book.best_ask().map(|level| level.price.to_string())),
+            spread: show(self.book.spread().map(|spread| spread.to_string())),
+            mid: show(self.book.mid().map(|mid| mid.normalize().to_string())),
+        }
+    }
+}
+
+/// 画面を消してカーソルを左上に戻す
+fn clear() -> Result<()> {
+    print!("\x1b[2J\x1b[H");
+    io::stdout().flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(timestamp: &str) -> Trade {
+        Trade {
+            price: "100".to_string(),
+            side: "BUY".to_string(),
+            size: "1".to_string(),
+            timestamp: timestamp.to_string(),
+        }
+    }
+
+    #[test]
+    fn identical_trades_at_the_last_timestamp_are_counted() {
+        // 前回は00:00:01.000の約定を1件受け取った
+        let trades = [
+            trade("2024-01-01T00:00:02.000Z"),
+            trade("2024-01-01T00:00:01.000Z"),
+            trade("2024-01-01T00:00:01.000Z"),
+            trade("2024-01-01T00:00:00.000Z"),
+        ];
+        assert_eq!(unseen(&trades, Some("2024-01-01T00:00:01.000Z"), 1), 2);
+        assert_eq!(unseen(&trades, Some("2024-01-01T00:00:01.000Z"), 2), 1);
+        assert_eq!(unseen(&trades, Some("2024-01-01T00:00:02.000Z"), 1), 0);
+    }
+
+    #[test]
+    fn first_poll_keeps_what_is_shown() {
+        let trades: Vec<Trade> = (0..TRADES_PAGE)
+            .map(|_| trade("2024-01-01T00:00:00.000Z"))
+            .collect();
+        assert_eq!(unseen(&trades, None, 0), TRADES_SHOWN);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{
+    JST,
+    error::Result,
+    public::api::{DAY_ROLLOVER, Trade},
+};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use time::{Duration, OffsetDateTime, PrimitiveDateTime, format_description::well_known::Rfc3339};
+
+/// ## Candle
+/// 約定履歴から組み立てたローソク足
+///
+/// `close_time`は足の終了時刻で、この時刻ちょうどの約定は次の足に入る。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub open_time: OffsetDateTime,
+    pub close_time: OffsetDateTime,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+    pub trades: usize,
+}
+
+impl Candle {
+    fn new(open_time: OffsetDateTime, close_time: OffsetDateTime, price: Decimal) -> Self {
+        Self {
+            open_time,
+            close_time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: Decimal::ZERO,
+            trades: 0,
+        }
+    }
+
+    /// `latest`でなければ、より新しい約定の`close`を残す
+    fn add(&mut self, price: Decimal, size: Decimal, latest: bool) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        if latest {
+            self.close = price;
+        }
+        self.volume += size;
+        self.trades += 1;
+    }
+}
+
+/// ## CandleEvent
+/// `CandleAggregator`が返すイベント
+#[derive(Debug, Clone, PartialEq)]
+pub enum CandleEvent {
+    /// 形成中の足が更新された
+    Updated(Candle),
+    /// 足が確定した
+    Closed(Candle),
+}
+
+/// ## CandleAggregator
+/// 約定履歴(`Trade`)から任意の間隔のローソク足を作る
+///
+/// 足は`klines`と揃うよう、営業日の切り替わり(JST 6時)を起点に区切る。
+/// 1日より短い足は1日を割り切れない間隔でも営業日をまたぐ足は作らず、その日の最後の足は6時で打ち切る。
+/// 1日以上の足はJSTの1970-01-01 6時を起点にする。
+/// 約定の無かった期間の足は作らない。
+#[derive(Debug)]
+pub struct CandleAggregator {
+    interval: Duration,
+    current: Option<Candle>,
+    /// 形成中の足で最も新しい約定の時刻
+    last_trade: Option<OffsetDateTime>,
+}
+
+impl CandleAggregator {
+    /// ### new
+    /// `interval`はミリ秒単位で扱う。1ミリ秒未満の間隔を渡すとpanicする。
+    pub fn new(interval: std::time::Duration) -> Self {
+        let interval = Duration::milliseconds(interval.as_millis() as i64);
+        assert!(
+            interval.is_positive(),
+            "candle interval must be at least 1ms"
+        );
+        Self {
+            interval,
+            current: None,
+            last_trade: None,
+        }
+    }
+
+    /// ### aggregate
+    /// 取得済みの約定履歴をまとめて足にする
+    ///
+    /// RESTの`trades`は新しい順に返ってくるので、時刻順に並べ替えてから集計する。
+    /// 最後の足も確定したものとして返す。
+    pub fn aggregate(interval: std::time::Duration, trades: &[Trade]) -> Result<Vec<Candle>> {
+        let mut parsed = trades.iter().map(parse_trade).collect::<Result<Vec<_>>>()?;
+        parsed.sort_by_key(|(timestamp, _, _)| *timestamp);
+
+        let mut aggregator = Self::new(interval);
+        let mut candles = Vec::new();
+        for (timestamp, price, size) in parsed {
+            candles.extend(
+                aggregator
+                    .apply(timestamp, price, size)
+                    .into_iter()
+                    .filter_map(|event| match event {
+                        CandleEvent::Closed(candle) => Some(candle),
+                        CandleEvent::Updated(_) => None,
+                    }),
+            );
+        }
+        candles.extend(aggregator.current.take());
+
+        Ok(candles)
+    }
+
+    pub fn current(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+
+    /// ### push
+    /// 約定を1件取り込む
+    ///
+    /// 足が切り替わった場合は確定した足の`Closed`に続けて新しい足の`Updated`を返す。
+    /// 形成中の足より古い約定は確定済みの足を変えられないので無視する。
+    /// 形成中の足の中で順番が前後した約定は、高値・安値・出来高には入れるが`close`は変えない。
+    pub fn push(&mut self, trade: &Trade) -> Result<Vec<CandleEvent>> {
+        let (timestamp, price, size) = parse_trade(trade)?;
+        Ok(self.apply(timestamp, price, size))
+    }
+
+    /// ### close_until
+    /// `now`までに終了時刻を迎えた形成中の足を確定させる
+    ///
+    /// 約定が途切れた場合でも足を確定させたいときに定期的に呼ぶ。
+    pub fn close_until(&mut self, now: OffsetDateTime) -> Option<CandleEvent> {
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|candle| candle.close_time <= now)
+        {
+            return self.current.take().map(CandleEvent::Closed);
+        }
+
+        None
+    }
+
+    fn apply(
+        &mut self,
+        timestamp: OffsetDateTime,
+        price: Decimal,
+        size: Decimal,
+    ) -> Vec<CandleEvent> {
+        let mut events = Vec::new();
+
+        if let Some(candle) = &self.current {
+            if timestamp < candle.open_time {
+                return events;
+            }
+            if timestamp >= candle.close_time {
+                events.extend(self.current.take().map(CandleEvent::Closed));
+            }
+        }
+
+        if self.current.is_none() {
+            self.last_trade = None;
+        }
+        let latest = self.last_trade.is_none_or(|last| timestamp >= last);
+        if latest {
+            self.last_trade = Some(timestamp);
+        }
+        let candle = self.current.get_or_insert_with(|| {
+            let (open_time, close_time) = bucket(timestamp, self.interval);
+            Candle::new(open_time, close_time, price)
+        });
+        candle.add(price, size, latest);
+        events.push(CandleEvent::Updated(candle.clone()));
+
+        events
+    }
+}
+
+/// 約定時刻を含む足の開始時刻と終了時刻
+fn bucket(timestamp: OffsetDateTime, interval: Duration) -> (OffsetDateTime, OffsetDateTime) {
+    let local = timestamp.to_offset(JST);
+    let step = interval.whole_milliseconds();
+
+    if interval >= Duration::DAY {
+        let origin = PrimitiveDateTime::new(time::macros::date!(1970 - 01 - 01), DAY_ROLLOVER)
+            .assume_offset(JST);
+        let elapsed = (local - origin).whole_milliseconds();
+        let open_time =
+            origin + Duration::milliseconds((elapsed - elapsed.rem_euclid(step)) as i64);
+        return (open_time, open_time + interval);
+    }
+
+    // 6時より前は前の営業日
+    let rollover = local.replace_time(DAY_ROLLOVER);
+    let day_start = if local < rollover {
+        rollover - Duration::DAY
+    } else {
+        rollover
+    };
+    let elapsed = (local - day_start).whole_milliseconds();
+    let open_time = day_start + Duration::milliseconds((elapsed - elapsed % step) as i64);
+    let close_time = (open_time + interval).min(day_start + Duration::DAY);
+
+    (open_time, close_time)
+}
+
+fn parse_trade(trade: &Trade) -> Result<(OffsetDateTime, Decimal, Decimal)> {
+    Ok((
+        OffsetDateTime::parse(&trade.timestamp, &Rfc3339)?,
+        Decimal::from_str(&trade.price)?,
+        Decimal::from_str(&trade.size)?,
+    ))
+}
@@ -0,0 +1,1129 @@
+use crate::{
+    Ack, Response, ResponseList, ResponsePage, Symbol,
+    clock::{self, Clock, TIMESTAMP_CODES},
+    config::{Config, Limits, Profile},
+    credentials::{CredentialProvider, Credentials, EnvProvider, Secret},
+    endpoint,
+    error::{GmoCoinError, Result},
+    http::{self, HttpOptions, Proxy, TlsOptions},
+    metrics::Metrics,
+    private::api::{
+        self as private_api, ActiveOrders, Assets, Execution, ExecutionType, ExecutionsParam,
+        LatestExecutions, Margin, OpenPositions, OrderInfo, PositionSummary, SettlePosition,
+        SignedRequest, UnsignedRequest, UreqResponse,
+    },
+    private::risk::{self, OrderCheck, Rejection, RiskAudit, RiskViolation},
+    public::api::{
+        ExchangeStatus, Kline, KlineDate, KlineInterval, LatestRate, Snapshot, SymbolRule,
+        TradesList,
+    },
+    signer::{HmacSigner, Signer},
+    status::{ExchangeState, ExchangeStatusTracker, MAINTENANCE_CODES, MaintenancePolicy},
+    trace::{self, RequestLog},
+};
+use crate::{LeverageSymbol, Side};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+use ureq::http::{HeaderMap, header::HeaderValue};
+
+/// ## RateLimitTier
+/// 取引高に応じたAPIの呼び出し制限
+///
+/// GET・POSTそれぞれ、Tier1は毎秒20回、Tier2は毎秒30回まで。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitTier {
+    #[default]
+    Tier1,
+    Tier2,
+}
+
+impl RateLimitTier {
+    pub fn requests_per_second(self) -> u32 {
+        match self {
+            RateLimitTier::Tier1 => 20,
+            RateLimitTier::Tier2 => 30,
+        }
+    }
+}
+
+impl TryFrom<u8> for self::RateLimitTier {
+    type Error = GmoCoinError;
+    fn try_from(tier: u8) -> Result<Self> {
+        match tier {
+            1 => Ok(RateLimitTier::Tier1),
+            2 => Ok(RateLimitTier::Tier2),
+            _ => Err(GmoCoinError::Config(format!(
+                "rate_limit_tier must be 1 or 2, got {}",
+                tier
+            ))),
+        }
+    }
+}
+
+/// 呼び出しの間隔を空ける
+#[derive(Debug)]
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(tier: RateLimitTier) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / tier.requests_per_second(),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// 待った場合は`true`を返す
+    fn wait(&self) -> bool {
+        let mut next = self.next.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let waited = *next > now;
+        if waited {
+            thread::sleep(*next - now);
+        }
+        *next = now.max(*next) + self.interval;
+        waited
+    }
+}
+
+/// 署名に使うAPIキーと`Signer`
+#[derive(Debug, Clone)]
+struct Resolved {
+    api_key: Secret,
+    signer: Arc<dyn Signer>,
+}
+
+/// ## GmoCoinClient
+/// 認証情報・接続先・タイムアウトなどの設定を持つクライアント
+///
+/// `from_profile`で設定ファイルのプロファイルから作る。
+/// 認証情報は最初に署名するときに取得する。
+#[derive(Debug)]
+pub struct GmoCoinClient {
+    provider: Box<dyn CredentialProvider>,
+    signer: Option<Arc<dyn Signer>>,
+    resolved: Mutex<Option<Resolved>>,
+    /// `false`なら署名のたびに認証情報を取得し直す
+    cache_credentials: bool,
+    public_api: String,
+    private_api: String,
+    public_ws: String,
+    private_ws: String,
+    http: HttpOptions,
+    agent: ureq::Agent,
+    rate_limit_tier: RateLimitTier,
+    get_limiter: RateLimiter,
+    post_limiter: RateLimiter,
+    default_symbol: Option<Symbol>,
+    limits: Limits,
+    audit: Mutex<RiskAudit>,
+    read_only: bool,
+    metrics: Arc<Metrics>,
+    exchange: Arc<ExchangeStatusTracker>,
+    maintenance: MaintenancePolicy,
+    maintenance_poll: Duration,
+    clock: Clock,
+}
+
+impl Default for GmoCoinClient {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+/// 関数版のAPIが共有する`from_env`のクライアント
+///
+/// 呼び出し制限・メトリクス・メンテナンスの状態・時計のずれを呼び出しをまたいで引き継ぐ。
+/// 環境変数でキーを入れ替えられるよう、認証情報は署名のたびに読み直す。
+pub(crate) fn shared_client() -> &'static GmoCoinClient {
+    static CLIENT: OnceLock<GmoCoinClient> = OnceLock::new();
+    CLIENT.get_or_init(|| GmoCoinClient {
+        cache_credentials: false,
+        ..GmoCoinClient::from_env()
+    })
+}
+
+impl GmoCoinClient {
+    /// ### from_env
+    /// 本番の接続先と、`GMO_COIN_API_KEY`・`GMO_COIN_SECRET_KEY`を使う
+    pub fn from_env() -> Self {
+        Self {
+            provider: Box::new(EnvProvider::default()),
+            signer: None,
+            resolved: Mutex::new(None),
+            cache_credentials: true,
+            public_api: endpoint::PUBLIC_API.to_string(),
+            private_api: endpoint::PRIVATE_API.to_string(),
+            public_ws: endpoint::PUBLIC_WEBSOCK_API.to_string(),
+            private_ws: endpoint::PRIVATE_WEBSOCK_API.to_string(),
+            http: HttpOptions::default(),
+            agent: http::shared_agent(),
+            rate_limit_tier: RateLimitTier::Tier1,
+            get_limiter: RateLimiter::new(RateLimitTier::Tier1),
+            post_limiter: RateLimiter::new(RateLimitTier::Tier1),
+            default_symbol: None,
+            limits: Limits::default(),
+            audit: Mutex::new(RiskAudit::default()),
+            read_only: false,
+            metrics: Arc::new(Metrics::new()),
+            exchange: Arc::new(ExchangeStatusTracker::new()),
+            maintenance: MaintenancePolicy::Ignore,
+            maintenance_poll: Duration::from_secs(10),
+            clock: Clock::default(),
+        }
+    }
+
+    /// ### from_profile
+    /// 既定の設定ファイルから名前を指定してプロファイルを読む
+    pub fn from_profile(name: &str) -> Result<Self> {
+        Self::from_config(&Config::load_default()?, Some(name))
+    }
+
+    /// ### from_config
+    /// 設定からプロファイルを選んでクライアントを作る
+    ///
+    /// プロファイルの選び方は`Config::profile`と同じ。
+    pub fn from_config(config: &Config, profile: Option<&str>) -> Result<Self> {
+        Self::with_profile(&config.profile(profile)?)
+    }
+
+    /// ### with_profile
+    /// プロファイルからクライアントを作る
+    ///
+    /// `GMO_COIN_API_KEY`・`GMO_COIN_SECRET_KEY`が設定されていれば、プロファイルより優先する。
+    pub fn with_profile(profile: &Profile) -> Result<Self> {
+        let mut client = Self::from_env().credential_provider(profile.credential_provider());
+        if let Some(path) = &profile.signer {
+            client = client.signer(unix_socket_signer(path)?);
+        }
+
+        if let Some(url) = &profile.public_api {
+            client.public_api = url.clone();
+        }
+        if let Some(url) = &profile.private_api {
+            client.private_api = url.clone();
+        }
+        if let Some(url) = &profile.public_ws {
+            client.public_ws = url.clone();
+        }
+        if let Some(url) = &profile.private_ws {
+            client.private_ws = url.clone();
+        }
+        if let Some(tier) = profile.rate_limit_tier {
+            client = client.rate_limit_tier(RateLimitTier::try_from(tier)?);
+        }
+        if let Some(symbol) = &profile.default_symbol {
+            client.default_symbol = Some(Symbol::from_str(symbol).map_err(|_| {
+                GmoCoinError::Config(format!("unknown default_symbol: {}", symbol))
+            })?);
+        }
+        if let Some(timeout) = profile.timeout {
+            client = client.timeout(Duration::from_secs(timeout));
+        }
+        if let Some(timeout) = profile.connect_timeout {
+            client = client.connect_timeout(Duration::from_secs(timeout));
+        }
+        if let Some(timeout) = profile.read_timeout {
+            client = client.read_timeout(Duration::from_secs(timeout));
+        }
+        if let Some(user_agent) = &profile.user_agent {
+            client = client.user_agent(user_agent);
+        }
+        if let Some(proxy) = &profile.proxy {
+            client = client.proxy(proxy.clone());
+        }
+        if !profile.ca_certs.is_empty() || !profile.pinned_keys.is_empty() {
+            client = client.tls(profile.tls_options()?);
+        }
+        client = client.limits(profile.limits.clone());
+        client.read_only = profile.read_only;
+
+        Ok(client)
+    }
+
+    pub fn credentials(self, api_key: &str, secret_key: &str) -> Self {
+        self.credential_provider(Credentials::new(api_key, secret_key))
+    }
+
+    /// ### credential_provider
+    /// 認証情報の取得元を差し替える
+    pub fn credential_provider<P: CredentialProvider + 'static>(mut self, provider: P) -> Self {
+        self.provider = Box::new(provider);
+        *self
+            .resolved
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+        self
+    }
+
+    /// ### signer
+    /// 署名を外部に任せる
+    ///
+    /// 指定するとシークレットキーは読まず、`CredentialProvider::api_key`だけを使う。
+    pub fn signer<S: Signer + 'static>(mut self, signer: S) -> Self {
+        self.signer = Some(Arc::new(signer));
+        *self
+            .resolved
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+        self
+    }
+
+    pub fn public_api(mut self, url: &str) -> Self {
+        self.public_api = url.to_string();
+        self
+    }
+
+    pub fn private_api(mut self, url: &str) -> Self {
+        self.private_api = url.to_string();
+        self
+    }
+
+    pub fn public_ws(mut self, url: &str) -> Self {
+        self.public_ws = url.to_string();
+        self
+    }
+
+    pub fn private_ws(mut self, url: &str) -> Self {
+        self.private_ws = url.to_string();
+        self
+    }
+
+    /// ### timeout
+    /// 1リクエスト全体のタイムアウト
+    pub fn timeout(self, timeout: Duration) -> Self {
+        let options = HttpOptions {
+            timeout: Some(timeout),
+            ..self.http.clone()
+        };
+        self.http_options(options)
+    }
+
+    /// ### connect_timeout
+    /// 接続のタイムアウト。既定は10秒
+    pub fn connect_timeout(self, timeout: Duration) -> Self {
+        let options = HttpOptions {
+            connect_timeout: Some(timeout),
+            ..self.http.clone()
+        };
+        self.http_options(options)
+    }
+
+    /// ### read_timeout
+    /// レスポンスを待つ・読むタイムアウト。既定は30秒
+    pub fn read_timeout(self, timeout: Duration) -> Self {
+        let options = HttpOptions {
+            read_timeout: Some(timeout),
+            ..self.http.clone()
+        };
+        self.http_options(options)
+    }
+
+    pub fn user_agent(self, user_agent: &str) -> Self {
+        let options = HttpOptions {
+            user_agent: user_agent.to_string(),
+            ..self.http.clone()
+        };
+        self.http_options(options)
+    }
+
+    /// ### proxy
+    /// RESTとWebSocketの接続に使うプロキシ
+    pub fn proxy(self, proxy: Proxy) -> Self {
+        let options = HttpOptions {
+            proxy: Some(proxy),
+            ..self.http.clone()
+        };
+        self.http_options(options)
+    }
+
+    /// ### tls
+    /// 追加のルート証明書と証明書のピン留め
+    pub fn tls(self, tls: TlsOptions) -> Self {
+        let options = HttpOptions {
+            tls,
+            ..self.http.clone()
+        };
+        self.http_options(options)
+    }
+
+    /// ### http_options
+    /// HTTPの設定をまとめて変える
+    ///
+    /// 既定の設定のクライアントは接続を共有する。設定を変えると、このクライアント用の接続になる。
+    pub fn http_options(mut self, options: HttpOptions) -> Self {
+        self.agent = if options == HttpOptions::default() {
+            http::shared_agent()
+        } else {
+            options.agent()
+        };
+        self.http = options;
+        self
+    }
+
+    pub fn get_http_options(&self) -> &HttpOptions {
+        &self.http
+    }
+
+    pub fn rate_limit_tier(mut self, tier: RateLimitTier) -> Self {
+        self.rate_limit_tier = tier;
+        self.get_limiter = RateLimiter::new(tier);
+        self.post_limiter = RateLimiter::new(tier);
+        self
+    }
+
+    pub fn default_symbol(mut self, symbol: Symbol) -> Self {
+        self.default_symbol = Some(symbol);
+        self
+    }
+
+    /// ### limits
+    /// `order`・`close_order`・`change_order`の前に確認する上限
+    pub fn limits(mut self, limits: Limits) -> Self {
+        let path = limits
+            .audit_log
+            .as_deref()
+            .map(crate::credentials::expand_home);
+        self.audit = Mutex::new(RiskAudit::new(path));
+        self.limits = limits;
+        self
+    }
+
+    pub fn public_ws_url(&self) -> &str {
+        &self.public_ws
+    }
+
+    pub fn private_ws_url(&self) -> &str {
+        &self.private_ws
+    }
+
+    pub fn get_default_symbol(&self) -> Option<Symbol> {
+        self.default_symbol
+    }
+
+    pub fn get_limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// ### rejections
+    /// 上限を超えて拒否した注文の記録(古い順)
+    pub fn rejections(&self) -> Vec<Rejection> {
+        self.audit
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .rejections()
+    }
+
+    /// ### read_only
+    /// 状態を変えるリクエスト(POST)を署名する前に拒否する
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn get_rate_limit_tier(&self) -> RateLimitTier {
+        self.rate_limit_tier
+    }
+
+    /// ### metrics
+    /// 呼び出しを記録する先。複数のクライアントで共有する場合に渡す
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn get_metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// ### maintenance
+    /// メンテナンス中のPrivate APIの呼び出しを待たせるか、送らずにエラーにする
+    ///
+    /// メンテナンス中かどうかは`status`の結果とエラーコードで判断するので、
+    /// 定期的に確認するには`StatusWatcher`を使う。
+    /// `Wait`で待っている間は、`StatusWatcher`が無くても`maintenance_poll`ごとに`status`を取り直す。
+    pub fn maintenance(mut self, policy: MaintenancePolicy) -> Self {
+        self.maintenance = policy;
+        self
+    }
+
+    /// ### maintenance_poll
+    /// `MaintenancePolicy::Wait`で待っている間に`status`を取り直す間隔。既定は10秒
+    pub fn maintenance_poll(mut self, interval: Duration) -> Self {
+        self.maintenance_poll = interval;
+        self
+    }
+
+    /// ### exchange_status
+    /// 最後に分かった稼動状態
+    pub fn exchange_status(&self) -> Arc<ExchangeStatusTracker> {
+        self.exchange.clone()
+    }
+
+    /// ### clock_skew_warning
+    /// サーバーの時計とのずれがこれを超えたら警告する(`tracing`フィーチャ)。既定は1秒
+    pub fn clock_skew_warning(self, threshold: Duration) -> Self {
+        self.clock.set_warning(threshold);
+        self
+    }
+
+    /// ### clock_offset_ms
+    /// サーバーの時計から手元の時計を引いた推定値(ミリ秒)
+    ///
+    /// レスポンスを受け取るたびに更新し、`API-TIMESTAMP`に足す。
+    pub fn clock_offset_ms(&self) -> i64 {
+        self.clock.offset_ms()
+    }
+
+    /// ### sync_clock
+    /// `status`を呼んでずれを推定し直す
+    pub fn sync_clock(&self) -> Result<i64> {
+        self.status()?;
+        Ok(self.clock.offset_ms())
+    }
+
+    /// ### sign
+    /// リクエストに署名する
+    pub fn sign(&self, request: UnsignedRequest) -> Result<SignedRequest> {
+        self.check_writable(request.method, request.path)?;
+        let resolved = self.resolve()?;
+        SignedRequest::new(
+            request,
+            &self.private_api,
+            self.clock.now_ms()?,
+            &resolved.api_key,
+            resolved.signer.as_ref(),
+        )
+    }
+
+    /// ### send
+    /// 署名済みのリクエストを送信する
+    pub fn send(&self, request: &SignedRequest) -> Result<UreqResponse> {
+        self.check_writable(request.method, request.path)?;
+        self.check_maintenance()?;
+        self.wait(request.method);
+        let start = Instant::now();
+        let sent = clock::local_ms()?;
+        let result = self.dispatch(request).inspect(|response| {
+            let date = response
+                .headers()
+                .get("date")
+                .and_then(|date| date.to_str().ok());
+            if let Ok(received) = clock::local_ms() {
+                self.clock.observe(sent, received, None, date);
+            }
+        });
+        self.observe(request.method, request.path, start, result)
+    }
+
+    /// ### call
+    /// 署名して送信し、レスポンスを読む
+    ///
+    /// `status`が0でなければ`GmoCoinError::Api`を返す。
+    /// `API-TIMESTAMP`のずれで拒否された場合は、そのレスポンスで時計を合わせ直して一度だけ送り直す。
+    pub fn call<T: DeserializeOwned>(&self, request: UnsignedRequest) -> Result<T> {
+        self.check_maintenance()?;
+        match self.call_once(request.clone()) {
+            Err(GmoCoinError::Api { messages, .. })
+                if messages
+                    .iter()
+                    .any(|message| TIMESTAMP_CODES.contains(&message.message_code.as_str())) =>
+            {
+                self.metrics.retry(request.path);
+                self.call_once(request)
+            }
+            result => result,
+        }
+    }
+
+    fn call_once<T: DeserializeOwned>(&self, request: UnsignedRequest) -> Result<T> {
+        let signed = self.sign(request)?;
+        self.wait(signed.method);
+        let start = Instant::now();
+        let sent = clock::local_ms()?;
+        let log = RequestLog::signed(&signed);
+        let result = self
+            .dispatch(&signed)
+            .and_then(|response| self.parse_checked(response, &log, sent));
+        self.observe(signed.method, signed.path, start, log.finish(result))
+    }
+
+    fn wait(&self, method: &str) {
+        let waited = match method {
+            "POST" => self.post_limiter.wait(),
+            _ => self.get_limiter.wait(),
+        };
+        if waited {
+            self.metrics.rate_limit_wait(method);
+        }
+    }
+
+    fn observe<T>(&self, method: &str, path: &str, start: Instant, result: Result<T>) -> Result<T> {
+        self.metrics
+            .request(method, path, start.elapsed(), result.as_ref().err());
+        if let Err(GmoCoinError::Api { messages, .. }) = &result
+            && messages
+                .iter()
+                .any(|message| MAINTENANCE_CODES.contains(&message.message_code.as_str()))
+        {
+            self.exchange.update(ExchangeState::Maintenance);
+        }
+        result
+    }
+
+    /// `status`が0でなければ`messages`をエラーにする
+    ///
+    /// `responsetime`(なければ`Date`ヘッダ)で時計のずれを推定する。
+    fn parse_checked<T: DeserializeOwned>(
+        &self,
+        mut response: UreqResponse,
+        log: &RequestLog,
+        sent: u64,
+    ) -> Result<T> {
+        let http_status = response.status().as_u16();
+        let date = response
+            .headers()
+            .get("date")
+            .and_then(|date| date.to_str().ok())
+            .map(str::to_string);
+        let body = response.body_mut().read_to_string()?;
+        let received = clock::local_ms()?;
+        let value: Value = serde_json::from_str(&body)?;
+        log.response(http_status, body.len(), &value);
+        self.clock.observe(
+            sent,
+            received,
+            value["responsetime"].as_str(),
+            date.as_deref(),
+        );
+        if value["status"] != 0 {
+            return Err(GmoCoinError::Api {
+                status: value["status"].as_i64().unwrap_or(-1),
+                messages: serde_json::from_value(value["messages"].clone()).unwrap_or_default(),
+            });
+        }
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// 署名する前に待つので、待った後のタイムスタンプで署名する
+    fn check_maintenance(&self) -> Result<()> {
+        match self.maintenance {
+            MaintenancePolicy::Ignore => Ok(()),
+            MaintenancePolicy::FailFast if self.exchange.is_maintenance() => {
+                Err(GmoCoinError::Maintenance)
+            }
+            MaintenancePolicy::FailFast => Ok(()),
+            MaintenancePolicy::Wait { timeout } => {
+                let deadline = timeout.map(|timeout| Instant::now() + timeout);
+                loop {
+                    let wait = deadline.map_or(self.maintenance_poll, |deadline| {
+                        deadline
+                            .saturating_duration_since(Instant::now())
+                            .min(self.maintenance_poll)
+                    });
+                    if self.exchange.wait_until_available(Some(wait)) {
+                        return Ok(());
+                    }
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return Err(GmoCoinError::Maintenance);
+                    }
+                    // 状態を更新するものが他に無くても終わりに気づけるようにする
+                    let _ = self.status();
+                }
+            }
+        }
+    }
+
+    fn dispatch(&self, request: &SignedRequest) -> Result<UreqResponse> {
+        let url = request.url();
+        match request.method {
+            "POST" | "PUT" => {
+                let mut builder = if request.method == "POST" {
+                    self.agent.post(&url)
+                } else {
+                    self.agent.put(&url)
+                }
+                .content_type("application/json");
+                set_request_headers(&mut builder, request)?;
+                return Ok(builder.send(&request.body)?);
+            }
+            "DELETE" => {
+                let mut builder = self
+                    .agent
+                    .delete(&url)
+                    .force_send_body()
+                    .content_type("application/json");
+                set_request_headers(&mut builder, request)?;
+                return Ok(builder.send(&request.body)?);
+            }
+            _ => {}
+        }
+
+        let mut builder = self.agent.get(&url);
+        set_request_headers(&mut builder, request)?;
+        for (key, value) in &request.query {
+            builder = builder.query(key, value);
+        }
+
+        Ok(builder.call()?)
+    }
+
+    /// ### check_order
+    /// 新規注文が`limits`を超えないか確認する
+    ///
+    /// 必要な場合だけ最新の約定価格と建玉サマリを取得する。超えていれば記録して`GmoCoinError::Risk`を返す。
+    pub fn check_order(
+        &self,
+        symbol: Symbol,
+        side: Side,
+        execution_type: ExecutionType,
+        price: Option<&str>,
+        size: &str,
+    ) -> Result<()> {
+        self.check_risk(&symbol.to_string(), side, execution_type, price, size, true)
+    }
+
+    /// ### check_close_order
+    /// 決済注文が`limits`を超えないか確認する。建玉の上限は見ない
+    pub fn check_close_order(
+        &self,
+        symbol: LeverageSymbol,
+        side: Side,
+        execution_type: ExecutionType,
+        price: Option<&str>,
+        size: &str,
+    ) -> Result<()> {
+        self.check_risk(
+            &symbol.to_string(),
+            side,
+            execution_type,
+            price,
+            size,
+            false,
+        )
+    }
+
+    /// ### check_change_order
+    /// 変更後の価格で注文が`limits`を超えないか確認する
+    ///
+    /// 銘柄・売買区分・残りの数量は注文情報から取得する。
+    pub fn check_change_order(&self, order_id: usize, price: &str) -> Result<()> {
+        if self.limits.is_empty() {
+            return Ok(());
+        }
+
+        let order = self
+            .orders(order_id)?
+            .data
+            .list
+            .into_iter()
+            .next()
+            .ok_or(RiskViolation::UnknownOrder(order_id))?;
+        let remaining = risk::number("size", &order.size)?
+            - risk::number("executedSize", &order.executed_size)?;
+        let side =
+            Side::from_str(&order.side).map_err(|_| RiskViolation::UnknownOrder(order_id))?;
+        self.check_risk(
+            &order.symbol,
+            side,
+            ExecutionType::LIMIT,
+            Some(price),
+            &remaining.to_string(),
+            false,
+        )
+    }
+
+    fn check_risk(
+        &self,
+        symbol: &str,
+        side: Side,
+        execution_type: ExecutionType,
+        price: Option<&str>,
+        size: &str,
+        opens_position: bool,
+    ) -> Result<()> {
+        if self.limits.is_empty() {
+            return Ok(());
+        }
+
+        let limits = self.limits.for_symbol(symbol);
+        let price = match execution_type {
+            ExecutionType::MARKET => None,
+            _ => price
+                .map(|price| risk::number("price", price))
+                .transpose()?,
+        };
+        let size = risk::number("size", size)?;
+
+        let reference = match limits.needs_reference(price) {
+            true => self.latest_price(symbol)?,
+            false => None,
+        };
+        let position = match limits.needs_position(opens_position) {
+            true => self.position_size(symbol, side)?,
+            false => None,
+        };
+
+        let order = OrderCheck {
+            symbol: symbol.to_string(),
+            side,
+            price,
+            size,
+            opens_position,
+            reference,
+            position,
+        };
+        limits.check(&order).map_err(|violation| {
+            self.audit
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .record(Rejection::new(&order, &violation));
+            GmoCoinError::Risk(violation)
+        })
+    }
+
+    fn latest_price(&self, symbol: &str) -> Result<Option<Decimal>> {
+        let Ok(symbol) = Symbol::from_str(symbol) else {
+            return Ok(None);
+        };
+        self.ticker(Some(symbol))?
+            .data
+            .into_iter()
+            .next()
+            .map(|rate| Ok(risk::number("last", &rate.last)?))
+            .transpose()
+    }
+
+    /// 同じ売買区分の建玉数量。現物の銘柄は`None`
+    fn position_size(&self, symbol: &str, side: Side) -> Result<Option<Decimal>> {
+        if LeverageSymbol::from_str(symbol).is_err() {
+            return Ok(None);
+        }
+        let Ok(symbol) = Symbol::from_str(symbol) else {
+            return Ok(None);
+        };
+
+        let mut position = Decimal::ZERO;
+        for summary in self.position_summary(symbol)?.data.list {
+            if summary.side == side.to_string() {
+                position += risk::number("sumPositionQuantity", &summary.sum_position_quantity)?;
+            }
+        }
+        Ok(Some(position))
+    }
+
+    /// 注文・キャンセル・決済などはすべてPOSTなので、読み取り専用ならPOSTを拒否する
+    ///
+    /// WebSocketのアクセストークン(`/v1/ws-auth`)は取引に関わらないので許す。
+    fn check_writable(&self, method: &str, path: &'static str) -> Result<()> {
+        if self.read_only && method == "POST" && path != "/v1/ws-auth" {
+            return Err(GmoCoinError::ReadOnly(path));
+        }
+        Ok(())
+    }
+
+    fn resolve(&self) -> Result<Resolved> {
+        let mut resolved = self.resolved.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(resolved) = resolved.as_ref() {
+            return Ok(resolved.clone());
+        }
+
+        let new = match &self.signer {
+            Some(signer) => Resolved {
+                api_key: self.provider.api_key()?,
+                signer: signer.clone(),
+            },
+            None => {
+                let Credentials {
+                    api_key,
+                    secret_key,
+                } = self.provider.credentials()?;
+                Resolved {
+                    api_key,
+                    signer: Arc::new(HmacSigner::new(secret_key)),
+                }
+            }
+        };
+        if self.cache_credentials {
+            *resolved = Some(new.clone());
+        }
+        Ok(new)
+    }
+
+    fn get_public<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T> {
+        self.wait("GET");
+        let start = Instant::now();
+        let sent = clock::local_ms()?;
+        let symbol = trace::symbol(query.iter().map(|(key, value)| (*key, value.as_str())), "");
+        let log = RequestLog::new("GET", path, symbol.as_deref());
+        let mut builder = self.agent.get(format!("{}{}", self.public_api, path));
+        for (key, value) in query {
+            builder = builder.query(*key, value);
+        }
+        let result = builder
+            .call()
+            .map_err(GmoCoinError::from)
+            .and_then(|response| self.parse_checked(response, &log, sent));
+        self.observe("GET", path, start, log.finish(result))
+    }
+
+    /// ### status
+    /// 取引所の稼動状態。結果は`exchange_status`にも反映する
+    pub fn status(&self) -> Result<Response<ExchangeStatus>> {
+        let response: Response<ExchangeStatus> = self.get_public("/v1/status", &[])?;
+        if let Ok(state) = response.data.state() {
+            self.exchange.update(state);
+        }
+        Ok(response)
+    }
+
+    /// ### ticker
+    /// symbolの指定が無い場合、全銘柄のレートを取得する。
+    pub fn ticker(&self, symbol: Option<Symbol>) -> Result<Response<Vec<LatestRate>>> {
+        let query: Vec<_> = symbol
+            .map(|s| ("symbol", s.to_string()))
+            .into_iter()
+            .collect();
+        self.get_public("/v1/ticker", &query)
+    }
+
+    pub fn orderbooks(&self, symbol: Symbol) -> Result<Response<Snapshot>> {
+        self.get_public("/v1/orderbooks", &[("symbol", symbol.to_string())])
+    }
+
+    pub fn trades(
+        &self,
+        symbol: Symbol,
+        page: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<Response<TradesList>> {
+        let mut query = vec![("symbol", symbol.to_string())];
+        if let Some(page) = page {
+            query.push(("page", page.to_string()));
+        }
+        if let Some(count) = count {
+            query.push(("count", count.to_string()));
+        }
+        self.get_public("/v1/trades", &query)
+    }
+
+    pub fn klines<D: Into<KlineDate>>(
+        &self,
+        symbol: Symbol,
+        interval: KlineInterval,
+        date: D,
+    ) -> Result<Response<Vec<Kline>>> {
+        let date = date.into();
+        date.validate(interval)?;
+        self.get_public(
+            "/v1/klines",
+            &[
+                ("symbol", symbol.to_string()),
+                ("interval", interval.to_string()),
+                ("date", date.to_string()),
+            ],
+        )
+    }
+
+    pub fn symbols(&self) -> Result<Response<Vec<SymbolRule>>> {
+        self.get_public("/v1/symbols", &[])
+    }
+
+    pub fn margin(&self) -> Result<Response<Margin>> {
+        self.call(private_api::margin_request())
+    }
+
+    pub fn assets(&self) -> Result<Response<Vec<Assets>>> {
+        self.call(private_api::assets_request())
+    }
+
+    pub fn trading_volume(&self) -> Result<Response<Value>> {
+        self.call(private_api::trading_volume_request())
+    }
+
+    pub fn orders<T: ToString>(&self, order_id: T) -> Result<ResponseList<OrderInfo>> {
+        self.call(private_api::orders_request(order_id))
+    }
+
+    pub fn active_orders(
+        &self,
+        symbol: Symbol,
+        page: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<ResponsePage<ActiveOrders>> {
+        self.call(private_api::active_orders_request(symbol, page, count))
+    }
+
+    pub fn executions(&self, param: ExecutionsParam) -> Result<ResponseList<Execution>> {
+        self.call(private_api::executions_request(param))
+    }
+
+    pub fn latest_executions(
+        &self,
+        symbol: Symbol,
+        page: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<ResponsePage<LatestExecutions>> {
+        self.call(private_api::latest_executions_request(symbol, page, count))
+    }
+
+    pub fn open_positions(
+        &self,
+        symbol: Symbol,
+        page: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<ResponsePage<OpenPositions>> {
+        self.call(private_api::open_positions_request(symbol, page, count))
+    }
+
+    pub fn position_summary(&self, symbol: Symbol) -> Result<ResponseList<PositionSummary>> {
+        self.call(private_api::position_summary_request(symbol))
+    }
+
+    pub fn order(
+        &self,
+        symbol: Symbol,
+        side: Side,
+        execution_type: ExecutionType,
+        price: Option<String>,
+        size: String,
+    ) -> Result<Response<String>> {
+        let request =
+            private_api::order_request(symbol, side, execution_type, price.clone(), size.clone());
+        self.check_writable(request.method, request.path)?;
+        self.check_order(symbol, side, execution_type, price.as_deref(), &size)?;
+        self.call(request)
+    }
+
+    pub fn change_order(
+        &self,
+        order_id: usize,
+        price: String,
+        losscut_price: Option<String>,
+    ) -> Result<Ack> {
+        let request = private_api::change_order_request(order_id, price.clone(), losscut_price);
+        self.check_writable(request.method, request.path)?;
+        self.check_change_order(order_id, &price)?;
+        self.call(request)
+    }
+
+    pub fn cancel_order(&self, order_id: usize) -> Result<Ack> {
+        self.call(private_api::cancel_order_request(order_id))
+    }
+
+    pub fn cancel_orders(&self, order_ids: Vec<usize>) -> Result<Response<Value>> {
+        self.call(private_api::cancel_orders_request(order_ids))
+    }
+
+    pub fn cancel_bulk_order(&self, symbols: Vec<Symbol>) -> Result<Response<Value>> {
+        self.call(private_api::cancel_bulk_order_request(symbols))
+    }
+
+    pub fn close_order(
+        &self,
+        symbol: LeverageSymbol,
+        side: Side,
+        execution_type: ExecutionType,
+        price: Option<String>,
+        settle_position: SettlePosition,
+    ) -> Result<Response<String>> {
+        let size = settle_position.size.clone();
+        let request = private_api::close_order_request(
+            symbol,
+            side,
+            execution_type,
+            price.clone(),
+            settle_position,
+        );
+        self.check_writable(request.method, request.path)?;
+        self.check_close_order(symbol, side, execution_type, price.as_deref(), &size)?;
+        self.call(request)
+    }
+
+    pub fn close_bulk_order(
+        &self,
+        symbol: LeverageSymbol,
+        side: Side,
+        execution_type: ExecutionType,
+        price: Option<String>,
+        size: String,
+    ) -> Result<Response<String>> {
+        self.call(private_api::close_bulk_order_request(
+            symbol,
+            side,
+            execution_type,
+            price,
+            size,
+        ))
+    }
+
+    pub fn change_losscut_price(&self, position_id: usize, losscut_price: String) -> Result<Ack> {
+        self.call(private_api::change_losscut_price_request(
+            position_id,
+            losscut_price,
+        ))
+    }
+
+    pub fn ws_auth(&self) -> Result<Response<String>> {
+        self.call(private_api::ws_auth_request())
+    }
+
+    pub fn extend_ws_auth(&self, token: &str) -> Result<Ack> {
+        self.call(private_api::extend_ws_auth_request(token))
+    }
+
+    pub fn delete_ws_auth(&self, token: &str) -> Result<Ack> {
+        self.call(private_api::delete_ws_auth_request(token))
+    }
+}
+
+#[cfg(unix)]
+fn unix_socket_signer(path: &Path) -> Result<crate::signer::UnixSocketSigner> {
+    Ok(crate::signer::UnixSocketSigner::new(
+        crate::credentials::expand_home(path),
+    ))
+}
+
+#[cfg(not(unix))]
+fn unix_socket_signer(path: &Path) -> Result<HmacSigner> {
+    Err(GmoCoinError::Config(format!(
+        "signer socket {} is only supported on unix",
+        path.display()
+    )))
+}
+
+fn insert_header(
+    headers: &mut HeaderMap<HeaderValue>,
+    key: &'static str,
+    value: &str,
+) -> Result<()> {
+    headers.insert(key, HeaderValue::from_str(value)?);
+    Ok(())
+}
+
+fn set_request_headers<B>(
+    request: &mut ureq::RequestBuilder<B>,
+    signed: &SignedRequest,
+) -> Result<()> {
+    let timestamp_string = signed.timestamp.to_string();
+    let headers = request
+        .headers_mut()
+        .ok_or(GmoCoinError::MissingRequestHeaders)?;
+    insert_header(headers, "API-KEY", signed.api_key.expose())?;
+    insert_header(headers, "API-TIMESTAMP", &timestamp_string)?;
+    insert_header(headers, "API-SIGN", &signed.sign)?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! サーバーの時計とのずれの推定
+//!
+//! レスポンスの`responsetime`(なければHTTPの`Date`ヘッダ)と、
+//! 送信・受信したときの手元の時刻の中間との差をずれとする。
+
+use crate::error::Result;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use time::OffsetDateTime;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+
+/// メッセージコード。`API-TIMESTAMP`がサーバーの時刻より後・前
+pub(crate) const TIMESTAMP_CODES: &[&str] = &["ERR-5008", "ERR-5009"];
+
+/// 既定で警告するずれ
+const DEFAULT_WARNING: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub(crate) struct Clock {
+    offset_ms: AtomicI64,
+    /// `responsetime`から推定したことがあるか。`Date`ヘッダは秒単位なので、それより優先する
+    precise: AtomicBool,
+    warning_ms: AtomicI64,
+    warned: AtomicBool,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            offset_ms: AtomicI64::new(0),
+            precise: AtomicBool::new(false),
+            warning_ms: AtomicI64::new(DEFAULT_WARNING.as_millis() as i64),
+            warned: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Clock {
+    /// サーバーの時計に合わせた現在時刻(ミリ秒)
+    pub(crate) fn now_ms(&self) -> Result<u64> {
+        Ok(local_ms()?.saturating_add_signed(self.offset_ms()))
+    }
+
+    pub(crate) fn offset_ms(&self) -> i64 {
+        self.offset_ms.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_warning(&self, warning: Duration) {
+        self.warning_ms
+            .store(warning.as_millis() as i64, Ordering::Relaxed);
+    }
+
+    /// ### observe
+    /// `sent`・`received`は手元の時刻(ミリ秒)
+    pub(crate) fn observe(
+        &self,
+        sent: u64,
+        received: u64,
+        responsetime: Option<&str>,
+        date: Option<&str>,
+    ) {
+        let midpoint = (sent + received) as i64 / 2;
+        if let Some(server) = responsetime.and_then(|text| parse_ms(text, &Rfc3339)) {
+            self.precise.store(true, Ordering::Relaxed);
+            self.set_offset(server - midpoint);
+        } else if !self.precise.load(Ordering::Relaxed)
+            && let Some(server) = date.and_then(|text| parse_ms(text, &Rfc2822))
+        {
+            // 秒単位に切り捨てられているので、その秒の真ん中とする
+            self.set_offset(server + 500 - midpoint);
+        }
+    }
+
+    fn set_offset(&self, offset: i64) {
+        self.offset_ms.store(offset, Ordering::Relaxed);
+        let exceeded = offset.abs() > self.warning_ms.load(Ordering::Relaxed);
+        // 超えたときに一度だけ警告する
+        if self.warned.swap(exceeded, Ordering::Relaxed) != exceeded && exceeded {
+            crate::trace::clock_skew(offset);
+        }
+    }
+}
+
+pub(crate) fn local_ms() -> Result<u64> {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    Ok(since_epoch.as_millis() as u64)
+}
+
+fn parse_ms<F: time::parsing::Parsable + ?Sized>(text: &str, format: &F) -> Option<i64> {
+    let time = OffsetDateTime::parse(text, format).ok()?;
+    Some((time.unix_timestamp_nanos() / 1_000_000) as i64)
+}
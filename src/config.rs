@@ -0,0 +1,256 @@
+use crate::credentials::{self, Secret, SourceProvider};
+use crate::error::{GmoCoinError, Result};
+use crate::http::{Proxy, TlsOptions};
+pub use crate::private::risk::Limits;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+/// 設定ファイルの場所を上書きする環境変数
+pub const CONFIG_ENV: &str = "GMO_COIN_CONFIG";
+/// 使うプロファイルを選ぶ環境変数
+pub const PROFILE_ENV: &str = "GMO_COIN_PROFILE";
+/// プロファイルより優先されるAPIキーの環境変数
+pub const API_KEY_ENV: &str = "GMO_COIN_API_KEY";
+/// プロファイルより優先されるシークレットキーの環境変数
+pub const SECRET_KEY_ENV: &str = "GMO_COIN_SECRET_KEY";
+
+/// ## Config
+/// 設定ファイル
+///
+/// ```toml
+/// default_profile = "main"
+///
+/// [profiles.main]
+/// api_key = { env = "MAIN_API_KEY" }
+/// secret_key = { file = "~/.config/gmo_coin/main.secret" }
+/// rate_limit_tier = 1
+/// default_symbol = "BTC_JPY"
+/// timeout = 10
+///
+/// [profiles.main.limits]
+/// max_order_size = "0.1"
+/// max_notional = "1000000"
+///
+/// [profiles.analyst]
+/// api_key = { env = "ANALYST_API_KEY" }
+/// secret_key = { env = "ANALYST_SECRET_KEY" }
+/// read_only = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// ## Profile
+/// 名前付きの接続設定
+///
+/// 省略した項目は既定値(本番のURL、Tier1、`HttpOptions::default`のタイムアウト)になる。
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub api_key: Option<SecretSource>,
+    pub secret_key: Option<SecretSource>,
+    /// 署名プロセスのUnixソケット。指定すると`secret_key`は使わない
+    pub signer: Option<PathBuf>,
+    pub public_api: Option<String>,
+    pub private_api: Option<String>,
+    pub public_ws: Option<String>,
+    pub private_ws: Option<String>,
+    /// 1または2
+    pub rate_limit_tier: Option<u8>,
+    pub default_symbol: Option<String>,
+    /// リクエスト全体のタイムアウト(秒)
+    pub timeout: Option<u64>,
+    /// 接続のタイムアウト(秒)。既定は10
+    pub connect_timeout: Option<u64>,
+    /// レスポンスを待つ・読むタイムアウト(秒)。既定は30
+    pub read_timeout: Option<u64>,
+    pub user_agent: Option<String>,
+    /// `http://`・`socks5://`のプロキシ
+    pub proxy: Option<Proxy>,
+    /// 追加で信頼するルート証明書(PEM)のパス
+    #[serde(default)]
+    pub ca_certs: Vec<PathBuf>,
+    /// ピン留めする公開鍵のSHA-256(16進数)
+    #[serde(default)]
+    pub pinned_keys: Vec<String>,
+    /// 注文前のリスクチェック(`private::risk`)
+    #[serde(default)]
+    pub limits: Limits,
+    /// 注文・キャンセル・決済などを送らない
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// ## SecretSource
+/// APIキー・シークレットキーの取得元
+///
+///  - `"..."`: 値をそのまま書く
+///  - `{ env = "NAME" }`: 環境変数
+///  - `{ file = "path" }`: ファイルの中身(前後の空白は除く)。他のユーザーが読めるファイルは拒否する
+///  - `{ command = ["pass", "show", "gmo"] }`: コマンドの標準出力
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum SecretSource {
+    Value(Secret),
+    Env { env: String },
+    File { file: PathBuf },
+    Command { command: Vec<String> },
+}
+
+impl fmt::Debug for self::SecretSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SecretSource::Value(_) => write!(f, "Value(***)"),
+            SecretSource::Env { env } => write!(f, "Env({})", env),
+            SecretSource::File { file } => write!(f, "File({})", file.display()),
+            SecretSource::Command { command } => write!(f, "Command({:?})", command),
+        }
+    }
+}
+
+impl SecretSource {
+    /// ### resolve
+    /// 値を取り出す
+    pub fn resolve(&self) -> Result<Secret> {
+        match self {
+            SecretSource::Value(value) => Ok(value.clone()),
+            SecretSource::Env { env } => credentials::read_env(env),
+            SecretSource::File { file } => credentials::read_file(file),
+            SecretSource::Command { command } => credentials::run_command(command),
+        }
+    }
+}
+
+impl Profile {
+    /// ### tls_options
+    /// `ca_certs`・`pinned_keys`から作るTLSの設定
+    pub fn tls_options(&self) -> Result<TlsOptions> {
+        let mut tls = TlsOptions::default();
+        for path in &self.ca_certs {
+            tls = tls.root_certificate_file(credentials::expand_home(path))?;
+        }
+        for pin in &self.pinned_keys {
+            tls = tls.pin_sha256(pin)?;
+        }
+        Ok(tls)
+    }
+
+    /// ### credential_provider
+    /// `api_key`・`secret_key`の取得元
+    ///
+    /// `GMO_COIN_API_KEY`・`GMO_COIN_SECRET_KEY`が設定されていれば、プロファイルより優先する。
+    pub fn credential_provider(&self) -> SourceProvider {
+        let source = |name: &str, source: &Option<SecretSource>| match source {
+            Some(source) if env::var_os(name).is_none() => source.clone(),
+            _ => SecretSource::Env {
+                env: name.to_string(),
+            },
+        };
+        SourceProvider {
+            api_key: source(API_KEY_ENV, &self.api_key),
+            secret_key: source(SECRET_KEY_ENV, &self.secret_key),
+        }
+    }
+}
+
+impl Config {
+    /// ### default_path
+    /// `$GMO_COIN_CONFIG`、なければ`$XDG_CONFIG_HOME/gmo_coin/config.toml`
+    /// (`~/.config/gmo_coin/config.toml`)
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os(CONFIG_ENV) {
+            return Some(PathBuf::from(path));
+        }
+
+        let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => env::home_dir()?.join(".config"),
+        };
+        Some(config_dir.join("gmo_coin").join("config.toml"))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| GmoCoinError::Config(format!("can't read {}: {}", path.display(), e)))?;
+        Self::parse(&content)
+    }
+
+    /// ### load_default
+    /// 既定の場所から読む
+    ///
+    /// `$GMO_COIN_CONFIG`を指定していない場合に限り、ファイルが無ければ空の設定にする。
+    pub fn load_default() -> Result<Self> {
+        match Self::default_path() {
+            Some(path) if path.exists() || env::var_os(CONFIG_ENV).is_some() => Self::load(path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// ### parse
+    /// エラーには該当行を含めず、値も伏せる
+    pub fn parse(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| {
+            let line = e
+                .span()
+                .map(|span| content[..span.start].matches('\n').count() + 1);
+            let message = redact_quoted(e.message());
+            GmoCoinError::ConfigParse(match line {
+                Some(line) => format!("line {}: {}", line, message),
+                None => message,
+            })
+        })
+    }
+
+    /// ### profile
+    /// プロファイルを選ぶ
+    ///
+    /// 名前を指定しなければ`$GMO_COIN_PROFILE`、`default_profile`の順に探し、
+    /// どちらも無ければ`default`という名前のプロファイル(無ければ既定値)を使う。
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        let name = match name {
+            Some(name) => Some(name.to_string()),
+            None => env::var(PROFILE_ENV)
+                .ok()
+                .or_else(|| self.default_profile.clone()),
+        };
+
+        match name {
+            Some(name) => self
+                .profiles
+                .get(&name)
+                .cloned()
+                .ok_or(GmoCoinError::ProfileNotFound(name)),
+            None => Ok(self.profiles.get("default").cloned().unwrap_or_default()),
+        }
+    }
+}
+
+/// エラーメッセージ中の引用符で囲まれた値(キーかもしれない)を`***`に置き換える
+fn redact_quoted(message: &str) -> String {
+    let mut redacted = String::with_capacity(message.len());
+    let mut quoted = false;
+    for c in message.chars() {
+        if c == '"' {
+            if quoted {
+                redacted.push_str("***\"");
+            } else {
+                redacted.push('"');
+            }
+            quoted = !quoted;
+        } else if !quoted {
+            redacted.push(c);
+        }
+    }
+    if quoted {
+        redacted.push_str("***");
+    }
+    redacted
+}
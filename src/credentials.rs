@@ -0,0 +1,304 @@
+use crate::config::{API_KEY_ENV, SECRET_KEY_ENV, SecretSource};
+use crate::error::{GmoCoinError, Result};
+use serde::{Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fmt, fs};
+use zeroize::Zeroize;
+
+/// ## Secret
+/// APIキーやシークレットキーを入れる文字列
+///
+/// `Debug`・`Display`は`***`を表示し、破棄するときにメモリを0で埋める。
+/// 中身は`expose`でしか取り出せない。
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new<S: Into<String>>(value: S) -> Self {
+        Self(value.into())
+    }
+
+    /// ### expose
+    /// 中身を取り出す。署名やヘッダに渡す直前だけ使う
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for self::Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "***")
+    }
+}
+
+impl fmt::Display for self::Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "***")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+/// ## Credentials
+/// APIキーとシークレットキーの組
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub api_key: Secret,
+    pub secret_key: Secret,
+}
+
+impl Credentials {
+    pub fn new<K: Into<Secret>, S: Into<Secret>>(api_key: K, secret_key: S) -> Self {
+        Self {
+            api_key: api_key.into(),
+            secret_key: secret_key.into(),
+        }
+    }
+}
+
+/// ## CredentialProvider
+/// 認証情報の取得元
+///
+/// `GmoCoinClient::credential_provider`で渡すと、最初に署名するときに一度だけ呼ばれる。
+/// 返すエラーにキーの値を含めてはいけない。
+pub trait CredentialProvider: Send + Sync {
+    fn credentials(&self) -> Result<Credentials>;
+
+    /// ### api_key
+    /// APIキーだけを取得する。`Signer`に署名を任せる場合はこちらだけが呼ばれる
+    fn api_key(&self) -> Result<Secret> {
+        Ok(self.credentials()?.api_key)
+    }
+}
+
+impl fmt::Debug for dyn CredentialProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CredentialProvider")
+    }
+}
+
+/// 値をそのまま使う
+impl CredentialProvider for Credentials {
+    fn credentials(&self) -> Result<Credentials> {
+        Ok(self.clone())
+    }
+}
+
+/// ## EnvProvider
+/// 環境変数から読む。既定は`GMO_COIN_API_KEY`・`GMO_COIN_SECRET_KEY`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvProvider {
+    pub api_key: String,
+    pub secret_key: String,
+}
+
+impl Default for EnvProvider {
+    fn default() -> Self {
+        Self::new(API_KEY_ENV, SECRET_KEY_ENV)
+    }
+}
+
+impl EnvProvider {
+    pub fn new(api_key: &str, secret_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            secret_key: secret_key.to_string(),
+        }
+    }
+}
+
+impl CredentialProvider for EnvProvider {
+    fn credentials(&self) -> Result<Credentials> {
+        Ok(Credentials {
+            api_key: read_env(&self.api_key)?,
+            secret_key: read_env(&self.secret_key)?,
+        })
+    }
+
+    fn api_key(&self) -> Result<Secret> {
+        read_env(&self.api_key)
+    }
+}
+
+/// ## FileProvider
+/// ファイルから読む(前後の空白は除く)
+///
+/// Unixでは他のユーザーが読めるファイルは拒否する。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileProvider {
+    pub api_key: PathBuf,
+    pub secret_key: PathBuf,
+}
+
+impl FileProvider {
+    pub fn new<P: Into<PathBuf>, Q: Into<PathBuf>>(api_key: P, secret_key: Q) -> Self {
+        Self {
+            api_key: api_key.into(),
+            secret_key: secret_key.into(),
+        }
+    }
+}
+
+impl CredentialProvider for FileProvider {
+    fn credentials(&self) -> Result<Credentials> {
+        Ok(Credentials {
+            api_key: read_file(&self.api_key)?,
+            secret_key: read_file(&self.secret_key)?,
+        })
+    }
+
+    fn api_key(&self) -> Result<Secret> {
+        read_file(&self.api_key)
+    }
+}
+
+/// ## CommandProvider
+/// コマンドの標準出力から読む(`pass`や`op`など)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandProvider {
+    pub api_key: Vec<String>,
+    pub secret_key: Vec<String>,
+}
+
+impl CommandProvider {
+    pub fn new(api_key: Vec<String>, secret_key: Vec<String>) -> Self {
+        Self {
+            api_key,
+            secret_key,
+        }
+    }
+}
+
+impl CredentialProvider for CommandProvider {
+    fn credentials(&self) -> Result<Credentials> {
+        Ok(Credentials {
+            api_key: run_command(&self.api_key)?,
+            secret_key: run_command(&self.secret_key)?,
+        })
+    }
+
+    fn api_key(&self) -> Result<Secret> {
+        run_command(&self.api_key)
+    }
+}
+
+/// ## SourceProvider
+/// キーごとに取得元を選ぶ。設定ファイルのプロファイルはこれになる
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceProvider {
+    pub api_key: SecretSource,
+    pub secret_key: SecretSource,
+}
+
+impl CredentialProvider for SourceProvider {
+    fn credentials(&self) -> Result<Credentials> {
+        Ok(Credentials {
+            api_key: self.api_key.resolve()?,
+            secret_key: self.secret_key.resolve()?,
+        })
+    }
+
+    fn api_key(&self) -> Result<Secret> {
+        self.api_key.resolve()
+    }
+}
+
+/// 環境変数を読む。エラーには変数名だけを含める
+pub fn read_env(name: &str) -> Result<Secret> {
+    match env::var(name) {
+        Ok(value) => Ok(Secret(value)),
+        Err(env::VarError::NotPresent) => {
+            Err(GmoCoinError::Credentials(format!("{} is not set", name)))
+        }
+        Err(env::VarError::NotUnicode(_)) => Err(GmoCoinError::Credentials(format!(
+            "{} is not valid unicode",
+            name
+        ))),
+    }
+}
+
+/// ファイルを読む。他のユーザーが読めるファイルは拒否する
+pub fn read_file(path: &Path) -> Result<Secret> {
+    let path = expand_home(path);
+    let unreadable = |e: std::io::Error| {
+        GmoCoinError::Credentials(format!("can't read {}: {}", path.display(), e))
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path)
+            .map_err(unreadable)?
+            .permissions()
+            .mode();
+        if mode & 0o004 != 0 {
+            return Err(GmoCoinError::Credentials(format!(
+                "{} is world-readable (mode {:o}), run chmod o-r on it",
+                path.display(),
+                mode & 0o777
+            )));
+        }
+    }
+
+    let mut content = fs::read_to_string(&path).map_err(unreadable)?;
+    let secret = Secret(content.trim().to_string());
+    content.zeroize();
+    Ok(secret)
+}
+
+/// コマンドを実行して標準出力を読む。標準エラー出力はエラーに含めない
+pub fn run_command(command: &[String]) -> Result<Secret> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| GmoCoinError::Credentials("empty secret command".to_string()))?;
+    let mut output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| GmoCoinError::Credentials(format!("can't run {}: {}", program, e)))?;
+    output.stderr.zeroize();
+    if !output.status.success() {
+        output.stdout.zeroize();
+        return Err(GmoCoinError::Credentials(format!(
+            "{} exited with {}",
+            program, output.status
+        )));
+    }
+
+    let secret = Secret(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    output.stdout.zeroize();
+    Ok(secret)
+}
+
+pub(crate) fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
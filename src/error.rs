@@ -4,7 +4,7 @@ use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum GmoCoinError {
-    #[error("environment variable error: {0}")]
+    #[error("environment variable error: {}", env_var_reason(.0))]
     EnvVar(#[from] env::VarError),
 
     #[error("http request error: {0}")]
@@ -26,8 +26,90 @@ pub enum GmoCoinError {
     #[error("async request error: {0}")]
     Reqwest(#[from] reqwest::Error),
 
+    #[cfg(feature = "websocket")]
+    #[error("websocket error: {0}")]
+    WebSocket(#[from] tungstenite::Error),
+
+    #[error("websocket api error: {0}")]
+    WebSocketApi(String),
+
     #[error("system time error: {0}")]
     SystemTime(#[from] SystemTimeError),
+
+    #[error("decimal parse error: {0}")]
+    Decimal(#[from] rust_decimal::Error),
+
+    #[error("time parse error: {0}")]
+    TimeParse(#[from] time::error::Parse),
+
+    #[error("symbol mismatch: expected {expected}, got {actual}")]
+    SymbolMismatch { expected: String, actual: String },
+
+    #[error("invalid date range: {from} is after {to}")]
+    InvalidDateRange { from: time::Date, to: time::Date },
+
+    #[error("invalid kline open time: {0}")]
+    InvalidOpenTime(String),
+
+    #[error("invalid kline date {date} for {interval}: {reason}")]
+    InvalidKlineDate {
+        date: crate::public::api::KlineDate,
+        interval: crate::public::api::KlineInterval,
+        reason: &'static str,
+    },
+
+    #[error("order validation error: {0}")]
+    OrderValidation(#[from] crate::private::validation::OrderValidationError),
+
+    #[error("risk check rejected the order: {0}")]
+    Risk(#[from] crate::private::risk::RiskViolation),
+
+    #[error("order tracker error: {0}")]
+    Tracker(#[from] crate::private::tracker::TrackerError),
+
+    #[error("side parse error: {0}")]
+    Side(#[from] crate::SideError),
+
+    #[error("config error: {0}")]
+    Config(String),
+
+    #[error("config parse error: {0}")]
+    ConfigParse(String),
+
+    #[error("credential error: {0}")]
+    Credentials(String),
+
+    #[error("signer error: {0}")]
+    Signer(String),
+
+    #[error("read-only client refuses {0}")]
+    ReadOnly(&'static str),
+
+    #[error("exchange is under maintenance")]
+    Maintenance,
+
+    #[error("unknown exchange status: {0}")]
+    UnknownExchangeState(String),
+
+    #[error("profile not found: {0}")]
+    ProfileNotFound(String),
+
+    #[error("api error (status {status}): {}", .messages.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Api {
+        status: i64,
+        messages: Vec<crate::ApiMessage>,
+    },
+
+    #[error("klines have a gap between {from} and {to}")]
+    KlineGap { from: u64, to: u64 },
 }
 
 pub type Result<T> = std::result::Result<T, GmoCoinError>;
+
+/// 値(キーかもしれない)を含めずに理由だけを表示する
+fn env_var_reason(error: &env::VarError) -> &'static str {
+    match error {
+        env::VarError::NotPresent => "environment variable not found",
+        env::VarError::NotUnicode(_) => "environment variable was not valid unicode",
+    }
+}
@@ -0,0 +1,136 @@
+pub mod proxy;
+pub mod tls;
+mod transport;
+
+pub use proxy::Proxy;
+pub use tls::TlsOptions;
+
+use std::sync::OnceLock;
+use std::time::Duration;
+use transport::{SocksConnector, TlsConnector};
+use ureq::unversioned::resolver::DefaultResolver;
+use ureq::unversioned::transport::{ConnectProxyConnector, Connector, TcpConnector};
+
+#[cfg(feature = "async")]
+use crate::error::{GmoCoinError, Result};
+#[cfg(feature = "async")]
+use rustls::ClientConfig;
+
+/// 既定の`User-Agent`
+pub const USER_AGENT: &str = concat!("gmo_coin/", env!("CARGO_PKG_VERSION"));
+
+/// ## HttpOptions
+/// HTTPの接続の設定
+///
+/// 同じ設定から作ったエージェント(クライアント)は接続を使い回す。
+/// 既定は接続10秒・読み込み30秒で、全体の上限は無し。
+/// プロキシとTLSの設定はREST(同期・async)とWebSocketのどれにも使う。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpOptions {
+    /// 1回のリクエスト全体の上限
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// レスポンスを待つ・読む時間の上限
+    pub read_timeout: Option<Duration>,
+    pub user_agent: String,
+    /// `false`なら接続を使い回さない
+    pub keep_alive: bool,
+    /// 無ければ環境変数(`HTTPS_PROXY`・`ALL_PROXY`など)に従う
+    pub proxy: Option<Proxy>,
+    pub tls: TlsOptions,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            connect_timeout: Some(Duration::from_secs(10)),
+            read_timeout: Some(Duration::from_secs(30)),
+            user_agent: USER_AGENT.to_string(),
+            keep_alive: true,
+            proxy: None,
+            tls: TlsOptions::default(),
+        }
+    }
+}
+
+impl HttpOptions {
+    /// ### agent
+    /// 同期側(ureq)のエージェント
+    pub fn agent(&self) -> ureq::Agent {
+        let mut config = ureq::Agent::config_builder()
+            .timeout_global(self.timeout)
+            .timeout_connect(self.connect_timeout)
+            .timeout_recv_response(self.read_timeout)
+            .timeout_recv_body(self.read_timeout)
+            .user_agent(&self.user_agent);
+        if !self.keep_alive {
+            config = config.max_idle_connections(0);
+        }
+        if let Some(proxy) = &self.proxy {
+            config = config.proxy(Some(proxy.inner().clone()));
+        }
+        let connector =
+            ().chain(SocksConnector)
+                .chain(ConnectProxyConnector::default())
+                .chain(TcpConnector::default())
+                .chain(TlsConnector::new(self.tls.client_config()));
+        ureq::Agent::with_parts(config.build(), connector, DefaultResolver::default())
+    }
+
+    /// ### async_client
+    /// async側(reqwest)のクライアント
+    #[cfg(feature = "async")]
+    pub fn async_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder().user_agent(&self.user_agent);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+        if !self.keep_alive {
+            builder = builder.pool_max_idle_per_host(0);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy.url())?);
+        }
+        if let Some(config) = self.tls.client_config() {
+            builder = builder.tls_backend_preconfigured(ClientConfig::clone(&config));
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// 既定の設定のエージェント。`GmoCoinClient::from_env`と関数版のAPIで共有する
+pub(crate) fn shared_agent() -> ureq::Agent {
+    static AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+    AGENT.get_or_init(|| HttpOptions::default().agent()).clone()
+}
+
+#[cfg(feature = "async")]
+static ASYNC_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// ## configure_async
+/// async版のAPIが共有するクライアントの設定
+///
+/// 最初のリクエストより前に一度だけ呼べる。
+#[cfg(feature = "async")]
+pub fn configure_async(options: &HttpOptions) -> Result<()> {
+    ASYNC_CLIENT
+        .set(options.async_client()?)
+        .map_err(|_| GmoCoinError::Config("async http client is already in use".to_string()))
+}
+
+/// async版のAPIが共有するクライアント
+#[cfg(feature = "async")]
+pub(crate) fn async_client() -> Result<reqwest::Client> {
+    if let Some(client) = ASYNC_CLIENT.get() {
+        return Ok(client.clone());
+    }
+    let client = HttpOptions::default().async_client()?;
+    Ok(ASYNC_CLIENT.get_or_init(|| client).clone())
+}
@@ -0,0 +1,189 @@
+use crate::error::{GmoCoinError, Result};
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::server::ParsedCertificate;
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// ## TlsOptions
+/// 追加で信頼するルート証明書と、証明書のピン留め
+///
+/// ピンは公開鍵(SubjectPublicKeyInfo)のSHA-256で、
+/// 通常の検証に加えて、サーバーの証明書チェーンのどれかが一致しなければ接続を拒否する。
+/// 既定の接続先は`api.coin.z.com`だけなので、そのピンを指定する。
+#[derive(Clone, Default)]
+pub struct TlsOptions {
+    roots: Vec<CertificateDer<'static>>,
+    pins: Vec<[u8; 32]>,
+    /// 既定のままなら`None`で、各ライブラリの既定の設定を使う
+    config: Option<Arc<ClientConfig>>,
+}
+
+impl PartialEq for TlsOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.roots == other.roots && self.pins == other.pins
+    }
+}
+
+impl Eq for TlsOptions {}
+
+impl fmt::Debug for TlsOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TlsOptions")
+            .field("roots", &self.roots.len())
+            .field(
+                "pins",
+                &self.pins.iter().map(hex::encode).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl TlsOptions {
+    /// ### root_certificates
+    /// PEMに含まれる証明書をすべてルート証明書に加える
+    pub fn root_certificates(mut self, pem: &[u8]) -> Result<Self> {
+        let certs = CertificateDer::pem_slice_iter(pem)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| GmoCoinError::Config(format!("invalid certificate: {}", e)))?;
+        if certs.is_empty() {
+            return Err(GmoCoinError::Config("no certificate in PEM".to_string()));
+        }
+        self.roots.extend(certs);
+        self.build()
+    }
+
+    pub fn root_certificate_file<P: AsRef<Path>>(self, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let pem = fs::read(path)
+            .map_err(|e| GmoCoinError::Config(format!("can't read {}: {}", path.display(), e)))?;
+        self.root_certificates(&pem)
+    }
+
+    /// ### pin_sha256
+    /// 公開鍵のSHA-256(16進数)をピン留めする
+    ///
+    /// `openssl x509 -pubkey -noout | openssl pkey -pubin -outform der | openssl dgst -sha256`で求まる。
+    pub fn pin_sha256(mut self, hex: &str) -> Result<Self> {
+        let pin = hex::decode(hex.trim())
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| GmoCoinError::Config(format!("invalid sha256 pin: {}", hex)))?;
+        self.pins.push(pin);
+        self.build()
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        !self.pins.is_empty()
+    }
+
+    pub(crate) fn client_config(&self) -> Option<Arc<ClientConfig>> {
+        self.config.clone()
+    }
+
+    fn build(mut self) -> Result<Self> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        for cert in &self.roots {
+            roots.add(cert.clone()).map_err(tls_error)?;
+        }
+
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?;
+        let config = if self.pins.is_empty() {
+            builder.with_root_certificates(roots)
+        } else {
+            let verifier = PinnedVerifier {
+                inner: WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+                    .build()
+                    .map_err(tls_error)?,
+                pins: self.pins.clone(),
+            };
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(verifier))
+        };
+        self.config = Some(Arc::new(config.with_no_client_auth()));
+        Ok(self)
+    }
+}
+
+fn tls_error<E: fmt::Display>(e: E) -> GmoCoinError {
+    GmoCoinError::Config(format!("tls: {}", e))
+}
+
+/// 通常の検証のあとで、チェーンの公開鍵がピンに含まれるか確かめる
+#[derive(Debug)]
+struct PinnedVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        let pinned = std::iter::once(end_entity)
+            .chain(intermediates)
+            .filter_map(spki_sha256)
+            .any(|digest| self.pins.contains(&digest));
+        if pinned {
+            Ok(verified)
+        } else {
+            Err(rustls::Error::General(
+                "server certificate does not match the pinned keys".to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+fn spki_sha256(cert: &CertificateDer<'_>) -> Option<[u8; 32]> {
+    let parsed = ParsedCertificate::try_from(cert).ok()?;
+    let digest = ring::digest::digest(
+        &ring::digest::SHA256,
+        parsed.subject_public_key_info().as_ref(),
+    );
+    digest.as_ref().try_into().ok()
+}
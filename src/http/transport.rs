@@ -0,0 +1,197 @@
+//! ureqのコネクタ
+//!
+//! ureqの`socks-proxy`フィーチャの代わりにSOCKS5を扱い、
+//! `TlsOptions`を設定したときはそのrustlsの設定でTLSを張る。
+
+use super::proxy;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, StreamOwned};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use ureq::Error;
+use ureq::unversioned::transport::{
+    Buffers, ConnectionDetails, Connector, Either, LazyBuffers, NextTimeout, RustlsConnector,
+    Transport, TransportAdapter,
+};
+
+/// SOCKS5のプロキシが設定されていれば、それを経由して接続する
+#[derive(Debug, Default)]
+pub(crate) struct SocksConnector;
+
+impl<In: Transport> Connector<In> for SocksConnector {
+    type Out = Either<In, SocksTransport>;
+
+    fn connect(
+        &self,
+        details: &ConnectionDetails,
+        chained: Option<In>,
+    ) -> Result<Option<Self::Out>, Error> {
+        let Some(proxy) = details.config.proxy().filter(|p| proxy::is_socks(p)) else {
+            return Ok(chained.map(Either::A));
+        };
+        if chained.is_some() {
+            return Ok(chained.map(Either::A));
+        }
+        if proxy.is_no_proxy(details.uri) {
+            return Ok(None);
+        }
+
+        let host = details.uri.host().unwrap_or_default();
+        let port = details
+            .uri
+            .port_u16()
+            .unwrap_or(if details.needs_tls() { 443 } else { 80 });
+        let config = details.config;
+        let stream = proxy::connect(Some(proxy), host, port, config.timeouts().connect)?;
+        if config.no_delay() {
+            stream.set_nodelay(true)?;
+        }
+        let buffers = LazyBuffers::new(config.input_buffer_size(), config.output_buffer_size());
+        Ok(Some(Either::B(SocksTransport { stream, buffers })))
+    }
+}
+
+/// プロキシを経由したTCPの接続
+#[derive(Debug)]
+pub(crate) struct SocksTransport {
+    stream: TcpStream,
+    buffers: LazyBuffers,
+}
+
+impl Transport for SocksTransport {
+    fn buffers(&mut self) -> &mut dyn Buffers {
+        &mut self.buffers
+    }
+
+    fn transmit_output(&mut self, amount: usize, timeout: NextTimeout) -> Result<(), Error> {
+        self.stream
+            .set_write_timeout(timeout.not_zero().map(|t| *t))?;
+        let output = &self.buffers.output()[..amount];
+        self.stream
+            .write_all(output)
+            .map_err(|e| timeout_error(e, timeout))
+    }
+
+    fn await_input(&mut self, timeout: NextTimeout) -> Result<bool, Error> {
+        self.stream
+            .set_read_timeout(timeout.not_zero().map(|t| *t))?;
+        let input = self.buffers.input_append_buf();
+        let amount = self
+            .stream
+            .read(input)
+            .map_err(|e| timeout_error(e, timeout))?;
+        self.buffers.input_appended(amount);
+        Ok(amount > 0)
+    }
+
+    fn is_open(&mut self) -> bool {
+        // 読めるものが無い(ブロックする)なら開いている
+        let mut buf = [0];
+        let open = self.stream.set_nonblocking(true).is_ok()
+            && matches!(self.stream.peek(&mut buf), Err(e) if e.kind() == io::ErrorKind::WouldBlock);
+        open && self.stream.set_nonblocking(false).is_ok()
+    }
+}
+
+fn timeout_error(error: io::Error, timeout: NextTimeout) -> Error {
+    match error.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Error::Timeout(timeout.reason),
+        _ => error.into(),
+    }
+}
+
+/// `TlsOptions`の設定が無ければureqの既定のrustlsを使う
+#[derive(Debug, Default)]
+pub(crate) struct TlsConnector {
+    config: Option<Arc<ClientConfig>>,
+    fallback: RustlsConnector,
+}
+
+impl TlsConnector {
+    pub(crate) fn new(config: Option<Arc<ClientConfig>>) -> Self {
+        Self {
+            config,
+            fallback: RustlsConnector::default(),
+        }
+    }
+}
+
+impl<In: Transport> Connector<In> for TlsConnector {
+    type Out = Either<In, Box<dyn Transport>>;
+
+    fn connect(
+        &self,
+        details: &ConnectionDetails,
+        chained: Option<In>,
+    ) -> Result<Option<Self::Out>, Error> {
+        let Some(config) = &self.config else {
+            let transport = self.fallback.connect(details, chained)?;
+            return Ok(transport.map(|transport| match transport {
+                Either::A(transport) => Either::A(transport),
+                Either::B(transport) => Either::B(transport.boxed()),
+            }));
+        };
+        let Some(transport) = chained else {
+            return Ok(None);
+        };
+        if !details.needs_tls() || transport.is_tls() {
+            return Ok(Some(Either::A(transport)));
+        }
+
+        let host = details.uri.host().unwrap_or_default();
+        let name = ServerName::try_from(host.trim_matches(['[', ']']).to_string())
+            .map_err(|_| Error::Tls("invalid server name"))?;
+        let conn = ClientConnection::new(config.clone(), name)
+            .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+        let buffers = LazyBuffers::new(
+            details.config.input_buffer_size(),
+            details.config.output_buffer_size(),
+        );
+        Ok(Some(Either::B(Box::new(TlsTransport {
+            buffers,
+            stream: StreamOwned::new(conn, TransportAdapter::new(transport.boxed())),
+        }))))
+    }
+}
+
+struct TlsTransport {
+    buffers: LazyBuffers,
+    stream: StreamOwned<ClientConnection, TransportAdapter>,
+}
+
+impl fmt::Debug for TlsTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TlsTransport").finish()
+    }
+}
+
+impl Transport for TlsTransport {
+    fn buffers(&mut self) -> &mut dyn Buffers {
+        &mut self.buffers
+    }
+
+    fn transmit_output(&mut self, amount: usize, timeout: NextTimeout) -> Result<(), Error> {
+        self.stream.get_mut().set_timeout(timeout);
+        let output = &self.buffers.output()[..amount];
+        self.stream.write_all(output)?;
+        Ok(())
+    }
+
+    fn await_input(&mut self, timeout: NextTimeout) -> Result<bool, Error> {
+        self.stream.get_mut().set_timeout(timeout);
+        let input = self.buffers.input_append_buf();
+        let amount = self.stream.read(input)?;
+        self.buffers.input_appended(amount);
+        Ok(amount > 0)
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.stream.get_mut().get_mut().is_open()
+    }
+
+    fn is_tls(&self) -> bool {
+        true
+    }
+}
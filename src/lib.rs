@@ -5,19 +5,38 @@ extern crate serde_json;
 extern crate time;
 extern crate ureq;
 
+pub mod candle;
+pub mod client;
+mod clock;
+pub mod config;
+pub mod credentials;
 pub mod endpoint;
 pub mod error;
+pub mod http;
+pub mod metrics;
+pub mod orderbook;
+pub mod pnl;
 pub mod private;
 pub mod public;
+pub mod signer;
+pub mod status;
+#[cfg(feature = "websocket")]
+pub mod stream;
+mod trace;
 
+pub use client::GmoCoinClient;
 pub use error::{GmoCoinError, Result as GmoCoinResult};
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use time::UtcOffset;
+
+/// 日本標準時
+pub(crate) const JST: UtcOffset = time::macros::offset!(+9);
 
 /// ## Symbol
 /// validなsymbol
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[allow(non_camel_case_types)]
 pub enum Symbol {
     BTC,
@@ -102,6 +121,8 @@ impl fmt::Display for self::SymbolError {
     }
 }
 
+impl std::error::Error for self::SymbolError {}
+
 impl std::str::FromStr for self::Symbol {
     type Err = self::SymbolError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -143,7 +164,7 @@ impl std::str::FromStr for self::Symbol {
 
 /// ## LevarageSymbol
 /// levarageのvalidなsymbol
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[allow(non_camel_case_types)]
 pub enum LeverageSymbol {
     BTC_JPY,
@@ -202,7 +223,7 @@ impl std::str::FromStr for self::LeverageSymbol {
 
 /// ## Side
 /// 売買
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[allow(non_camel_case_types)]
 pub enum Side {
     BUY,
@@ -231,6 +252,8 @@ impl fmt::Display for self::SideError {
     }
 }
 
+impl std::error::Error for self::SideError {}
+
 impl std::str::FromStr for self::Side {
     type Err = self::SideError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -264,6 +287,28 @@ pub struct Response<T> {
     pub responsetime: String,
 }
 
+/// ## Ack
+/// `data`を返さないAPI(注文変更・キャンセルなど)のレスポンス
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Ack {
+    pub status: usize,
+    pub responsetime: String,
+}
+
+/// ## ApiMessage
+/// `status`が0でないときに返ってくるエラーメッセージ
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiMessage {
+    pub message_code: String,
+    pub message_string: String,
+}
+
+impl fmt::Display for self::ApiMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.message_code, self.message_string)
+    }
+}
+
 /// ## ResponsePage
 ///
 /// Response
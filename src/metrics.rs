@@ -0,0 +1,323 @@
+use crate::error::{GmoCoinError, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// リクエストの所要時間のバケット(秒)
+const DURATION_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+/// WebSocketのメッセージの遅延のバケット(秒)
+const LAG_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// ## Metrics
+/// APIの利用状況のカウンタとヒストグラム
+///
+/// `GmoCoinClient::metrics`で複数のクライアントやストリームで共有できる。
+/// `render`でPrometheusのテキスト形式にする。
+#[derive(Debug, Default)]
+pub struct Metrics {
+    registry: Mutex<Registry>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    /// (method, path)
+    requests: BTreeMap<(String, String), u64>,
+    /// (path, code)
+    errors: BTreeMap<(String, String), u64>,
+    /// path
+    durations: BTreeMap<String, Histogram>,
+    /// method
+    rate_limit_waits: BTreeMap<String, u64>,
+    /// path
+    retries: BTreeMap<String, u64>,
+    /// url
+    ws_reconnects: BTreeMap<String, u64>,
+    /// channel
+    ws_lag: BTreeMap<String, Histogram>,
+}
+
+#[derive(Debug)]
+struct Histogram {
+    buckets: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.buckets.iter().zip(&mut self.counts) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ### request
+    /// REST APIの呼び出しを記録する。`error`があればそのコードも数える
+    pub fn request(
+        &self,
+        method: &str,
+        path: &str,
+        elapsed: Duration,
+        error: Option<&GmoCoinError>,
+    ) {
+        let mut registry = self.registry();
+        *registry
+            .requests
+            .entry((method.to_string(), path.to_string()))
+            .or_default() += 1;
+        registry
+            .durations
+            .entry(path.to_string())
+            .or_insert_with(|| Histogram::new(DURATION_BUCKETS))
+            .observe(elapsed.as_secs_f64());
+        if let Some(error) = error {
+            *registry
+                .errors
+                .entry((path.to_string(), error_code(error)))
+                .or_default() += 1;
+        }
+    }
+
+    /// ### rate_limit_wait
+    /// 流量制限のために送信を待たせた
+    pub fn rate_limit_wait(&self, method: &str) {
+        *self
+            .registry()
+            .rate_limit_waits
+            .entry(method.to_string())
+            .or_default() += 1;
+    }
+
+    /// ### retry
+    /// リクエストを送り直した
+    pub fn retry(&self, path: &str) {
+        *self.registry().retries.entry(path.to_string()).or_default() += 1;
+    }
+
+    /// ### ws_reconnect
+    /// WebSocketに接続し直した
+    pub fn ws_reconnect(&self, url: &str) {
+        *self
+            .registry()
+            .ws_reconnects
+            .entry(url.to_string())
+            .or_default() += 1;
+    }
+
+    /// ### ws_message
+    /// WebSocketのメッセージのタイムスタンプから受け取るまでの遅延を記録する
+    pub fn ws_message(&self, channel: &str, lag: Duration) {
+        self.registry()
+            .ws_lag
+            .entry(channel.to_string())
+            .or_insert_with(|| Histogram::new(LAG_BUCKETS))
+            .observe(lag.as_secs_f64());
+    }
+
+    /// ### render
+    /// Prometheusのテキスト形式にする
+    pub fn render(&self) -> String {
+        let registry = self.registry();
+        let mut out = String::new();
+
+        counter(
+            &mut out,
+            "gmo_coin_requests_total",
+            "REST API requests",
+            registry.requests.iter().map(|((method, path), value)| {
+                (
+                    vec![("method", method.as_str()), ("path", path.as_str())],
+                    *value,
+                )
+            }),
+        );
+        counter(
+            &mut out,
+            "gmo_coin_request_errors_total",
+            "REST API requests that failed, by GMO message code or HTTP status",
+            registry.errors.iter().map(|((path, code), value)| {
+                (
+                    vec![("path", path.as_str()), ("code", code.as_str())],
+                    *value,
+                )
+            }),
+        );
+        histogram(
+            &mut out,
+            "gmo_coin_request_duration_seconds",
+            "REST API request latency",
+            "path",
+            &registry.durations,
+        );
+        counter(
+            &mut out,
+            "gmo_coin_rate_limit_waits_total",
+            "requests delayed by the client side rate limiter",
+            registry
+                .rate_limit_waits
+                .iter()
+                .map(|(method, value)| (vec![("method", method.as_str())], *value)),
+        );
+        counter(
+            &mut out,
+            "gmo_coin_retries_total",
+            "REST API requests sent again",
+            registry
+                .retries
+                .iter()
+                .map(|(path, value)| (vec![("path", path.as_str())], *value)),
+        );
+        counter(
+            &mut out,
+            "gmo_coin_ws_reconnects_total",
+            "WebSocket reconnects",
+            registry
+                .ws_reconnects
+                .iter()
+                .map(|(url, value)| (vec![("url", url.as_str())], *value)),
+        );
+        histogram(
+            &mut out,
+            "gmo_coin_ws_message_lag_seconds",
+            "delay between the message timestamp and its arrival",
+            "channel",
+            &registry.ws_lag,
+        );
+
+        out
+    }
+
+    fn registry(&self) -> std::sync::MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// ## serve
+/// `listener`で受けたHTTPリクエストに`render`の結果を返す
+///
+/// パスは問わない。接続ごとにスレッドを分け、10秒以内にリクエストを送ってこない接続は閉じる。
+/// 接続ごとのエラーや受け付けの失敗は無視して続ける。
+pub fn serve(listener: &TcpListener, metrics: &Metrics) -> Result<()> {
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            // 1つの接続の失敗で止めない
+            let Ok(stream) = stream else {
+                // ファイルディスクリプタが尽きたときなどに空回りしない
+                std::thread::sleep(ACCEPT_RETRY);
+                continue;
+            };
+            scope.spawn(move || {
+                let _ = respond(stream, metrics);
+            });
+        }
+    });
+    Ok(())
+}
+
+const SERVE_TIMEOUT: Duration = Duration::from_secs(10);
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> Result<()> {
+    stream.set_read_timeout(Some(SERVE_TIMEOUT))?;
+    stream.set_write_timeout(Some(SERVE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let body = metrics.render();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// エラーのラベル。APIのエラーはメッセージコード、HTTPのエラーはステータス
+fn error_code(error: &GmoCoinError) -> String {
+    match error {
+        GmoCoinError::Api { status, messages } => messages
+            .first()
+            .map(|message| message.message_code.clone())
+            .unwrap_or_else(|| format!("status_{}", status)),
+        GmoCoinError::Ureq(ureq::Error::StatusCode(status)) => format!("http_{}", status),
+        GmoCoinError::Ureq(ureq::Error::Timeout(_)) => "timeout".to_string(),
+        GmoCoinError::Ureq(_) => "transport".to_string(),
+        _ => "other".to_string(),
+    }
+}
+
+fn counter<'a, I: Iterator<Item = (Vec<(&'a str, &'a str)>, u64)>>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    samples: I,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{} {}", name, labels_text(&labels), value);
+    }
+}
+
+fn histogram(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    histograms: &BTreeMap<String, Histogram>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (key, histogram) in histograms {
+        for (bound, count) in histogram.buckets.iter().zip(&histogram.counts) {
+            let le = bound.to_string();
+            let labels = labels_text(&[(label, key), ("le", &le)]);
+            let _ = writeln!(out, "{}_bucket{} {}", name, labels, count);
+        }
+        let labels = labels_text(&[(label, key), ("le", "+Inf")]);
+        let _ = writeln!(out, "{}_bucket{} {}", name, labels, histogram.count);
+        let labels = labels_text(&[(label, key)]);
+        let _ = writeln!(out, "{}_sum{} {}", name, labels, histogram.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, labels, histogram.count);
+    }
+}
+
+fn labels_text(labels: &[(&str, &str)]) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", key, value)
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
@@ -0,0 +1,231 @@
+use crate::{
+    Response, Side,
+    error::{GmoCoinError, Result},
+    public::api::{Ask, Bid, Snapshot},
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+/// ## OrderBookUpdate
+/// WebSocketの`orderbooks`チャンネルで配信される板情報
+///
+/// GMOコインは差分ではなく板全体を配信するので、
+/// 受信するたびに板を置き換える。
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderBookUpdate {
+    pub channel: String,
+    pub asks: Vec<Ask>,
+    pub bids: Vec<Bid>,
+    pub symbol: String,
+    pub timestamp: String,
+}
+
+/// ## Level
+/// 板の1価格帯
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Level {
+    pub price: Decimal,
+    pub size: Decimal,
+}
+
+/// ## OrderBook
+/// RESTの`orderbooks`とWebSocketの板情報から組み立てるローカルの板
+///
+/// 価格は数値として保持するので、常に価格順に並んでいる。
+#[derive(Debug, Default)]
+pub struct OrderBook {
+    symbol: Option<String>,
+    asks: BTreeMap<Decimal, Decimal>,
+    bids: BTreeMap<Decimal, Decimal>,
+    timestamp: Option<OffsetDateTime>,
+    received_at: Option<Instant>,
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ### from_response
+    /// RESTの`orderbooks`のレスポンスから板を作る
+    pub fn from_response(resp: &Response<Snapshot>) -> Result<Self> {
+        let mut book = Self::new();
+        book.apply_response(resp)?;
+        Ok(book)
+    }
+
+    /// ### apply_response
+    /// RESTの`orderbooks`のレスポンスで板を置き換える
+    ///
+    /// 板の時刻には`responsetime`を使う。
+    pub fn apply_response(&mut self, resp: &Response<Snapshot>) -> Result<bool> {
+        self.replace(
+            &resp.data.symbol,
+            &resp.data.asks,
+            &resp.data.bids,
+            &resp.responsetime,
+        )
+    }
+
+    /// ### apply_update
+    /// WebSocketの板情報で板を置き換える
+    ///
+    /// 保持している板より古い板を受け取った場合は何もせず`false`を返す。
+    pub fn apply_update(&mut self, update: &OrderBookUpdate) -> Result<bool> {
+        self.replace(
+            &update.symbol,
+            &update.asks,
+            &update.bids,
+            &update.timestamp,
+        )
+    }
+
+    fn replace(
+        &mut self,
+        symbol: &str,
+        asks: &[Ask],
+        bids: &[Bid],
+        timestamp: &str,
+    ) -> Result<bool> {
+        if let Some(expected) = &self.symbol
+            && expected != symbol
+        {
+            return Err(GmoCoinError::SymbolMismatch {
+                expected: expected.clone(),
+                actual: symbol.to_string(),
+            });
+        }
+
+        let timestamp = OffsetDateTime::parse(timestamp, &Rfc3339)?;
+        if self.timestamp.is_some_and(|current| timestamp < current) {
+            return Ok(false);
+        }
+
+        let asks = levels(asks.iter().map(|a| (a.price.as_str(), a.size.as_str())))?;
+        let bids = levels(bids.iter().map(|b| (b.price.as_str(), b.size.as_str())))?;
+
+        self.symbol = Some(symbol.to_string());
+        self.asks = asks;
+        self.bids = bids;
+        self.timestamp = Some(timestamp);
+        self.received_at = Some(Instant::now());
+        Ok(true)
+    }
+
+    pub fn symbol(&self) -> Option<&str> {
+        self.symbol.as_deref()
+    }
+
+    /// ### timestamp
+    /// 最後に取り込んだ板の取引所側の時刻
+    pub fn timestamp(&self) -> Option<OffsetDateTime> {
+        self.timestamp
+    }
+
+    /// ### asks
+    /// 売り板を最良気配から順に返す
+    pub fn asks(&self) -> impl Iterator<Item = Level> + '_ {
+        self.asks
+            .iter()
+            .map(|(&price, &size)| Level { price, size })
+    }
+
+    /// ### bids
+    /// 買い板を最良気配から順に返す
+    pub fn bids(&self) -> impl Iterator<Item = Level> + '_ {
+        self.bids
+            .iter()
+            .rev()
+            .map(|(&price, &size)| Level { price, size })
+    }
+
+    pub fn best_ask(&self) -> Option<Level> {
+        self.asks().next()
+    }
+
+    pub fn best_bid(&self) -> Option<Level> {
+        self.bids().next()
+    }
+
+    pub fn spread(&self) -> Option<Decimal> {
+        Some(self.best_ask()?.price - self.best_bid()?.price)
+    }
+
+    pub fn mid(&self) -> Option<Decimal> {
+        Some((self.best_ask()?.price + self.best_bid()?.price) / Decimal::TWO)
+    }
+
+    /// ### microprice
+    /// 最良気配の数量で重み付けした仲値
+    pub fn microprice(&self) -> Option<Decimal> {
+        let ask = self.best_ask()?;
+        let bid = self.best_bid()?;
+        let total = ask.size + bid.size;
+        if total.is_zero() {
+            return None;
+        }
+
+        Some((bid.price * ask.size + ask.price * bid.size) / total)
+    }
+
+    /// ### is_crossed
+    /// 最良買い気配が最良売り気配以上になっているか
+    pub fn is_crossed(&self) -> bool {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => bid.price >= ask.price,
+            _ => false,
+        }
+    }
+
+    /// ### is_stale
+    /// 最後に板を取り込んでから`max_age`以上経っているか
+    ///
+    /// 一度も取り込んでいない場合も`true`を返す。
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.received_at
+            .is_none_or(|received_at| received_at.elapsed() >= max_age)
+    }
+
+    /// ### depth_to
+    /// 最良気配から`price`までの累積数量
+    ///
+    /// `Side::BUY`は買い板、`Side::SELL`は売り板を見る。
+    pub fn depth_to(&self, side: Side, price: Decimal) -> Decimal {
+        match side {
+            Side::BUY => self.bids.range(price..).map(|(_, size)| *size).sum(),
+            Side::SELL => self.asks.range(..=price).map(|(_, size)| *size).sum(),
+        }
+    }
+
+    /// ### size_within_ticks
+    /// 最良気配から`ticks`ティック以内にある数量
+    pub fn size_within_ticks(&self, side: Side, ticks: u32, tick_size: Decimal) -> Decimal {
+        let distance = tick_size * Decimal::from(ticks);
+        match side {
+            Side::BUY => self.best_bid().map_or(Decimal::ZERO, |best| {
+                self.depth_to(side, best.price - distance)
+            }),
+            Side::SELL => self.best_ask().map_or(Decimal::ZERO, |best| {
+                self.depth_to(side, best.price + distance)
+            }),
+        }
+    }
+}
+
+fn levels<'a>(
+    entries: impl Iterator<Item = (&'a str, &'a str)>,
+) -> Result<BTreeMap<Decimal, Decimal>> {
+    let mut levels = BTreeMap::new();
+    for (price, size) in entries {
+        let size = Decimal::from_str(size)?;
+        if !size.is_zero() {
+            levels.insert(Decimal::from_str(price)?, size);
+        }
+    }
+
+    Ok(levels)
+}
@@ -0,0 +1,254 @@
+use crate::{
+    LeverageSymbol, Side, Symbol,
+    error::Result,
+    private::api::{Execution, LatestExecutions, OpenPositions},
+    public::api::LatestRate,
+};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+/// 約定1件
+#[derive(Debug, Clone)]
+struct Fill {
+    symbol: String,
+    side: Side,
+    price: Decimal,
+    size: Decimal,
+    fee: Decimal,
+    loss_gain: Decimal,
+}
+
+/// ## SymbolPnl
+/// 銘柄ごとの損益
+///
+/// `realized`は手数料を引く前の実現損益で、`net`で手数料と含み損益をまとめる。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolPnl {
+    pub realized: Decimal,
+    pub fees: Decimal,
+    /// 現在の価格での含み損益
+    pub unrealized: Decimal,
+    /// 現物は保有数量、レバレッジは買い建玉から売り建玉を引いた数量
+    pub position: Decimal,
+    /// 現物の平均取得単価。保有していなければ`None`
+    pub average_cost: Option<Decimal>,
+    /// 期間内の約定の数
+    pub executions: usize,
+}
+
+impl SymbolPnl {
+    pub fn net(&self) -> Decimal {
+        self.realized - self.fees + self.unrealized
+    }
+}
+
+/// ## PnlTotal
+/// 全銘柄の損益の合計
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PnlTotal {
+    pub realized: Decimal,
+    pub fees: Decimal,
+    pub unrealized: Decimal,
+}
+
+impl PnlTotal {
+    pub fn net(&self) -> Decimal {
+        self.realized - self.fees + self.unrealized
+    }
+}
+
+/// ## PnlReport
+/// `PnlEngine::report`の結果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PnlReport {
+    pub symbols: BTreeMap<String, SymbolPnl>,
+    /// 価格が無く、含み損益を計算できなかった銘柄
+    pub unpriced: Vec<String>,
+}
+
+impl PnlReport {
+    pub fn total(&self) -> PnlTotal {
+        self.symbols
+            .values()
+            .fold(PnlTotal::default(), |total, pnl| PnlTotal {
+                realized: total.realized + pnl.realized,
+                fees: total.fees + pnl.fees,
+                unrealized: total.unrealized + pnl.unrealized,
+            })
+    }
+}
+
+/// ## PnlEngine
+/// 約定・建玉・最新レートから損益を計算する
+///
+/// 現物(`BTC`など)は約定を古い順にたどって移動平均法で取得単価を求め、売却ごとに実現損益を出す。
+/// 最初の約定より前から持っていた分は取得単価が分からないので、その売却は実現損益に含めない。
+/// レバレッジ(`BTC_JPY`など)の実現損益は決済約定の`lossGain`を使う。
+/// 含み損益は`set_open_positions`の建玉と`update_price`の価格(買いはbid、売りはask)で計算する。
+#[derive(Debug, Default)]
+pub struct PnlEngine {
+    /// 約定日時と約定IDの順
+    fills: BTreeMap<(OffsetDateTime, usize), Fill>,
+    positions: BTreeMap<String, Vec<OpenPositions>>,
+    /// bid, ask
+    prices: BTreeMap<String, (Decimal, Decimal)>,
+}
+
+impl PnlEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ### add_execution
+    /// 約定を加える。同じ約定を何度加えても1回と数える
+    pub fn add_execution(&mut self, execution: &Execution) -> Result<()> {
+        self.add_fill(
+            execution.execution_id,
+            &execution.timestamp,
+            Fill {
+                symbol: execution.symbol.clone(),
+                side: Side::from_str(&execution.side)?,
+                price: Decimal::from_str(&execution.price)?,
+                size: Decimal::from_str(&execution.size)?,
+                fee: Decimal::from_str(&execution.fee)?,
+                loss_gain: Decimal::from_str(&execution.loss_gain)?,
+            },
+        )
+    }
+
+    pub fn add_latest_executions(&mut self, executions: &[LatestExecutions]) -> Result<()> {
+        for execution in executions {
+            self.add_fill(
+                execution.execution_id,
+                &execution.timestamp,
+                Fill {
+                    symbol: execution.symbol.clone(),
+                    side: Side::from_str(&execution.side)?,
+                    price: Decimal::from_str(&execution.price)?,
+                    size: Decimal::from_str(&execution.size)?,
+                    fee: Decimal::from_str(&execution.fee)?,
+                    loss_gain: Decimal::from_str(&execution.loss_gain)?,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// ### set_open_positions
+    /// `symbol`の有効建玉を置き換える
+    pub fn set_open_positions(&mut self, symbol: Symbol, positions: &[OpenPositions]) {
+        self.positions
+            .insert(symbol.to_string(), positions.to_vec());
+    }
+
+    /// ### update_price
+    /// `ticker`(`TickerUpdate`からも変換できる)で銘柄の価格を更新する
+    pub fn update_price(&mut self, rate: &LatestRate) -> Result<()> {
+        let bid = Decimal::from_str(&rate.bid)?;
+        let ask = Decimal::from_str(&rate.ask)?;
+        self.prices.insert(rate.symbol.clone(), (bid, ask));
+        Ok(())
+    }
+
+    /// ### report
+    /// `from`以降`to`より前の約定の実現損益・手数料と、現在の含み損益
+    ///
+    /// `None`なら期間の端を区切らない。
+    pub fn report(
+        &self,
+        from: Option<OffsetDateTime>,
+        to: Option<OffsetDateTime>,
+    ) -> Result<PnlReport> {
+        let mut report = PnlReport::default();
+        let mut holdings: BTreeMap<&str, (Decimal, Decimal)> = BTreeMap::new();
+
+        for ((timestamp, _), fill) in &self.fills {
+            let in_window =
+                from.is_none_or(|from| *timestamp >= from) && to.is_none_or(|to| *timestamp < to);
+            let pnl = report.symbols.entry(fill.symbol.clone()).or_default();
+            let realized = if is_leverage(&fill.symbol) {
+                fill.loss_gain
+            } else {
+                let (size, cost) = holdings.entry(&fill.symbol).or_default();
+                spot_fill(size, cost, fill)
+            };
+            if in_window {
+                pnl.realized += realized;
+                pnl.fees += fill.fee;
+                pnl.executions += 1;
+            }
+        }
+
+        for (symbol, (size, cost)) in holdings {
+            let pnl = report.symbols.entry(symbol.to_string()).or_default();
+            pnl.position = size;
+            if size.is_zero() {
+                continue;
+            }
+            pnl.average_cost = Some(cost);
+            match self.prices.get(symbol) {
+                Some((bid, _)) => pnl.unrealized = (*bid - cost) * size,
+                None => report.unpriced.push(symbol.to_string()),
+            }
+        }
+
+        for (symbol, positions) in &self.positions {
+            let price = self.prices.get(symbol);
+            let pnl = report.symbols.entry(symbol.clone()).or_default();
+            for position in positions {
+                let side = Side::from_str(&position.side)?;
+                let size = Decimal::from_str(&position.size)?;
+                let open = Decimal::from_str(&position.price)?;
+                pnl.position += match side {
+                    Side::BUY => size,
+                    Side::SELL => -size,
+                };
+                // 価格が無ければ取引所が計算した評価損益を使う
+                pnl.unrealized += match (price, side) {
+                    (Some((bid, _)), Side::BUY) => (*bid - open) * size,
+                    (Some((_, ask)), Side::SELL) => (open - *ask) * size,
+                    (None, _) => Decimal::from_str(&position.loss_gain)?,
+                };
+            }
+            if price.is_none() && !positions.is_empty() {
+                report.unpriced.push(symbol.clone());
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn add_fill(&mut self, execution_id: usize, timestamp: &str, fill: Fill) -> Result<()> {
+        let timestamp = OffsetDateTime::parse(timestamp, &Rfc3339)?;
+        self.fills.insert((timestamp, execution_id), fill);
+        Ok(())
+    }
+}
+
+/// レバレッジ取引の銘柄か
+fn is_leverage(symbol: &str) -> bool {
+    LeverageSymbol::from_str(symbol).is_ok()
+}
+
+/// 現物の約定で保有数量と平均取得単価を更新し、実現損益を返す
+fn spot_fill(size: &mut Decimal, cost: &mut Decimal, fill: &Fill) -> Decimal {
+    match fill.side {
+        Side::BUY => {
+            let total = *size + fill.size;
+            *cost = (*cost * *size + fill.price * fill.size) / total;
+            *size = total;
+            Decimal::ZERO
+        }
+        Side::SELL => {
+            let sold = fill.size.min(*size);
+            let realized = (fill.price - *cost) * sold;
+            *size -= sold;
+            if size.is_zero() {
+                *cost = Decimal::ZERO;
+            }
+            realized
+        }
+    }
+}
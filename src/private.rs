@@ -1,4 +1,8 @@
 pub mod api;
 #[cfg(feature = "async")]
 pub mod async_api;
-//TODO: pub mod websock_api;
+pub mod risk;
+pub mod tracker;
+pub mod validation;
+#[cfg(feature = "websocket")]
+pub mod websock_api;
@@ -1,62 +1,132 @@
 use crate::{
-    LeverageSymbol, Response, ResponseList, ResponsePage, Side, Symbol, endpoint,
-    error::{GmoCoinError, Result},
+    Ack, LeverageSymbol, Response, ResponseList, ResponsePage, Side, Symbol, client::shared_client,
+    credentials::Secret, error::Result, signer::Signer,
 };
 use ring::hmac;
-use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use std::time::{SystemTime, UNIX_EPOCH};
-use std::{env, fmt};
-use ureq::http::{HeaderMap, header::HeaderValue};
+use std::fmt;
 
-type UreqResponse = ureq::http::Response<ureq::Body>;
+pub(crate) type UreqResponse = ureq::http::Response<ureq::Body>;
 
-fn response_body(mut response: UreqResponse) -> Result<String> {
-    Ok(response.body_mut().read_to_string()?)
+pub(crate) fn sign_request(
+    timestamp: u64,
+    method: &str,
+    path: &str,
+    body: &str,
+    secret_key: &str,
+) -> String {
+    let text = format!("{}{}{}{}", timestamp, method, path, body);
+    let signed_key = hmac::Key::new(hmac::HMAC_SHA256, secret_key.as_bytes());
+    hex::encode(hmac::sign(&signed_key, text.as_bytes()).as_ref())
 }
 
-fn parse_response<T: DeserializeOwned>(response: UreqResponse) -> Result<T> {
-    let body = response_body(response)?;
-    Ok(serde_json::from_str(&body)?)
+/// ## UnsignedRequest
+/// 署名前のリクエスト
+///
+/// `*_request`関数で作り、`GmoCoinClient::sign`で署名する。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsignedRequest {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub query: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl UnsignedRequest {
+    fn get(path: &'static str, query: Value) -> Self {
+        Self {
+            method: "GET",
+            path,
+            query: query_pairs(query),
+            body: String::new(),
+        }
+    }
+
+    fn post(path: &'static str, payload: Value) -> Self {
+        Self::with_body("POST", path, payload)
+    }
+
+    fn with_body(method: &'static str, path: &'static str, payload: Value) -> Self {
+        Self {
+            method,
+            path,
+            query: Vec::new(),
+            body: payload.to_string(),
+        }
+    }
 }
 
-fn api_key() -> Result<String> {
-    Ok(env::var("GMO_COIN_API_KEY")?)
+/// ## SignedRequest
+/// 署名済みで送信前のリクエスト
+///
+/// `GmoCoinClient::sign`で作り、`GmoCoinClient::send`で送信する。
+/// 送信せずに内容を確認したい場合(dry-run)にも使う。
+/// `Debug`は`api_key`と`sign`を`***`にする。
+#[derive(Clone)]
+pub struct SignedRequest {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub query: Vec<(String, String)>,
+    pub body: String,
+    pub base_url: String,
+    pub timestamp: u64,
+    pub api_key: Secret,
+    pub sign: String,
+}
+
+impl SignedRequest {
+    pub(crate) fn new(
+        request: UnsignedRequest,
+        base_url: &str,
+        timestamp: u64,
+        api_key: &Secret,
+        signer: &dyn Signer,
+    ) -> Result<Self> {
+        let sign = signer.sign(timestamp, request.method, request.path, &request.body)?;
+
+        Ok(Self {
+            method: request.method,
+            path: request.path,
+            query: request.query,
+            body: request.body,
+            base_url: base_url.to_string(),
+            timestamp,
+            api_key: api_key.clone(),
+            sign,
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("{}{}", self.base_url, self.path)
+    }
 }
 
-fn secret_key() -> Result<String> {
-    Ok(env::var("GMO_COIN_SECRET_KEY")?)
+impl fmt::Debug for self::SignedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SignedRequest")
+            .field("method", &self.method)
+            .field("path", &self.path)
+            .field("query", &self.query)
+            .field("body", &self.body)
+            .field("base_url", &self.base_url)
+            .field("timestamp", &self.timestamp)
+            .field("api_key", &self.api_key)
+            .field("sign", &"***")
+            .finish()
+    }
 }
 
-fn insert_header(
-    headers: &mut HeaderMap<HeaderValue>,
-    key: &'static str,
-    value: &str,
-) -> Result<()> {
-    headers.insert(key, HeaderValue::from_str(value)?);
-    Ok(())
+/// ## send
+/// 署名済みのリクエストを送信する
+pub fn send(request: &SignedRequest) -> Result<UreqResponse> {
+    shared_client().send(request)
 }
 
-fn set_request_headers<B>(
-    request: &mut ureq::RequestBuilder<B>,
-    timestamp: u64,
-    sign: &str,
-    api_key: &str,
-) -> Result<()> {
-    let timestamp_string = timestamp.to_string();
-    let headers = request
-        .headers_mut()
-        .ok_or(GmoCoinError::MissingRequestHeaders)?;
-    insert_header(headers, "API-KEY", api_key)?;
-    insert_header(headers, "API-TIMESTAMP", &timestamp_string)?;
-    insert_header(headers, "API-SIGN", sign)?;
-    Ok(())
-}
-
-fn sign_request(timestamp: u64, method: &str, path: &str, body: &str, secret_key: &str) -> String {
-    let text = format!("{}{}{}{}", timestamp, method, path, body);
-    let signed_key = hmac::Key::new(hmac::HMAC_SHA256, secret_key.as_bytes());
-    hex::encode(hmac::sign(&signed_key, text.as_bytes()).as_ref())
+/// 署名して送信し、レスポンスは読まずに返す
+fn send_unchecked(request: UnsignedRequest) -> Result<UreqResponse> {
+    let client = shared_client();
+    client.send(&client.sign(request)?)
 }
 
 /// ## Margin
@@ -71,10 +141,11 @@ pub struct Margin {
 }
 
 pub fn margin() -> Result<Response<Margin>> {
-    let path = "/v1/account/margin";
-    let resp = get_without_params(path)?;
+    shared_client().margin()
+}
 
-    parse_response(resp)
+pub fn margin_request() -> UnsignedRequest {
+    UnsignedRequest::get("/v1/account/margin", Value::Null)
 }
 
 /// ## Asset
@@ -89,19 +160,21 @@ pub struct Assets {
 }
 
 pub fn assets() -> Result<Response<Vec<Assets>>> {
-    let path = "/v1/account/assets";
-    let resp = get_without_params(path)?;
+    shared_client().assets()
+}
 
-    parse_response(resp)
+pub fn assets_request() -> UnsignedRequest {
+    UnsignedRequest::get("/v1/account/assets", Value::Null)
 }
 
 /// ## TradingVolume
 /// 取引高情報
 pub fn trading_volume() -> Result<Response<Value>> {
-    let path = "/v1/account/tradingVolume";
-    let resp = get_without_params(path)?;
+    shared_client().trading_volume()
+}
 
-    parse_response(resp)
+pub fn trading_volume_request() -> UnsignedRequest {
+    UnsignedRequest::get("/v1/account/tradingVolume", Value::Null)
 }
 
 /// ## Order
@@ -131,15 +204,16 @@ pub struct OrderInfo {
 /// ### Parameters
 ///  - order_id: 注文ID（カンマ区切りで複数指定可）
 pub fn orders<T: ToString>(order_id: T) -> Result<ResponseList<OrderInfo>> {
-    let path = "/v1/orders";
-    let resp = get_with_params(path, json!({ "orderId": order_id.to_string() }))?;
+    shared_client().orders(order_id)
+}
 
-    parse_response(resp)
+pub fn orders_request<T: ToString>(order_id: T) -> UnsignedRequest {
+    UnsignedRequest::get("/v1/orders", json!({ "orderId": order_id.to_string() }))
 }
 
 /// ## ActiveOrder
 /// 有効注文
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ActiveOrders {
     pub root_order_id: usize,
@@ -165,15 +239,21 @@ pub fn active_orders(
     page: Option<usize>,
     count: Option<usize>,
 ) -> Result<ResponsePage<ActiveOrders>> {
-    let path = "/v1/activeOrders";
+    shared_client().active_orders(symbol, page, count)
+}
+
+pub fn active_orders_request(
+    symbol: Symbol,
+    page: Option<usize>,
+    count: Option<usize>,
+) -> UnsignedRequest {
     let query = json!({
         "symbol": format!("{}", symbol),
         "page": page.unwrap_or(1),
         "count": count.unwrap_or(100)
     });
 
-    let resp = get_with_params(path, query)?;
-    parse_response(resp)
+    UnsignedRequest::get("/v1/activeOrders", query)
 }
 
 /// ## Execution
@@ -222,14 +302,16 @@ impl fmt::Display for self::ExecutionsParam {
 /// ## executions
 /// 約定情報(Execution)の取得
 pub fn executions(param: ExecutionsParam) -> Result<ResponseList<Execution>> {
-    let path = "/v1/executions";
+    shared_client().executions(param)
+}
+
+pub fn executions_request(param: ExecutionsParam) -> UnsignedRequest {
     let query = match param {
         ExecutionsParam::OrderId(value) => json!({ "orderId": value }),
         ExecutionsParam::ExecutionId(value) => json!({ "executionId": value }),
     };
 
-    let resp = get_with_params(path, query)?;
-    parse_response(resp)
+    UnsignedRequest::get("/v1/executions", query)
 }
 
 /// ## LatestExecution
@@ -256,20 +338,26 @@ pub fn latest_executions(
     page: Option<usize>,
     count: Option<usize>,
 ) -> Result<ResponsePage<LatestExecutions>> {
-    let path = "/v1/latestExecutions";
+    shared_client().latest_executions(symbol, page, count)
+}
+
+pub fn latest_executions_request(
+    symbol: Symbol,
+    page: Option<usize>,
+    count: Option<usize>,
+) -> UnsignedRequest {
     let query = json!({
         "symbol":format!("{}", symbol),
         "page":page.unwrap_or(1),
         "count":count.unwrap_or(100)
     });
 
-    let resp = get_with_params(path, query)?;
-    parse_response(resp)
+    UnsignedRequest::get("/v1/latestExecutions", query)
 }
 
 /// ## OpenPosition
 /// 有効建玉
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OpenPositions {
     pub position_id: usize,
@@ -291,15 +379,21 @@ pub fn open_positions(
     page: Option<usize>,
     count: Option<usize>,
 ) -> Result<ResponsePage<OpenPositions>> {
-    let path = "/v1/openPositions";
+    shared_client().open_positions(symbol, page, count)
+}
+
+pub fn open_positions_request(
+    symbol: Symbol,
+    page: Option<usize>,
+    count: Option<usize>,
+) -> UnsignedRequest {
     let query = json!({
         "symbol":format!("{}", symbol),
         "page":page.unwrap_or(1),
         "count":count.unwrap_or(100)
     });
 
-    let resp = get_with_params(path, query)?;
-    parse_response(resp)
+    UnsignedRequest::get("/v1/openPositions", query)
 }
 
 /// ## PositionSummary
@@ -318,14 +412,16 @@ pub struct PositionSummary {
 /// ## position_summary
 /// 建玉サマリの取得
 pub fn position_summary(symbol: Symbol) -> Result<ResponseList<PositionSummary>> {
-    let path = "/v1/positionSummary";
+    shared_client().position_summary(symbol)
+}
+
+pub fn position_summary_request(symbol: Symbol) -> UnsignedRequest {
     let query = json!({ "symbol": format!("{}", symbol) });
 
-    let resp = get_with_params(path, query)?;
-    parse_response(resp)
+    UnsignedRequest::get("/v1/positionSummary", query)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExecutionType {
     MARKET,
     LIMIT,
@@ -342,6 +438,33 @@ impl fmt::Display for self::ExecutionType {
     }
 }
 
+#[derive(Debug)]
+pub enum ExecutionTypeError {
+    ExecutionTypeParseError,
+}
+
+impl fmt::Display for self::ExecutionTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecutionTypeError::ExecutionTypeParseError => write!(f, "Can't parse execution type"),
+        }
+    }
+}
+
+impl std::error::Error for self::ExecutionTypeError {}
+
+impl std::str::FromStr for self::ExecutionType {
+    type Err = self::ExecutionTypeError;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "MARKET" | "market" => Ok(ExecutionType::MARKET),
+            "LIMIT" | "limit" => Ok(ExecutionType::LIMIT),
+            "STOP" | "stop" => Ok(ExecutionType::STOP),
+            _ => Err(ExecutionTypeError::ExecutionTypeParseError),
+        }
+    }
+}
+
 /// ## order
 /// 新規注文
 pub fn order(
@@ -351,6 +474,18 @@ pub fn order(
     price: Option<String>,
     size: String,
 ) -> Result<Response<String>> {
+    shared_client().order(symbol, side, execution_type, price, size)
+}
+
+/// ## order_request
+/// 新規注文のリクエストを作る
+pub fn order_request(
+    symbol: Symbol,
+    side: Side,
+    execution_type: ExecutionType,
+    price: Option<String>,
+    size: String,
+) -> UnsignedRequest {
     let path = "/v1/order";
     let mut payload = json!({
         "symbol": format!("{}", symbol),
@@ -363,9 +498,7 @@ pub fn order(
         payload["price"] = json!(price);
     }
 
-    let resp = post_with_params(path, payload)?;
-
-    parse_response(resp)
+    UnsignedRequest::post(path, payload)
 }
 
 /// ## change_order
@@ -375,6 +508,26 @@ pub fn change_order(
     price: String,
     losscut_price: Option<String>,
 ) -> Result<UreqResponse> {
+    send_unchecked(change_order_request(order_id, price, losscut_price))
+}
+
+/// ## change_order_checked
+/// 注文変更。`status`を確かめて`Ack`で返す
+pub fn change_order_checked(
+    order_id: usize,
+    price: String,
+    losscut_price: Option<String>,
+) -> Result<Ack> {
+    shared_client().change_order(order_id, price, losscut_price)
+}
+
+/// ## change_order_request
+/// 注文変更のリクエストを作る
+pub fn change_order_request(
+    order_id: usize,
+    price: String,
+    losscut_price: Option<String>,
+) -> UnsignedRequest {
     let path = "/v1/changeOrder";
     let mut payload = json!({
         "orderId":order_id,
@@ -385,35 +538,71 @@ pub fn change_order(
         payload["losscutPrice"] = json!(losscut_price);
     }
 
-    post_with_params(path, payload)
+    UnsignedRequest::post(path, payload)
 }
 
 /// ## cancel_order
 /// 注文キャンセル
 pub fn cancel_order(order_id: usize) -> Result<UreqResponse> {
+    send_unchecked(cancel_order_request(order_id))
+}
+
+/// ## cancel_order_checked
+/// 注文キャンセル。`status`を確かめて`Ack`で返す
+pub fn cancel_order_checked(order_id: usize) -> Result<Ack> {
+    shared_client().cancel_order(order_id)
+}
+
+/// ## cancel_order_request
+/// 注文キャンセルのリクエストを作る
+pub fn cancel_order_request(order_id: usize) -> UnsignedRequest {
     let path = "/v1/cancelOrder";
     let query = json!({ "orderId": order_id });
 
-    post_with_params(path, query)
+    UnsignedRequest::post(path, query)
 }
 
 /// ## cancel_orders
 /// 複数注文のキャンセル
 pub fn cancel_orders(order_ids: Vec<usize>) -> Result<UreqResponse> {
+    send_unchecked(cancel_orders_request(order_ids))
+}
+
+/// ## cancel_orders_checked
+/// 複数注文のキャンセル。`status`を確かめて型付きで返す
+pub fn cancel_orders_checked(order_ids: Vec<usize>) -> Result<Response<Value>> {
+    shared_client().cancel_orders(order_ids)
+}
+
+/// ## cancel_orders_request
+/// 複数注文のキャンセルのリクエストを作る
+pub fn cancel_orders_request(order_ids: Vec<usize>) -> UnsignedRequest {
     let path = "/v1/cancelOrders";
     let query = json!({ "orderIds": order_ids });
 
-    post_with_params(path, query)
+    UnsignedRequest::post(path, query)
 }
 
 /// ## cancel_bulk_order
 /// 指定銘柄の一括キャンセル
 pub fn cancel_bulk_order(symbols: Vec<Symbol>) -> Result<UreqResponse> {
+    send_unchecked(cancel_bulk_order_request(symbols))
+}
+
+/// ## cancel_bulk_order_checked
+/// 指定銘柄の一括キャンセル。`status`を確かめて型付きで返す
+pub fn cancel_bulk_order_checked(symbols: Vec<Symbol>) -> Result<Response<Value>> {
+    shared_client().cancel_bulk_order(symbols)
+}
+
+/// ## cancel_bulk_order_request
+/// 指定銘柄の一括キャンセルのリクエストを作る
+pub fn cancel_bulk_order_request(symbols: Vec<Symbol>) -> UnsignedRequest {
     let path = "/v1/cancelBulkOrder";
     let symbols: Vec<String> = symbols.into_iter().map(|s| s.to_string()).collect();
     let query = json!({ "symbols": symbols });
 
-    post_with_params(path, query)
+    UnsignedRequest::post(path, query)
 }
 
 /// ## SettlePosition
@@ -434,6 +623,18 @@ pub fn close_order(
     price: Option<String>,
     settle_position: SettlePosition,
 ) -> Result<Response<String>> {
+    shared_client().close_order(symbol, side, execution_type, price, settle_position)
+}
+
+/// ## close_order_request
+/// 決済注文のリクエストを作る
+pub fn close_order_request(
+    symbol: LeverageSymbol,
+    side: Side,
+    execution_type: ExecutionType,
+    price: Option<String>,
+    settle_position: SettlePosition,
+) -> UnsignedRequest {
     let path = "/v1/closeOrder";
     let mut payload = json!({
         "symbol": format!("{}", symbol),
@@ -451,8 +652,7 @@ pub fn close_order(
         payload["price"] = json!(price);
     }
 
-    let resp = post_with_params(path, payload)?;
-    parse_response(resp)
+    UnsignedRequest::post(path, payload)
 }
 
 /// ## close_bulk_order
@@ -464,6 +664,18 @@ pub fn close_bulk_order(
     price: Option<String>,
     size: String,
 ) -> Result<Response<String>> {
+    shared_client().close_bulk_order(symbol, side, execution_type, price, size)
+}
+
+/// ## close_bulk_order_request
+/// 一括決済注文のリクエストを作る
+pub fn close_bulk_order_request(
+    symbol: LeverageSymbol,
+    side: Side,
+    execution_type: ExecutionType,
+    price: Option<String>,
+    size: String,
+) -> UnsignedRequest {
     let path = "/v1/closeBulkOrder";
     let mut payload = json!({
         "symbol": format!("{}", symbol),
@@ -476,71 +688,61 @@ pub fn close_bulk_order(
         payload["price"] = json!(price);
     }
 
-    let resp = post_with_params(path, payload)?;
-    parse_response(resp)
+    UnsignedRequest::post(path, payload)
 }
 
 /// ## change_losscut_price
 /// 建玉のロスカットレート変更
 pub fn change_losscut_price(position_id: usize, losscut_price: String) -> Result<UreqResponse> {
+    send_unchecked(change_losscut_price_request(position_id, losscut_price))
+}
+
+/// ## change_losscut_price_checked
+/// 建玉のロスカットレート変更。`status`を確かめて`Ack`で返す
+pub fn change_losscut_price_checked(position_id: usize, losscut_price: String) -> Result<Ack> {
+    shared_client().change_losscut_price(position_id, losscut_price)
+}
+
+/// ## change_losscut_price_request
+/// ロスカットレート変更のリクエストを作る
+pub fn change_losscut_price_request(position_id: usize, losscut_price: String) -> UnsignedRequest {
     let path = "/v1/changeLosscutPrice";
     let query = json!({
         "positionId": position_id,
         "losscutPrice": losscut_price
     });
 
-    post_with_params(path, query)
+    UnsignedRequest::post(path, query)
 }
 
-fn timestamp() -> Result<u64> {
-    let start = SystemTime::now();
-    let since_epoch = start.duration_since(UNIX_EPOCH)?;
-
-    Ok(since_epoch.as_secs() * 1000 + since_epoch.subsec_nanos() as u64 / 1000000)
+/// ## ws_auth
+/// Private WebSocket APIのアクセストークンを取得する(有効期限は60分)
+pub fn ws_auth() -> Result<Response<String>> {
+    shared_client().ws_auth()
 }
 
-fn get_with_params(path: &'static str, query: Value) -> Result<UreqResponse> {
-    let timestamp = timestamp()?;
-    let api_key = api_key()?;
-    let secret_key = secret_key()?;
-    let sign = sign_request(timestamp, "GET", path, "", &secret_key);
-
-    let url = format!("{}{}", endpoint::PRIVATE_API, path);
-    let mut request = ureq::get(&url);
-    set_request_headers(&mut request, timestamp, &sign, &api_key)?;
-
-    for (key, value) in query_pairs(query) {
-        request = request.query(&key, &value);
-    }
-
-    Ok(request.call()?)
+pub fn ws_auth_request() -> UnsignedRequest {
+    UnsignedRequest::post("/v1/ws-auth", json!({}))
 }
 
-fn post_with_params(path: &'static str, payload: Value) -> Result<UreqResponse> {
-    let timestamp = timestamp()?;
-    let api_key = api_key()?;
-    let secret_key = secret_key()?;
-    let body = payload.to_string();
-    let sign = sign_request(timestamp, "POST", path, &body, &secret_key);
-
-    let url = format!("{}{}", endpoint::PRIVATE_API, path);
-    let mut request = ureq::post(&url).content_type("application/json");
-    set_request_headers(&mut request, timestamp, &sign, &api_key)?;
-
-    Ok(request.send(&body)?)
+/// ## extend_ws_auth
+/// アクセストークンの有効期限を60分に延長する
+pub fn extend_ws_auth(token: &str) -> Result<Ack> {
+    shared_client().extend_ws_auth(token)
 }
 
-fn get_without_params(path: &'static str) -> Result<UreqResponse> {
-    let timestamp = timestamp()?;
-    let api_key = api_key()?;
-    let secret_key = secret_key()?;
-    let sign = sign_request(timestamp, "GET", path, "", &secret_key);
+pub fn extend_ws_auth_request(token: &str) -> UnsignedRequest {
+    UnsignedRequest::with_body("PUT", "/v1/ws-auth", json!({ "token": token }))
+}
 
-    let url = format!("{}{}", endpoint::PRIVATE_API, path);
-    let mut request = ureq::get(&url);
-    set_request_headers(&mut request, timestamp, &sign, &api_key)?;
+/// ## delete_ws_auth
+/// アクセストークンを削除する
+pub fn delete_ws_auth(token: &str) -> Result<Ack> {
+    shared_client().delete_ws_auth(token)
+}
 
-    Ok(request.call()?)
+pub fn delete_ws_auth_request(token: &str) -> UnsignedRequest {
+    UnsignedRequest::with_body("DELETE", "/v1/ws-auth", json!({ "token": token }))
 }
 
 fn query_pairs(query: Value) -> Vec<(String, String)> {
@@ -1,9 +1,26 @@
+//! Private APIのasync版
+//!
+//! `GmoCoinClient`を通さず、環境変数の`GMO_COIN_API_KEY`・`GMO_COIN_SECRET_KEY`で
+//! その場で署名し、ローカルの時計を`API-TIMESTAMP`にする。
+//! そのため`GmoCoinClient`の次の設定はどれも効かない。
+//!
+//! - `signer`(`Signer`に署名を任せる)と設定ファイルのプロファイル
+//! - 時計のずれの補正と、`API-TIMESTAMP`のずれで拒否されたときの送り直し
+//! - `read_only`と`limits`による注文の拒否
+//! - 呼び出し制限・メトリクス・メンテナンスの扱い
+//!
+//! これらが必要なら、同期版の`GmoCoinClient`を`tokio::task::spawn_blocking`などから呼ぶ。
+
 use crate::{
-    LeverageSymbol, Response, ResponseList, ResponsePage, Side, Symbol, endpoint, error::Result,
+    LeverageSymbol, Response, ResponseList, ResponsePage, Side, Symbol,
+    credentials::{CredentialProvider, EnvProvider},
+    endpoint,
+    error::Result,
+    http,
+    trace::{self, RequestLog},
 };
 use ring::hmac;
 use serde_json::{Value, json};
-use std::env;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use super::api::{
@@ -234,14 +251,6 @@ pub async fn change_losscut_price(
     post_with_params(path, query).await
 }
 
-fn api_key() -> Result<String> {
-    Ok(env::var("GMO_COIN_API_KEY")?)
-}
-
-fn secret_key() -> Result<String> {
-    Ok(env::var("GMO_COIN_SECRET_KEY")?)
-}
-
 fn timestamp() -> Result<u64> {
     let start = SystemTime::now();
     let since_epoch = start.duration_since(UNIX_EPOCH)?;
@@ -257,61 +266,70 @@ fn sign_request(timestamp: u64, method: &str, path: &str, body: &str, secret_key
 
 async fn get_with_params(path: &'static str, query: Value) -> Result<reqwest::Response> {
     let timestamp = timestamp()?;
-    let api_key = api_key()?;
-    let secret_key = secret_key()?;
-    let sign = sign_request(timestamp, "GET", path, "", &secret_key);
+    let credentials = EnvProvider::default().credentials()?;
+    let sign = sign_request(timestamp, "GET", path, "", credentials.secret_key.expose());
 
     let url = format!("{}{}", endpoint::PRIVATE_API, path);
-    let client = reqwest::Client::new();
+    let client = http::async_client()?;
 
     let mut request = client
         .get(&url)
-        .header("API-KEY", api_key)
+        .header("API-KEY", credentials.api_key.expose())
         .header("API-TIMESTAMP", format!("{}", timestamp))
         .header("API-SIGN", sign);
 
-    for (key, value) in query_pairs(query) {
+    let query = query_pairs(query);
+    for (key, value) in &query {
         request = request.query(&[(key, value)]);
     }
 
-    Ok(request.send().await?.error_for_status()?)
+    let symbol = trace::symbol(
+        query
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+        "",
+    );
+    trace::send(&RequestLog::new("GET", path, symbol.as_deref()), request).await
 }
 
 async fn post_with_params(path: &'static str, payload: Value) -> Result<reqwest::Response> {
     let timestamp = timestamp()?;
-    let api_key = api_key()?;
-    let secret_key = secret_key()?;
+    let credentials = EnvProvider::default().credentials()?;
     let body = payload.to_string();
-    let sign = sign_request(timestamp, "POST", path, &body, &secret_key);
-
+    let sign = sign_request(
+        timestamp,
+        "POST",
+        path,
+        &body,
+        credentials.secret_key.expose(),
+    );
+
+    let log = RequestLog::new("POST", path, trace::symbol([], &body).as_deref());
     let url = format!("{}{}", endpoint::PRIVATE_API, path);
-    Ok(reqwest::Client::new()
+    let request = http::async_client()?
         .post(&url)
         .header("content-type", "application/json")
-        .header("API-KEY", api_key)
+        .header("API-KEY", credentials.api_key.expose())
         .header("API-TIMESTAMP", format!("{}", timestamp))
         .header("API-SIGN", sign)
-        .body(body)
-        .send()
-        .await?
-        .error_for_status()?)
+        .body(body);
+
+    trace::send(&log, request).await
 }
 
 async fn get_without_params(path: &'static str) -> Result<reqwest::Response> {
     let timestamp = timestamp()?;
-    let api_key = api_key()?;
-    let secret_key = secret_key()?;
-    let sign = sign_request(timestamp, "GET", path, "", &secret_key);
+    let credentials = EnvProvider::default().credentials()?;
+    let sign = sign_request(timestamp, "GET", path, "", credentials.secret_key.expose());
 
     let url = format!("{}{}", endpoint::PRIVATE_API, path);
-    Ok(reqwest::Client::new()
+    let request = http::async_client()?
         .get(&url)
-        .header("API-KEY", api_key)
+        .header("API-KEY", credentials.api_key.expose())
         .header("API-TIMESTAMP", format!("{}", timestamp))
-        .header("API-SIGN", sign)
-        .send()
-        .await?
-        .error_for_status()?)
+        .header("API-SIGN", sign);
+
+    trace::send(&RequestLog::new("GET", path, None), request).await
 }
 
 fn query_pairs(query: Value) -> Vec<(String, String)> {
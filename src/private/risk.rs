@@ -0,0 +1,311 @@
+use crate::Side;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use thiserror::Error;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// メモリに残す拒否の記録の数
+const AUDIT_CAPACITY: usize = 1000;
+
+/// ## RiskViolation
+/// 送信前に拒否した注文の理由
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RiskViolation {
+    #[error("invalid number for {field}: {value}")]
+    InvalidNumber { field: &'static str, value: String },
+
+    #[error("{symbol}: size {size} exceeds max_order_size {max}")]
+    SizeTooLarge {
+        symbol: String,
+        size: Decimal,
+        max: Decimal,
+    },
+
+    #[error("{symbol}: notional {notional} JPY exceeds max_notional {max}")]
+    NotionalTooLarge {
+        symbol: String,
+        notional: Decimal,
+        max: Decimal,
+    },
+
+    #[error("{symbol}: {side} position would be {position}, exceeding max_position {max}")]
+    PositionTooLarge {
+        symbol: String,
+        side: Side,
+        position: Decimal,
+        max: Decimal,
+    },
+
+    #[error(
+        "{symbol}: price {price} deviates {deviation}% from the latest {reference}, exceeding max_price_deviation {max}%"
+    )]
+    PriceDeviation {
+        symbol: String,
+        price: Decimal,
+        reference: Decimal,
+        deviation: Decimal,
+        max: Decimal,
+    },
+
+    #[error("{0}: no latest price to check the order against")]
+    NoReference(String),
+
+    #[error("order {0} not found")]
+    UnknownOrder(usize),
+}
+
+/// ## Limits
+/// 誤発注を防ぐための上限
+///
+/// `symbols`に書いた銘柄はその値で上書きする(書いていない項目は全体の値を使う)。
+///
+/// ```toml
+/// [profiles.main.limits]
+/// max_notional = "1000000"
+/// max_price_deviation = "5"
+/// audit_log = "~/.local/state/gmo_coin/rejections.ndjson"
+///
+/// [profiles.main.limits.symbols.BTC_JPY]
+/// max_order_size = "0.1"
+/// max_position = "0.5"
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limits {
+    /// 1注文の数量の上限
+    pub max_order_size: Option<Decimal>,
+    /// 1注文の約定代金(JPY)の上限
+    pub max_notional: Option<Decimal>,
+    /// 新規注文が約定した後の、同じ売買区分の建玉数量の上限(レバレッジ銘柄のみ)
+    pub max_position: Option<Decimal>,
+    /// 指値・逆指値の価格と最新の約定価格の乖離(%)の上限
+    pub max_price_deviation: Option<Decimal>,
+    /// 銘柄ごとの上書き
+    #[serde(default)]
+    pub symbols: BTreeMap<String, SymbolLimits>,
+    /// 拒否した注文を1行1JSONで追記するファイル
+    pub audit_log: Option<PathBuf>,
+}
+
+/// ## SymbolLimits
+/// 1銘柄に適用する上限
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SymbolLimits {
+    pub max_order_size: Option<Decimal>,
+    pub max_notional: Option<Decimal>,
+    pub max_position: Option<Decimal>,
+    pub max_price_deviation: Option<Decimal>,
+}
+
+/// ## OrderCheck
+/// 検査する注文
+///
+/// `reference`(最新の約定価格)と`position`(同じ売買区分の建玉数量)は、
+/// `SymbolLimits::needs_reference`・`needs_position`が`true`の場合だけ必要。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderCheck {
+    pub symbol: String,
+    pub side: Side,
+    /// 成行なら`None`
+    pub price: Option<Decimal>,
+    pub size: Decimal,
+    /// 決済注文なら`false`
+    pub opens_position: bool,
+    pub reference: Option<Decimal>,
+    pub position: Option<Decimal>,
+}
+
+impl Limits {
+    /// ### for_symbol
+    /// 銘柄ごとの上書きを反映した上限
+    pub fn for_symbol(&self, symbol: &str) -> SymbolLimits {
+        let base = SymbolLimits {
+            max_order_size: self.max_order_size,
+            max_notional: self.max_notional,
+            max_position: self.max_position,
+            max_price_deviation: self.max_price_deviation,
+        };
+        match self.symbols.get(symbol) {
+            Some(limits) => SymbolLimits {
+                max_order_size: limits.max_order_size.or(base.max_order_size),
+                max_notional: limits.max_notional.or(base.max_notional),
+                max_position: limits.max_position.or(base.max_position),
+                max_price_deviation: limits.max_price_deviation.or(base.max_price_deviation),
+            },
+            None => base,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_order_size.is_none()
+            && self.max_notional.is_none()
+            && self.max_position.is_none()
+            && self.max_price_deviation.is_none()
+            && self.symbols.is_empty()
+    }
+}
+
+impl SymbolLimits {
+    /// 最新の約定価格が必要か(成行の約定代金、指値の乖離)
+    pub fn needs_reference(&self, price: Option<Decimal>) -> bool {
+        match price {
+            None => self.max_notional.is_some(),
+            Some(_) => self.max_price_deviation.is_some(),
+        }
+    }
+
+    /// 現在の建玉数量が必要か
+    pub fn needs_position(&self, opens_position: bool) -> bool {
+        opens_position && self.max_position.is_some()
+    }
+
+    /// ### check
+    /// 上限を超えていれば理由を返す
+    pub fn check(&self, order: &OrderCheck) -> Result<(), RiskViolation> {
+        let symbol = || order.symbol.clone();
+
+        if let Some(max) = self.max_order_size
+            && order.size > max
+        {
+            return Err(RiskViolation::SizeTooLarge {
+                symbol: symbol(),
+                size: order.size,
+                max,
+            });
+        }
+
+        if let Some(max) = self.max_notional {
+            let price = order
+                .price
+                .or(order.reference)
+                .ok_or_else(|| RiskViolation::NoReference(symbol()))?;
+            let notional = (price * order.size).normalize();
+            if notional > max {
+                return Err(RiskViolation::NotionalTooLarge {
+                    symbol: symbol(),
+                    notional,
+                    max,
+                });
+            }
+        }
+
+        if let Some(max) = self.max_position
+            && order.opens_position
+            && let Some(position) = order.position
+        {
+            let position = (position + order.size).normalize();
+            if position > max {
+                return Err(RiskViolation::PositionTooLarge {
+                    symbol: symbol(),
+                    side: order.side,
+                    position,
+                    max,
+                });
+            }
+        }
+
+        if let Some(max) = self.max_price_deviation
+            && let Some(price) = order.price
+        {
+            let reference = order
+                .reference
+                .filter(|reference| !reference.is_zero())
+                .ok_or_else(|| RiskViolation::NoReference(symbol()))?;
+            let deviation = ((price - reference).abs() / reference * Decimal::ONE_HUNDRED)
+                .round_dp(2)
+                .normalize();
+            if deviation > max {
+                return Err(RiskViolation::PriceDeviation {
+                    symbol: symbol(),
+                    price,
+                    reference,
+                    deviation,
+                    max,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 文字列の価格・数量を読む
+pub fn number(field: &'static str, value: &str) -> Result<Decimal, RiskViolation> {
+    Decimal::from_str(value).map_err(|_| RiskViolation::InvalidNumber {
+        field,
+        value: value.to_string(),
+    })
+}
+
+/// ## Rejection
+/// 拒否した注文の記録
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rejection {
+    pub timestamp: String,
+    pub symbol: String,
+    pub side: String,
+    pub price: Option<String>,
+    pub size: String,
+    pub reason: String,
+}
+
+impl Rejection {
+    pub fn new(order: &OrderCheck, violation: &RiskViolation) -> Self {
+        Self {
+            timestamp: OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .unwrap_or_default(),
+            symbol: order.symbol.clone(),
+            side: order.side.to_string(),
+            price: order.price.map(|price| price.to_string()),
+            size: order.size.to_string(),
+            reason: violation.to_string(),
+        }
+    }
+}
+
+/// ## RiskAudit
+/// 拒否の記録。新しいものから`AUDIT_CAPACITY`件をメモリに残し、
+/// `audit_log`があればそこにも追記する
+#[derive(Debug, Default)]
+pub struct RiskAudit {
+    path: Option<PathBuf>,
+    rejections: VecDeque<Rejection>,
+}
+
+impl RiskAudit {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            rejections: VecDeque::new(),
+        }
+    }
+
+    /// ### record
+    /// 記録する。ファイルに書けなくても注文の拒否は変わらないので、書き込みのエラーは返さない
+    pub fn record(&mut self, rejection: Rejection) {
+        if let Some(path) = &self.path
+            && let Ok(line) = serde_json::to_string(&rejection)
+            && let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path)
+        {
+            let _ = writeln!(file, "{}", line);
+        }
+
+        if self.rejections.len() == AUDIT_CAPACITY {
+            self.rejections.pop_front();
+        }
+        self.rejections.push_back(rejection);
+    }
+
+    pub fn rejections(&self) -> Vec<Rejection> {
+        self.rejections.iter().cloned().collect()
+    }
+}
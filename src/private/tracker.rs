@@ -0,0 +1,626 @@
+use crate::{
+    GmoCoinClient, Symbol,
+    error::Result,
+    private::api::{ActiveOrders, Execution, ExecutionsParam, LatestExecutions, OrderInfo},
+};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[cfg(feature = "websocket")]
+use crate::{
+    private::websock_api::{ExecutionEvent, OrderEvent, PrivateMessage},
+    stream::Resync,
+};
+
+/// `orders`で一度に問い合わせられる注文の数
+const ORDERS_PER_REQUEST: usize = 10;
+/// `active_orders`の1ページの件数
+const PAGE_SIZE: usize = 100;
+
+/// ## OrderStatus
+/// 注文の状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(non_camel_case_types)]
+pub enum OrderStatus {
+    WAITING,
+    ORDERED,
+    MODIFYING,
+    CANCELLING,
+    CANCELED,
+    EXECUTED,
+    EXPIRED,
+}
+
+impl fmt::Display for self::OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrderStatus::WAITING => write!(f, "WAITING"),
+            OrderStatus::ORDERED => write!(f, "ORDERED"),
+            OrderStatus::MODIFYING => write!(f, "MODIFYING"),
+            OrderStatus::CANCELLING => write!(f, "CANCELLING"),
+            OrderStatus::CANCELED => write!(f, "CANCELED"),
+            OrderStatus::EXECUTED => write!(f, "EXECUTED"),
+            OrderStatus::EXPIRED => write!(f, "EXPIRED"),
+        }
+    }
+}
+
+impl std::str::FromStr for self::OrderStatus {
+    type Err = TrackerError;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "WAITING" => Ok(OrderStatus::WAITING),
+            "ORDERED" => Ok(OrderStatus::ORDERED),
+            "MODIFYING" => Ok(OrderStatus::MODIFYING),
+            "CANCELLING" => Ok(OrderStatus::CANCELLING),
+            "CANCELED" => Ok(OrderStatus::CANCELED),
+            "EXECUTED" => Ok(OrderStatus::EXECUTED),
+            "EXPIRED" => Ok(OrderStatus::EXPIRED),
+            _ => Err(TrackerError::UnknownStatus(s.to_string())),
+        }
+    }
+}
+
+impl OrderStatus {
+    /// 取消・約定・失効のあとは変わらない
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::CANCELED | OrderStatus::EXECUTED | OrderStatus::EXPIRED
+        )
+    }
+
+    /// ### can_become
+    /// `self`から`to`に変わりうるか
+    ///
+    /// 同じ状態のままの通知(一部約定など)は許す。
+    pub fn can_become(&self, to: OrderStatus) -> bool {
+        use OrderStatus::*;
+        if *self == to {
+            return true;
+        }
+        match self {
+            WAITING => to != WAITING,
+            ORDERED => to != WAITING,
+            // 変更・取消の途中でも約定・失効しうる
+            MODIFYING | CANCELLING => to != WAITING,
+            CANCELED | EXECUTED | EXPIRED => false,
+        }
+    }
+}
+
+/// ## TrackerError
+/// 起こりえない注文の変化
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TrackerError {
+    #[error("unknown order status: {0}")]
+    UnknownStatus(String),
+
+    #[error("order {order_id}: impossible transition from {from} to {to}")]
+    Transition {
+        order_id: usize,
+        from: OrderStatus,
+        to: OrderStatus,
+    },
+
+    #[error("order {order_id}: executed size went back from {from} to {to}")]
+    ExecutedSizeDecreased {
+        order_id: usize,
+        from: Decimal,
+        to: Decimal,
+    },
+
+    #[error("order {order_id}: executed size {executed} exceeds order size {size}")]
+    Overfilled {
+        order_id: usize,
+        size: Decimal,
+        executed: Decimal,
+    },
+
+    #[error("order {order_id}: invalid number {value:?}")]
+    InvalidNumber { order_id: usize, value: String },
+
+    /// まとめて取り込んだうち、取り込めなかった注文のエラー
+    #[error("{} orders rejected: {}", .0.len(), .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Rejected(Vec<TrackerError>),
+}
+
+/// ## Fill
+/// 注文の約定1件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fill {
+    pub execution_id: usize,
+    pub order_id: usize,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub fee: Decimal,
+    pub timestamp: String,
+}
+
+/// ## TrackedOrder
+/// `OrderTracker`が持つ注文の状態
+///
+/// `executed_size`は取引所が返す累計で、`filled_size`・`average_price`・`fee`は
+/// `OrderTracker`が受け取った約定から計算する。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackedOrder {
+    pub order_id: usize,
+    pub symbol: String,
+    pub side: String,
+    pub execution_type: String,
+    pub settle_type: String,
+    pub size: Decimal,
+    pub price: String,
+    pub status: OrderStatus,
+    pub executed_size: Decimal,
+    pub filled_size: Decimal,
+    pub fee: Decimal,
+    pub timestamp: String,
+    notional: Decimal,
+}
+
+impl TrackedOrder {
+    /// ### average_price
+    /// 受け取った約定の平均価格。約定が無ければ`None`
+    pub fn average_price(&self) -> Option<Decimal> {
+        (!self.filled_size.is_zero()).then(|| self.notional / self.filled_size)
+    }
+
+    pub fn remaining_size(&self) -> Decimal {
+        self.size - self.executed_size
+    }
+}
+
+/// REST・WebSocketのどちらから来ても同じ形にした注文の状態
+struct OrderState<'a> {
+    order_id: usize,
+    symbol: &'a str,
+    side: &'a str,
+    execution_type: &'a str,
+    settle_type: &'a str,
+    size: &'a str,
+    price: &'a str,
+    status: Option<&'a str>,
+    executed_size: &'a str,
+    timestamp: &'a str,
+}
+
+impl<'a> From<&'a ActiveOrders> for OrderState<'a> {
+    fn from(order: &'a ActiveOrders) -> Self {
+        Self {
+            order_id: order.order_id,
+            symbol: &order.symbol,
+            side: &order.side,
+            execution_type: &order.execution_type,
+            settle_type: &order.settle_type,
+            size: &order.size,
+            price: &order.price,
+            status: Some(&order.status),
+            executed_size: &order.executed_size,
+            timestamp: &order.timestamp,
+        }
+    }
+}
+
+impl<'a> From<&'a OrderInfo> for OrderState<'a> {
+    fn from(order: &'a OrderInfo) -> Self {
+        Self {
+            order_id: order.order_id,
+            symbol: &order.symbol,
+            side: &order.side,
+            execution_type: &order.execution_type,
+            settle_type: &order.settle_type,
+            size: &order.size,
+            price: &order.price,
+            status: Some(&order.status),
+            executed_size: &order.executed_size,
+            timestamp: &order.timestamp,
+        }
+    }
+}
+
+type FillCallback = Box<dyn FnMut(&TrackedOrder, &Fill) + Send>;
+type OrderCallback = Box<dyn FnMut(&TrackedOrder) + Send>;
+
+/// ## OrderTracker
+/// 注文ごとの状態を手元で持つ
+///
+/// `seed`で`active_orders`から始め、Private WebSocket APIの`orderEvents`・`executionEvents`
+/// (`apply_message`)か、`refresh`のポーリングで更新する。
+/// 起こりえない変化(終わった注文が動く、約定数量が減るなど)は`TrackerError`にして取り込まない。
+/// 約定数量が注文数量に達した注文は、どの経路で約定を受け取っても`EXECUTED`にする。
+#[derive(Default)]
+pub struct OrderTracker {
+    orders: BTreeMap<usize, TrackedOrder>,
+    /// 注文ごとの受け取った約定ID。約定をすべて受け取って終わった注文の分は捨てる
+    executions: HashMap<usize, HashSet<usize>>,
+    on_fill: Vec<FillCallback>,
+    on_cancel: Vec<OrderCallback>,
+    on_expire: Vec<OrderCallback>,
+}
+
+impl fmt::Debug for OrderTracker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OrderTracker")
+            .field("orders", &self.orders)
+            .finish()
+    }
+}
+
+impl OrderTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ### on_fill
+    /// 約定を受け取るたびに呼ぶ
+    pub fn on_fill<F: FnMut(&TrackedOrder, &Fill) + Send + 'static>(mut self, callback: F) -> Self {
+        self.on_fill.push(Box::new(callback));
+        self
+    }
+
+    /// ### on_cancel
+    /// 注文が取り消されたときに呼ぶ
+    pub fn on_cancel<F: FnMut(&TrackedOrder) + Send + 'static>(mut self, callback: F) -> Self {
+        self.on_cancel.push(Box::new(callback));
+        self
+    }
+
+    /// ### on_expire
+    /// 注文が失効したときに呼ぶ
+    pub fn on_expire<F: FnMut(&TrackedOrder) + Send + 'static>(mut self, callback: F) -> Self {
+        self.on_expire.push(Box::new(callback));
+        self
+    }
+
+    pub fn get(&self, order_id: usize) -> Option<&TrackedOrder> {
+        self.orders.get(&order_id)
+    }
+
+    pub fn orders(&self) -> impl Iterator<Item = &TrackedOrder> {
+        self.orders.values()
+    }
+
+    /// ### open_orders
+    /// まだ終わっていない注文
+    pub fn open_orders(&self) -> impl Iterator<Item = &TrackedOrder> {
+        self.orders
+            .values()
+            .filter(|order| !order.status.is_terminal())
+    }
+
+    /// ### seed
+    /// `symbol`の有効注文をすべて取り込む
+    ///
+    /// 取り込めない注文があっても残りのページは取り込み、最後に`TrackerError::Rejected`を返す。
+    pub fn seed(&mut self, client: &GmoCoinClient, symbol: Symbol) -> Result<()> {
+        let mut rejected = Vec::new();
+        let mut page = 1;
+        loop {
+            let list = client
+                .active_orders(symbol, Some(page), Some(PAGE_SIZE))?
+                .data
+                .list;
+            rejected.extend(self.apply_orders(&list));
+            if list.len() < PAGE_SIZE {
+                return rejected_error(rejected);
+            }
+            page += 1;
+        }
+    }
+
+    /// ### refresh
+    /// 終わっていない注文を`orders`で問い合わせ直す(WebSocketを使わない場合のポーリング)
+    ///
+    /// 約定数量が増えた注文は`executions`で約定を取り込む。
+    pub fn refresh(&mut self, client: &GmoCoinClient) -> Result<()> {
+        let ids: Vec<usize> = self.open_orders().map(|order| order.order_id).collect();
+        self.query_orders(client, &ids)
+    }
+
+    /// ### reconcile
+    /// `symbol`の有効注文の一覧`orders`を正として取り込む
+    ///
+    /// 終わっていないはずなのに一覧に無い注文は、切断中に取消・失効・約定したものとして
+    /// `orders`で問い合わせ直す。
+    pub fn reconcile(
+        &mut self,
+        client: &GmoCoinClient,
+        symbol: Symbol,
+        orders: &[ActiveOrders],
+    ) -> Result<()> {
+        let rejected = self.apply_orders(orders);
+        let listed: HashSet<usize> = orders.iter().map(|order| order.order_id).collect();
+        let symbol = symbol.to_string();
+        let missing: Vec<usize> = self
+            .open_orders()
+            .filter(|order| order.symbol == symbol && !listed.contains(&order.order_id))
+            .map(|order| order.order_id)
+            .collect();
+        self.query_orders(client, &missing)?;
+        rejected_error(rejected)
+    }
+
+    /// ### apply_active_orders
+    /// 有効注文を取り込む
+    ///
+    /// 取り込めない注文があっても残りは取り込み、そのエラーを`TrackerError::Rejected`にまとめて返す。
+    pub fn apply_active_orders(&mut self, orders: &[ActiveOrders]) -> Result<()> {
+        let rejected = self.apply_orders(orders);
+        rejected_error(rejected)
+    }
+
+    pub fn apply_order_info(&mut self, order: &OrderInfo) -> Result<()> {
+        Ok(self.apply_state(order.into())?)
+    }
+
+    /// ### apply_execution
+    /// `executions`の約定を取り込む。知らない注文の約定は無視する
+    pub fn apply_execution(&mut self, execution: &Execution) -> Result<()> {
+        self.apply_fill(Fill {
+            execution_id: execution.execution_id,
+            order_id: execution.order_id,
+            price: Decimal::from_str(&execution.price)?,
+            size: Decimal::from_str(&execution.size)?,
+            fee: Decimal::from_str(&execution.fee)?,
+            timestamp: execution.timestamp.clone(),
+        })
+    }
+
+    pub fn apply_latest_executions(&mut self, executions: &[LatestExecutions]) -> Result<()> {
+        // 新しい順に返ってくるので古い順に取り込む
+        for execution in executions.iter().rev() {
+            self.apply_fill(Fill {
+                execution_id: execution.execution_id,
+                order_id: execution.order_id,
+                price: Decimal::from_str(&execution.price)?,
+                size: Decimal::from_str(&execution.size)?,
+                fee: Decimal::from_str(&execution.fee)?,
+                timestamp: execution.timestamp.clone(),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// ### apply_order_event
+    /// `orderEvents`の注文の変化を取り込む
+    #[cfg(feature = "websocket")]
+    pub fn apply_order_event(&mut self, event: &OrderEvent) -> Result<()> {
+        Ok(self.apply_state(OrderState {
+            order_id: event.order_id,
+            symbol: &event.symbol,
+            side: &event.side,
+            execution_type: &event.execution_type,
+            settle_type: &event.settle_type,
+            size: &event.order_size,
+            price: &event.order_price,
+            status: Some(&event.order_status),
+            executed_size: &event.order_executed_size,
+            timestamp: &event.order_timestamp,
+        })?)
+    }
+
+    /// ### apply_execution_event
+    /// `executionEvents`の約定を取り込む
+    ///
+    /// 約定数量が注文数量に達したら`EXECUTED`にする。
+    #[cfg(feature = "websocket")]
+    pub fn apply_execution_event(&mut self, event: &ExecutionEvent) -> Result<()> {
+        let executed =
+            Decimal::from_str(&event.order_executed_size)? == Decimal::from_str(&event.order_size)?;
+        self.apply_state(OrderState {
+            order_id: event.order_id,
+            symbol: &event.symbol,
+            side: &event.side,
+            execution_type: &event.execution_type,
+            settle_type: &event.settle_type,
+            size: &event.order_size,
+            price: &event.order_price,
+            status: executed.then_some("EXECUTED"),
+            executed_size: &event.order_executed_size,
+            timestamp: &event.order_timestamp,
+        })?;
+        self.apply_fill(Fill {
+            execution_id: event.execution_id,
+            order_id: event.order_id,
+            price: Decimal::from_str(&event.execution_price)?,
+            size: Decimal::from_str(&event.execution_size)?,
+            fee: Decimal::from_str(&event.fee)?,
+            timestamp: event.execution_timestamp.clone(),
+        })
+    }
+
+    /// ### apply_message
+    /// Private WebSocket APIのメッセージのうち、注文と約定を取り込む
+    #[cfg(feature = "websocket")]
+    pub fn apply_message(&mut self, message: &PrivateMessage) -> Result<()> {
+        match message {
+            PrivateMessage::Order(event) => self.apply_order_event(event),
+            PrivateMessage::Execution(event) => self.apply_execution_event(event),
+            _ => Ok(()),
+        }
+    }
+
+    /// ### apply_resync
+    /// 再接続のあとに取り直した有効注文と最新の約定を取り込む
+    ///
+    /// 有効注文は`reconcile`で取り込むので、切断中に終わった注文も`client`で問い合わせて終わらせる。
+    #[cfg(feature = "websocket")]
+    pub fn apply_resync(&mut self, client: &GmoCoinClient, resync: &Resync) -> Result<()> {
+        match resync {
+            Resync::ActiveOrders(symbol, orders) => self.reconcile(client, *symbol, orders),
+            Resync::LatestExecutions(_, executions) => self.apply_latest_executions(executions),
+            Resync::OrderBook(..) => Ok(()),
+        }
+    }
+
+    fn query_orders(&mut self, client: &GmoCoinClient, ids: &[usize]) -> Result<()> {
+        for chunk in ids.chunks(ORDERS_PER_REQUEST) {
+            let query = chunk
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+            for info in client.orders(query)?.data.list {
+                self.apply_order_info(&info)?;
+                let filled = self
+                    .orders
+                    .get(&info.order_id)
+                    .is_some_and(|order| order.filled_size < order.executed_size);
+                if filled {
+                    let executions = client
+                        .executions(ExecutionsParam::order_id(info.order_id))?
+                        .data
+                        .list;
+                    for execution in &executions {
+                        self.apply_execution(execution)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 取り込めなかった注文のエラーを返す
+    fn apply_orders(&mut self, orders: &[ActiveOrders]) -> Vec<TrackerError> {
+        orders
+            .iter()
+            .filter_map(|order| self.apply_state(order.into()).err())
+            .collect()
+    }
+
+    fn apply_state(&mut self, state: OrderState) -> std::result::Result<(), TrackerError> {
+        let number = |value: &str| {
+            Decimal::from_str(value).map_err(|_| TrackerError::InvalidNumber {
+                order_id: state.order_id,
+                value: value.to_string(),
+            })
+        };
+        let size = number(state.size)?;
+        let executed_size = number(state.executed_size)?;
+        let status = state.status.map(OrderStatus::from_str).transpose()?;
+        if executed_size > size {
+            return Err(TrackerError::Overfilled {
+                order_id: state.order_id,
+                size,
+                executed: executed_size,
+            });
+        }
+
+        let Some(order) = self.orders.get_mut(&state.order_id) else {
+            let order = TrackedOrder {
+                order_id: state.order_id,
+                symbol: state.symbol.to_string(),
+                side: state.side.to_string(),
+                execution_type: state.execution_type.to_string(),
+                settle_type: state.settle_type.to_string(),
+                size,
+                price: state.price.to_string(),
+                status: status.unwrap_or(OrderStatus::ORDERED),
+                executed_size,
+                filled_size: Decimal::ZERO,
+                fee: Decimal::ZERO,
+                timestamp: state.timestamp.to_string(),
+                notional: Decimal::ZERO,
+            };
+            self.orders.insert(state.order_id, order);
+            return Ok(());
+        };
+
+        let from = order.status;
+        let to = status.unwrap_or(from);
+        if !from.can_become(to) {
+            return Err(TrackerError::Transition {
+                order_id: order.order_id,
+                from,
+                to,
+            });
+        }
+        if executed_size < order.executed_size {
+            return Err(TrackerError::ExecutedSizeDecreased {
+                order_id: order.order_id,
+                from: order.executed_size,
+                to: executed_size,
+            });
+        }
+        // 終わった注文の同じ通知は何もしない
+        if from.is_terminal() {
+            return Ok(());
+        }
+
+        order.size = size;
+        order.price = state.price.to_string();
+        order.status = to;
+        order.executed_size = executed_size;
+        order.timestamp = state.timestamp.to_string();
+        if is_settled(order) {
+            self.executions.remove(&order.order_id);
+        }
+
+        let callbacks = match to {
+            OrderStatus::CANCELED => &mut self.on_cancel,
+            OrderStatus::EXPIRED => &mut self.on_expire,
+            _ => return Ok(()),
+        };
+        for callback in callbacks {
+            callback(order);
+        }
+        Ok(())
+    }
+
+    fn apply_fill(&mut self, fill: Fill) -> Result<()> {
+        let Some(order) = self.orders.get_mut(&fill.order_id) else {
+            return Ok(());
+        };
+        // 約定をすべて受け取って終わった注文には、同じ約定が届き直すことしかない
+        if is_settled(order) {
+            return Ok(());
+        }
+        let seen = self.executions.entry(order.order_id).or_default();
+        if seen.contains(&fill.execution_id) {
+            return Ok(());
+        }
+        let filled_size = order.filled_size + fill.size;
+        if filled_size > order.size {
+            return Err(TrackerError::Overfilled {
+                order_id: order.order_id,
+                size: order.size,
+                executed: filled_size,
+            }
+            .into());
+        }
+
+        seen.insert(fill.execution_id);
+        order.filled_size = filled_size;
+        order.notional += fill.price * fill.size;
+        order.fee += fill.fee;
+        // 注文の状態より先に約定が届いた
+        order.executed_size = order.executed_size.max(filled_size);
+        for callback in &mut self.on_fill {
+            callback(order, &fill);
+        }
+        if filled_size == order.size && !order.status.is_terminal() {
+            order.status = OrderStatus::EXECUTED;
+        }
+        if is_settled(order) {
+            self.executions.remove(&order.order_id);
+        }
+        Ok(())
+    }
+}
+
+/// 終わった注文で、取引所の約定数量まで約定を受け取ったか
+fn is_settled(order: &TrackedOrder) -> bool {
+    order.status.is_terminal() && order.filled_size >= order.executed_size
+}
+
+fn rejected_error(rejected: Vec<TrackerError>) -> Result<()> {
+    if rejected.is_empty() {
+        Ok(())
+    } else {
+        Err(TrackerError::Rejected(rejected).into())
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{
+    Ack, GmoCoinClient, LeverageSymbol, Response, Side, Symbol, error::Result,
+    public::api::SymbolRule,
+};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::HashMap;
+use std::str::FromStr;
+use thiserror::Error;
+
+use super::api::{ExecutionType, SettlePosition};
+
+/// ## Rounding
+/// 刻みに合わない価格・数量の丸め方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Floor,
+    Ceil,
+    Nearest,
+}
+
+/// ## OrderValidationError
+/// 注文を送る前に検出した取引ルール違反
+#[derive(Debug, Error)]
+pub enum OrderValidationError {
+    #[error("no trading rule for {0}")]
+    UnknownSymbol(String),
+
+    #[error("invalid number for {field}: {value}")]
+    InvalidNumber { field: &'static str, value: String },
+
+    #[error("{symbol}: {field} must be positive, got {value}")]
+    NotPositive {
+        symbol: String,
+        field: &'static str,
+        value: Decimal,
+    },
+
+    #[error("{symbol}: size {size} is below the minimum order size {min}")]
+    SizeTooSmall {
+        symbol: String,
+        size: Decimal,
+        min: Decimal,
+    },
+
+    #[error("{symbol}: size {size} exceeds the maximum order size {max}")]
+    SizeTooLarge {
+        symbol: String,
+        size: Decimal,
+        max: Decimal,
+    },
+
+    #[error("{symbol}: size {size} is not a multiple of the size step {step}")]
+    SizeStep {
+        symbol: String,
+        size: Decimal,
+        step: Decimal,
+    },
+
+    #[error("{symbol}: price {price} is not a multiple of the tick size {tick}")]
+    PriceTick {
+        symbol: String,
+        price: Decimal,
+        tick: Decimal,
+    },
+}
+
+#[derive(Debug)]
+struct Rule {
+    min_order_size: Decimal,
+    max_order_size: Decimal,
+    size_step: Decimal,
+    tick_size: Decimal,
+}
+
+impl Rule {
+    fn parse(rule: &SymbolRule) -> std::result::Result<Self, OrderValidationError> {
+        Ok(Self {
+            min_order_size: number("minOrderSize", &rule.min_order_size)?,
+            max_order_size: number("maxOrderSize", &rule.max_order_size)?,
+            size_step: number("sizeStep", &rule.size_step)?,
+            tick_size: number("tickSize", &rule.tick_size)?,
+        })
+    }
+}
+
+/// ## OrderValidator
+/// `symbols`の取引ルールで注文の価格と数量を送信前に検証する
+///
+/// 丸め方を指定しない場合、刻みに合わない値はエラーにする。
+/// 丸め方を指定した場合は刻みに合わせてから最小・最大注文数量を確認する。
+/// 注文は渡された`GmoCoinClient`で送るので、読み取り専用やリスク制限もそのまま効く。
+#[derive(Debug)]
+pub struct OrderValidator {
+    rules: HashMap<String, Rule>,
+    price_rounding: Option<Rounding>,
+    size_rounding: Option<Rounding>,
+}
+
+impl OrderValidator {
+    /// ### fetch
+    /// `client`の`symbols`から取引ルールを取得して保持する
+    pub fn fetch(client: &GmoCoinClient) -> Result<Self> {
+        let resp = client.symbols()?;
+        Self::from_rules(&resp.data)
+    }
+
+    pub fn from_rules(rules: &[SymbolRule]) -> Result<Self> {
+        let mut validator = Self {
+            rules: HashMap::new(),
+            price_rounding: None,
+            size_rounding: None,
+        };
+        validator.set_rules(rules)?;
+        Ok(validator)
+    }
+
+    /// ### refresh
+    /// 取引ルールを取得し直す
+    pub fn refresh(&mut self, client: &GmoCoinClient) -> Result<()> {
+        let resp = client.symbols()?;
+        self.set_rules(&resp.data)
+    }
+
+    fn set_rules(&mut self, rules: &[SymbolRule]) -> Result<()> {
+        self.rules = rules
+            .iter()
+            .map(|rule| Ok((rule.symbol.clone(), Rule::parse(rule)?)))
+            .collect::<std::result::Result<_, OrderValidationError>>()?;
+        Ok(())
+    }
+
+    pub fn price_rounding(mut self, rounding: Rounding) -> Self {
+        self.price_rounding = Some(rounding);
+        self
+    }
+
+    pub fn size_rounding(mut self, rounding: Rounding) -> Self {
+        self.size_rounding = Some(rounding);
+        self
+    }
+
+    /// ### check_price
+    /// 価格を検証し、送信する文字列を返す
+    pub fn check_price<S: ToString>(&self, symbol: S, price: &str) -> Result<String> {
+        let symbol = symbol.to_string();
+        let rule = self.rule(&symbol)?;
+        let price = positive(&symbol, "price", price)?;
+        let rounded = match self.price_rounding {
+            Some(rounding) => round(price, rule.tick_size, rounding),
+            None if is_multiple(price, rule.tick_size) => price,
+            None => {
+                return Err(OrderValidationError::PriceTick {
+                    symbol,
+                    price,
+                    tick: rule.tick_size,
+                }
+                .into());
+            }
+        };
+        if rounded.is_zero() {
+            return Err(OrderValidationError::NotPositive {
+                symbol,
+                field: "price",
+                value: rounded,
+            }
+            .into());
+        }
+
+        Ok(rounded.normalize().to_string())
+    }
+
+    /// ### check_size
+    /// 数量を検証し、送信する文字列を返す
+    pub fn check_size<S: ToString>(&self, symbol: S, size: &str) -> Result<String> {
+        let symbol = symbol.to_string();
+        let rule = self.rule(&symbol)?;
+        let size = positive(&symbol, "size", size)?;
+        let rounded = match self.size_rounding {
+            Some(rounding) => round(size, rule.size_step, rounding),
+            None if is_multiple(size, rule.size_step) => size,
+            None => {
+                return Err(OrderValidationError::SizeStep {
+                    symbol,
+                    size,
+                    step: rule.size_step,
+                }
+                .into());
+            }
+        };
+        if rounded < rule.min_order_size {
+            return Err(OrderValidationError::SizeTooSmall {
+                symbol,
+                size: rounded,
+                min: rule.min_order_size,
+            }
+            .into());
+        }
+        if rounded > rule.max_order_size {
+            return Err(OrderValidationError::SizeTooLarge {
+                symbol,
+                size: rounded,
+                max: rule.max_order_size,
+            }
+            .into());
+        }
+
+        Ok(rounded.normalize().to_string())
+    }
+
+    /// ### order
+    /// 検証してから`client`の`order`を呼ぶ
+    pub fn order(
+        &self,
+        client: &GmoCoinClient,
+        symbol: Symbol,
+        side: Side,
+        execution_type: ExecutionType,
+        price: Option<String>,
+        size: String,
+    ) -> Result<Response<String>> {
+        let price = price
+            .map(|price| self.check_price(symbol, &price))
+            .transpose()?;
+        let size = self.check_size(symbol, &size)?;
+
+        client.order(symbol, side, execution_type, price, size)
+    }
+
+    /// ### close_order
+    /// 検証してから`client`の`close_order`を呼ぶ
+    pub fn close_order(
+        &self,
+        client: &GmoCoinClient,
+        symbol: LeverageSymbol,
+        side: Side,
+        execution_type: ExecutionType,
+        price: Option<String>,
+        settle_position: SettlePosition,
+    ) -> Result<Response<String>> {
+        let price = price
+            .map(|price| self.check_price(symbol, &price))
+            .transpose()?;
+        let settle_position = SettlePosition {
+            position_id: settle_position.position_id,
+            size: self.check_size(symbol, &settle_position.size)?,
+        };
+
+        client.close_order(symbol, side, execution_type, price, settle_position)
+    }
+
+    /// ### change_order
+    /// 検証してから`client`の`change_order`を呼ぶ
+    ///
+    /// 注文IDからは銘柄が分からないので、`symbol`も指定する。
+    pub fn change_order(
+        &self,
+        client: &GmoCoinClient,
+        symbol: Symbol,
+        order_id: usize,
+        price: String,
+        losscut_price: Option<String>,
+    ) -> Result<Ack> {
+        let price = self.check_price(symbol, &price)?;
+        let losscut_price = losscut_price
+            .map(|price| self.check_price(symbol, &price))
+            .transpose()?;
+
+        client.change_order(order_id, price, losscut_price)
+    }
+
+    fn rule(&self, symbol: &str) -> std::result::Result<&Rule, OrderValidationError> {
+        self.rules
+            .get(symbol)
+            .ok_or_else(|| OrderValidationError::UnknownSymbol(symbol.to_string()))
+    }
+}
+
+fn number(field: &'static str, value: &str) -> std::result::Result<Decimal, OrderValidationError> {
+    Decimal::from_str(value).map_err(|_| OrderValidationError::InvalidNumber {
+        field,
+        value: value.to_string(),
+    })
+}
+
+fn positive(
+    symbol: &str,
+    field: &'static str,
+    value: &str,
+) -> std::result::Result<Decimal, OrderValidationError> {
+    let value = number(field, value)?;
+    if value.is_sign_negative() || value.is_zero() {
+        return Err(OrderValidationError::NotPositive {
+            symbol: symbol.to_string(),
+            field,
+            value,
+        });
+    }
+
+    Ok(value)
+}
+
+fn is_multiple(value: Decimal, step: Decimal) -> bool {
+    step.is_zero() || (value % step).is_zero()
+}
+
+fn round(value: Decimal, step: Decimal, rounding: Rounding) -> Decimal {
+    if step.is_zero() {
+        return value;
+    }
+
+    let steps = value / step;
+    let steps = match rounding {
+        Rounding::Floor => steps.floor(),
+        Rounding::Ceil => steps.ceil(),
+        Rounding::Nearest => {
+            steps.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+        }
+    };
+
+    steps * step
+}
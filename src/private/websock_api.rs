@@ -0,0 +1,447 @@
+use crate::{
+    GmoCoinClient, ResponsePage, Symbol,
+    error::{GmoCoinError, Result},
+    stream::{self, Backoff, Monitor, Resync, Socket, StreamEvent, Watchdog},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// アクセストークンの有効期限は60分なので、半分を過ぎたら延長する
+const TOKEN_REFRESH: Duration = Duration::from_secs(30 * 60);
+
+/// 取り直すときの1ページの件数(`activeOrders`・`latestExecutions`の上限)
+const RESYNC_PAGE: usize = 100;
+
+/// ## PrivateChannel
+/// Private WebSocket APIのチャンネル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrivateChannel {
+    ExecutionEvents,
+    OrderEvents,
+    PositionEvents,
+    PositionSummaryEvents,
+}
+
+impl fmt::Display for self::PrivateChannel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrivateChannel::ExecutionEvents => write!(f, "executionEvents"),
+            PrivateChannel::OrderEvents => write!(f, "orderEvents"),
+            PrivateChannel::PositionEvents => write!(f, "positionEvents"),
+            PrivateChannel::PositionSummaryEvents => write!(f, "positionSummaryEvents"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Command {
+    command: &'static str,
+    channel: String,
+}
+
+/// ## ExecutionEvent
+/// `executionEvents`チャンネルで配信される約定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionEvent {
+    pub channel: String,
+    pub order_id: usize,
+    pub execution_id: usize,
+    pub symbol: String,
+    pub settle_type: String,
+    pub execution_type: String,
+    pub side: String,
+    pub execution_price: String,
+    pub execution_size: String,
+    #[serde(default)]
+    pub position_id: Option<usize>,
+    pub order_timestamp: String,
+    pub execution_timestamp: String,
+    pub loss_gain: String,
+    pub fee: String,
+    pub order_price: String,
+    pub order_size: String,
+    pub order_executed_size: String,
+    pub time_in_force: String,
+    pub msg_type: String,
+}
+
+/// ## OrderEvent
+/// `orderEvents`チャンネルで配信される注文の変化
+///
+/// `msgType`は`NOR`(新規)・`ROR`(変更)・`COR`(取消)のいずれか。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderEvent {
+    pub channel: String,
+    pub order_id: usize,
+    pub symbol: String,
+    pub settle_type: String,
+    pub execution_type: String,
+    pub side: String,
+    pub order_status: String,
+    #[serde(default)]
+    pub cancel_type: Option<String>,
+    pub order_timestamp: String,
+    pub order_price: String,
+    pub order_size: String,
+    pub order_executed_size: String,
+    #[serde(default)]
+    pub losscut_price: Option<String>,
+    pub time_in_force: String,
+    pub msg_type: String,
+}
+
+/// ## PositionEvent
+/// `positionEvents`チャンネルで配信される建玉の変化
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionEvent {
+    pub channel: String,
+    pub position_id: usize,
+    pub symbol: String,
+    pub side: String,
+    pub size: String,
+    #[serde(rename = "orderdSize")]
+    pub order_size: String,
+    pub price: String,
+    pub loss_gain: String,
+    pub leverage: String,
+    pub losscut_price: String,
+    pub timestamp: String,
+    pub msg_type: String,
+}
+
+/// ## PositionSummaryEvent
+/// `positionSummaryEvents`チャンネルで配信される建玉サマリ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionSummaryEvent {
+    pub channel: String,
+    pub symbol: String,
+    pub side: String,
+    pub average_position_rate: String,
+    pub position_loss_gain: String,
+    pub sum_order_quantity: String,
+    pub sum_position_quantity: String,
+    pub timestamp: String,
+    pub msg_type: String,
+}
+
+/// ## PrivateMessage
+/// Private WebSocket APIから受け取るメッセージ
+#[derive(Debug)]
+pub enum PrivateMessage {
+    Execution(ExecutionEvent),
+    Order(OrderEvent),
+    Position(PositionEvent),
+    PositionSummary(PositionSummaryEvent),
+}
+
+impl PrivateMessage {
+    /// ### parse
+    /// テキストメッセージをチャンネルごとの型にする
+    ///
+    /// 知らないチャンネルのメッセージは`None`を返す。
+    pub fn parse(text: &str) -> Result<Option<Self>> {
+        let value: Value = serde_json::from_str(text)?;
+        if let Some(error) = value.get("error") {
+            return Err(GmoCoinError::WebSocketApi(match error {
+                Value::String(error) => error.clone(),
+                error => error.to_string(),
+            }));
+        }
+
+        let message = match value.get("channel").and_then(Value::as_str) {
+            Some("executionEvents") => PrivateMessage::Execution(serde_json::from_value(value)?),
+            Some("orderEvents") => PrivateMessage::Order(serde_json::from_value(value)?),
+            Some("positionEvents") => PrivateMessage::Position(serde_json::from_value(value)?),
+            Some("positionSummaryEvents") => {
+                PrivateMessage::PositionSummary(serde_json::from_value(value)?)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(message))
+    }
+
+    pub fn channel(&self) -> &str {
+        match self {
+            PrivateMessage::Execution(event) => &event.channel,
+            PrivateMessage::Order(event) => &event.channel,
+            PrivateMessage::Position(event) => &event.channel,
+            PrivateMessage::PositionSummary(event) => &event.channel,
+        }
+    }
+}
+
+/// ## PrivateStream
+/// Private WebSocket APIの接続
+///
+/// `GmoCoinClient`でアクセストークンを取得して接続し、30分ごとに延長する。
+/// メッセージが届かなくても延長できるよう、読み込みには常にタイムアウトを付ける。
+/// `next_event`を使えば切断したときに自動で再接続し、
+/// `resync_symbols`の有効注文と最新の約定をRESTで全ページ取り直す。
+/// トークンの延長に失敗した場合も切断として扱い、再接続で取得し直す。
+pub struct PrivateStream {
+    client: Arc<GmoCoinClient>,
+    socket: Socket,
+    token: String,
+    token_refreshed: Instant,
+    token_refresh: Duration,
+    subscriptions: Vec<PrivateChannel>,
+    last_command: Option<Instant>,
+    backoff: Backoff,
+    symbols: Vec<Symbol>,
+    pending: VecDeque<StreamEvent<PrivateMessage>>,
+    disconnected: bool,
+    monitor: Option<Monitor>,
+}
+
+impl PrivateStream {
+    /// ### connect
+    /// アクセストークンを取得して、`client`の`private_ws`に接続する
+    pub fn connect(client: Arc<GmoCoinClient>) -> Result<Self> {
+        let token = client.ws_auth()?.data;
+        let mut socket = open(&client, &token)?;
+        stream::set_read_timeout(&mut socket, TOKEN_REFRESH);
+        Ok(Self {
+            client,
+            socket,
+            token,
+            token_refreshed: Instant::now(),
+            token_refresh: TOKEN_REFRESH,
+            subscriptions: Vec::new(),
+            last_command: None,
+            backoff: Backoff::default(),
+            symbols: Vec::new(),
+            pending: VecDeque::new(),
+            disconnected: false,
+            monitor: None,
+        })
+    }
+
+    /// ### backoff
+    /// `next_event`で再接続するときの待ち時間
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// ### token_refresh
+    /// アクセストークンを延長する間隔。デフォルトは30分
+    pub fn token_refresh(mut self, interval: Duration) -> Self {
+        self.token_refresh = interval;
+        self.arm();
+        self
+    }
+
+    /// ### watchdog
+    /// チャンネルの途絶とハートビートを監視する。途絶は`next_event`が返す
+    pub fn watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.monitor = Some(Monitor::new(watchdog));
+        self.arm();
+        self
+    }
+
+    /// ### resync_symbols
+    /// 再接続したあと、`active_orders`と`latest_executions`を取り直す銘柄
+    pub fn resync_symbols(mut self, symbols: &[Symbol]) -> Self {
+        self.symbols = symbols.to_vec();
+        self
+    }
+
+    /// ### subscribe
+    /// チャンネルを購読する
+    ///
+    /// 取引所の制限に合わせて、前回のリクエストから1秒経つまで待つ。
+    pub fn subscribe(&mut self, channel: PrivateChannel) -> Result<()> {
+        self.send_command("subscribe", channel)?;
+        if !self.subscriptions.contains(&channel) {
+            self.subscriptions.push(channel);
+        }
+        Ok(())
+    }
+
+    pub fn unsubscribe(&mut self, channel: PrivateChannel) -> Result<()> {
+        self.send_command("unsubscribe", channel)?;
+        self.subscriptions.retain(|s| *s != channel);
+        Ok(())
+    }
+
+    pub fn subscriptions(&self) -> &[PrivateChannel] {
+        &self.subscriptions
+    }
+
+    pub fn get_token(&self) -> &str {
+        &self.token
+    }
+
+    /// ### read
+    /// 次のメッセージを受け取るまでブロックする
+    pub fn read(&mut self) -> Result<PrivateMessage> {
+        loop {
+            self.refresh_token()?;
+            if let Some(message) = self.poll()? {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// ### next_event
+    /// `read`と同じだが、切断したら`backoff`に従って再接続する
+    ///
+    /// `Backoff::max_attempts`回続けて失敗したらそのエラーを返す。
+    /// もう一度呼べば再び再接続を試みる。
+    pub fn next_event(&mut self) -> Result<StreamEvent<PrivateMessage>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+            if self.disconnected {
+                let backoff = self.backoff.clone();
+                let attempts = stream::retry(&backoff, || self.reconnect())?;
+                self.disconnected = false;
+                self.queue_resync();
+                return Ok(StreamEvent::Reconnected { attempts });
+            }
+
+            // 延長できなければ、再接続でトークンを取得し直す
+            if let Err(e) = self.refresh_token() {
+                self.disconnected = true;
+                return Ok(StreamEvent::Disconnected(e));
+            }
+            match self.poll() {
+                Ok(Some(message)) => return Ok(StreamEvent::Message(message)),
+                Ok(None) => {
+                    if let Some(monitor) = &mut self.monitor {
+                        self.disconnected = monitor.watch(&mut self.pending);
+                    }
+                }
+                Err(e) if stream::is_disconnect(&e) => {
+                    self.disconnected = true;
+                    return Ok(StreamEvent::Disconnected(e));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// ### reconnect
+    /// 接続し直して、購読していたチャンネルをすべて購読し直す
+    ///
+    /// アクセストークンを延長し、期限が切れていれば取得し直す。
+    pub fn reconnect(&mut self) -> Result<()> {
+        self.client
+            .get_metrics()
+            .ws_reconnect(self.client.private_ws_url());
+        let _ = self.socket.close(None);
+        if self.client.extend_ws_auth(&self.token).is_err() {
+            self.token = self.client.ws_auth()?.data;
+        }
+        self.token_refreshed = Instant::now();
+        self.socket = open(&self.client, &self.token)?;
+        self.arm();
+        for channel in self.subscriptions.clone() {
+            self.send_command("subscribe", channel)?;
+        }
+        Ok(())
+    }
+
+    /// ### close
+    /// 接続を閉じて、アクセストークンを削除する
+    pub fn close(mut self) -> Result<()> {
+        let _ = self.socket.close(None);
+        self.client.delete_ws_auth(&self.token)?;
+        Ok(())
+    }
+
+    /// 次のメッセージ。`Watchdog`の読み込みのタイムアウトでは`None`を返す
+    fn poll(&mut self) -> Result<Option<PrivateMessage>> {
+        let Some(text) = stream::read_text(&mut self.socket, self.monitor.as_mut())? else {
+            return Ok(None);
+        };
+        let message = PrivateMessage::parse(&text)?;
+        if let (Some(message), Some(monitor)) = (&message, &mut self.monitor) {
+            monitor.seen(message.channel());
+        }
+        Ok(message)
+    }
+
+    /// (再)接続したソケットの読み込みにタイムアウトを付ける
+    ///
+    /// `Watchdog`があればその間隔、無ければトークンを延長する間隔で`poll`が戻る。
+    fn arm(&mut self) {
+        match &mut self.monitor {
+            Some(monitor) => monitor.attach(&mut self.socket),
+            None => stream::set_read_timeout(&mut self.socket, self.token_refresh),
+        }
+    }
+
+    fn refresh_token(&mut self) -> Result<()> {
+        if self.token_refreshed.elapsed() >= self.token_refresh {
+            self.client.extend_ws_auth(&self.token)?;
+            self.token_refreshed = Instant::now();
+        }
+        Ok(())
+    }
+
+    fn queue_resync(&mut self) {
+        for symbol in &self.symbols {
+            self.pending.push_back(
+                match all_pages(|page| {
+                    self.client
+                        .active_orders(*symbol, Some(page), Some(RESYNC_PAGE))
+                }) {
+                    Ok(orders) => StreamEvent::Resync(Resync::ActiveOrders(*symbol, orders)),
+                    Err(e) => StreamEvent::ResyncFailed(e),
+                },
+            );
+            self.pending.push_back(
+                match all_pages(|page| {
+                    self.client
+                        .latest_executions(*symbol, Some(page), Some(RESYNC_PAGE))
+                }) {
+                    Ok(executions) => {
+                        StreamEvent::Resync(Resync::LatestExecutions(*symbol, executions))
+                    }
+                    Err(e) => StreamEvent::ResyncFailed(e),
+                },
+            );
+        }
+    }
+
+    fn send_command(&mut self, command: &'static str, channel: PrivateChannel) -> Result<()> {
+        let command = Command {
+            command,
+            channel: channel.to_string(),
+        };
+        stream::send_command(&mut self.socket, &mut self.last_command, &command)
+    }
+}
+
+/// 1ページ目から、`RESYNC_PAGE`件に満たないページまで取得する
+fn all_pages<T>(mut fetch: impl FnMut(usize) -> Result<ResponsePage<T>>) -> Result<Vec<T>> {
+    let mut all = Vec::new();
+    for page in 1.. {
+        let list = fetch(page)?.data.list;
+        let last = list.len() < RESYNC_PAGE;
+        all.extend(list);
+        if last {
+            break;
+        }
+    }
+    Ok(all)
+}
+
+fn open(client: &GmoCoinClient, token: &str) -> Result<Socket> {
+    let url = format!(
+        "{}/{}",
+        client.private_ws_url().trim_end_matches('/'),
+        token
+    );
+    stream::open(&url, client.get_http_options())
+}
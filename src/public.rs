@@ -1,4 +1,6 @@
 pub mod api;
 #[cfg(feature = "async")]
 pub mod async_api;
-// TODO: pub mod websock_api;
+pub mod history;
+#[cfg(feature = "websocket")]
+pub mod websock_api;
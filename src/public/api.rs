@@ -1,16 +1,11 @@
-use crate::{Pagenation, Response, Symbol, endpoint, error::Result};
-use serde::{Deserialize, Serialize, de::DeserializeOwned};
-
-type UreqResponse = ureq::http::Response<ureq::Body>;
-
-fn response_body(mut response: UreqResponse) -> Result<String> {
-    Ok(response.body_mut().read_to_string()?)
-}
-
-fn parse_response<T: DeserializeOwned>(response: UreqResponse) -> Result<T> {
-    let body = response_body(response)?;
-    Ok(serde_json::from_str(&body)?)
-}
+use crate::{
+    JST, Pagenation, Response, Symbol,
+    client::shared_client,
+    error::{GmoCoinError, Result},
+    status::ExchangeState,
+};
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime, Time};
 
 /// ## ExchangeStatus
 /// 取引所の稼動状態
@@ -24,10 +19,16 @@ pub struct ExchangeStatus {
     pub status: String,
 }
 
+impl ExchangeStatus {
+    /// ### state
+    /// `status`を`ExchangeState`にする
+    pub fn state(&self) -> Result<ExchangeState> {
+        self.status.parse()
+    }
+}
+
 pub fn status() -> Result<Response<ExchangeStatus>> {
-    let path = "/v1/status";
-    let resp = ureq::get(&format!("{}{}", endpoint::PUBLIC_API, path)).call()?;
-    parse_response(resp)
+    shared_client().status()
 }
 
 /// ## SymbolRate
@@ -47,13 +48,7 @@ pub struct LatestRate {
 /// ### ticker
 /// symbolの指定が無い場合、全銘柄のレートを取得する。
 pub fn ticker(symbol: Option<Symbol>) -> Result<Response<Vec<LatestRate>>> {
-    let path = "/v1/ticker";
-    let mut url = format!("{}{}", endpoint::PUBLIC_API, path);
-    if let Some(symbol) = symbol {
-        url = format!("{}?symbol={}", url, symbol);
-    }
-    let resp = ureq::get(&url).call()?;
-    parse_response(resp)
+    shared_client().ticker(symbol)
 }
 
 /// ### Ask
@@ -83,11 +78,7 @@ pub struct Snapshot {
 }
 
 pub fn orderbooks(symbol: Symbol) -> Result<Response<Snapshot>> {
-    let path = "/v1/orderbooks";
-    let url = format!("{}{}?symbol={}", endpoint::PUBLIC_API, path, symbol);
-    let resp = ureq::get(&url).call()?;
-
-    parse_response(resp)
+    shared_client().orderbooks(symbol)
 }
 
 /// ## Trade
@@ -125,25 +116,12 @@ pub fn trades(
     page: Option<usize>,
     count: Option<usize>,
 ) -> Result<Response<TradesList>> {
-    let path = "/v1/trades";
-    let mut query = format!("?symbol={}", symbol);
-    if let Some(page) = page {
-        query = format!("{}&page={}", query, page);
-    }
-
-    if let Some(count) = count {
-        query = format!("{}&count={}", query, count);
-    }
-
-    let url = format!("{}{}{}", endpoint::PUBLIC_API, path, query);
-    let resp = ureq::get(&url).call()?;
-
-    parse_response(resp)
+    shared_client().trades(symbol, page, count)
 }
 
 /// ## KlineInterval
 /// ローソク足の間隔
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum KlineInterval {
     OneMin,
     FiveMin,
@@ -178,6 +156,81 @@ impl std::fmt::Display for self::KlineInterval {
     }
 }
 
+#[derive(Debug)]
+pub enum KlineIntervalError {
+    KlineIntervalParseError,
+}
+
+impl std::fmt::Display for self::KlineIntervalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KlineIntervalError::KlineIntervalParseError => write!(f, "Can't parse kline interval"),
+        }
+    }
+}
+
+impl std::error::Error for self::KlineIntervalError {}
+
+impl std::str::FromStr for self::KlineInterval {
+    type Err = self::KlineIntervalError;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "1min" => Ok(KlineInterval::OneMin),
+            "5min" => Ok(KlineInterval::FiveMin),
+            "10min" => Ok(KlineInterval::TenMin),
+            "15min" => Ok(KlineInterval::FifteenMin),
+            "30min" => Ok(KlineInterval::ThirtyMin),
+            "1hour" => Ok(KlineInterval::OneHour),
+            "4hour" => Ok(KlineInterval::FourHour),
+            "8hour" => Ok(KlineInterval::EightHour),
+            "12hour" => Ok(KlineInterval::TwelveHour),
+            "1day" => Ok(KlineInterval::OneDay),
+            "1week" => Ok(KlineInterval::OneWeek),
+            "1month" => Ok(KlineInterval::OneMonth),
+            _ => Err(KlineIntervalError::KlineIntervalParseError),
+        }
+    }
+}
+
+impl KlineInterval {
+    /// ### is_yearly
+    /// `klines`の`date`を年(YYYY)で指定する間隔か
+    ///
+    /// 4hour以上は年、それ未満は日(YYYYMMDD)で指定する。
+    pub fn is_yearly(&self) -> bool {
+        !matches!(
+            self,
+            KlineInterval::OneMin
+                | KlineInterval::FiveMin
+                | KlineInterval::TenMin
+                | KlineInterval::FifteenMin
+                | KlineInterval::ThirtyMin
+                | KlineInterval::OneHour
+        )
+    }
+
+    /// ### duration
+    /// 足の長さ。1monthは月によって長さが違うので`None`
+    pub fn duration(&self) -> Option<std::time::Duration> {
+        let minutes = match self {
+            KlineInterval::OneMin => 1,
+            KlineInterval::FiveMin => 5,
+            KlineInterval::TenMin => 10,
+            KlineInterval::FifteenMin => 15,
+            KlineInterval::ThirtyMin => 30,
+            KlineInterval::OneHour => 60,
+            KlineInterval::FourHour => 4 * 60,
+            KlineInterval::EightHour => 8 * 60,
+            KlineInterval::TwelveHour => 12 * 60,
+            KlineInterval::OneDay => 24 * 60,
+            KlineInterval::OneWeek => 7 * 24 * 60,
+            KlineInterval::OneMonth => return None,
+        };
+
+        Some(std::time::Duration::from_secs(minutes * 60))
+    }
+}
+
 /// ## Kline
 /// ローソク足
 #[derive(Debug, Serialize, Deserialize)]
@@ -191,30 +244,135 @@ pub struct Kline {
     pub volume: String,
 }
 
+/// GMOコインの営業日はJSTの6時に切り替わる
+pub const DAY_ROLLOVER: Time = time::macros::time!(06:00);
+
+/// 日(YYYYMMDD)で指定できる最初の日
+const FIRST_KLINE_DAY: Date = time::macros::date!(2021 - 04 - 15);
+
+/// ## KlineDate
+/// `klines`の対象期間
+///
+///  - Day: 1hour以下の間隔で使う営業日(JST 6時始まり)
+///  - Year: 4hour以上の間隔で使う年
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KlineDate {
+    Day(Date),
+    Year(i32),
+}
+
+impl From<Date> for KlineDate {
+    fn from(date: Date) -> Self {
+        KlineDate::Day(date)
+    }
+}
+
+impl From<i32> for KlineDate {
+    fn from(year: i32) -> Self {
+        KlineDate::Year(year)
+    }
+}
+
+impl std::fmt::Display for self::KlineDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KlineDate::Day(date) => write!(
+                f,
+                "{:04}{:02}{:02}",
+                date.year(),
+                date.month() as u8,
+                date.day()
+            ),
+            KlineDate::Year(year) => write!(f, "{:04}", year),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum KlineDateError {
+    KlineDateParseError,
+}
+
+impl std::fmt::Display for self::KlineDateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KlineDateError::KlineDateParseError => {
+                write!(f, "Can't parse kline date (expected YYYYMMDD or YYYY)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for self::KlineDateError {}
+
+impl std::str::FromStr for self::KlineDate {
+    type Err = self::KlineDateError;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let format = time::macros::format_description!("[year][month][day]");
+        match s.len() {
+            8 => Date::parse(s, &format)
+                .map(KlineDate::Day)
+                .map_err(|_| KlineDateError::KlineDateParseError),
+            4 => s
+                .parse()
+                .map(KlineDate::Year)
+                .map_err(|_| KlineDateError::KlineDateParseError),
+            _ => Err(KlineDateError::KlineDateParseError),
+        }
+    }
+}
+
+impl KlineDate {
+    /// ### containing
+    /// `datetime`を含む期間
+    ///
+    /// 日単位の間隔では6時の切り替えを考慮するので、JSTの0時から6時までは前日になる。
+    pub fn containing(datetime: OffsetDateTime, interval: KlineInterval) -> Self {
+        let local = datetime.to_offset(JST);
+        if interval.is_yearly() {
+            return KlineDate::Year(local.year());
+        }
+
+        if local.time() < DAY_ROLLOVER {
+            KlineDate::Day(local.date().previous_day().unwrap_or(local.date()))
+        } else {
+            KlineDate::Day(local.date())
+        }
+    }
+
+    /// ### validate
+    /// `interval`と組み合わせられるか確かめる
+    pub fn validate(&self, interval: KlineInterval) -> Result<()> {
+        let reason = match self {
+            KlineDate::Day(_) if interval.is_yearly() => "4hour and above take a year",
+            KlineDate::Year(_) if !interval.is_yearly() => "1hour and below take a day",
+            KlineDate::Day(date) if *date < FIRST_KLINE_DAY => {
+                "days before 20210415 are not available"
+            }
+            _ => return Ok(()),
+        };
+
+        Err(GmoCoinError::InvalidKlineDate {
+            date: *self,
+            interval,
+            reason,
+        })
+    }
+}
+
 /// ## klines
 /// ローソク足を取得する
 ///
 /// ### Params
 ///  - symbol: 銘柄
 ///  - interval: 間隔（例: 1min）
-///  - date: 対象日（YYYYMMDD）
-pub fn klines(
+///  - date: 対象期間。1hour以下は営業日(`time::Date`)、4hour以上は年(`i32`)
+pub fn klines<D: Into<KlineDate>>(
     symbol: Symbol,
     interval: KlineInterval,
-    date: String,
+    date: D,
 ) -> Result<Response<Vec<Kline>>> {
-    let path = "/v1/klines";
-    let url = format!(
-        "{}{}?symbol={}&interval={}&date={}",
-        endpoint::PUBLIC_API,
-        path,
-        symbol,
-        interval,
-        date
-    );
-    let resp = ureq::get(&url).call()?;
-
-    parse_response(resp)
+    shared_client().klines(symbol, interval, date)
 }
 
 /// ## SymbolRule
@@ -234,8 +392,5 @@ pub struct SymbolRule {
 /// ## symbols
 /// 取引ルールを取得する
 pub fn symbols() -> Result<Response<Vec<SymbolRule>>> {
-    let path = "/v1/symbols";
-    let resp = ureq::get(&format!("{}{}", endpoint::PUBLIC_API, path)).call()?;
-
-    parse_response(resp)
+    shared_client().symbols()
 }
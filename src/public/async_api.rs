@@ -1,30 +1,29 @@
-use crate::{Response, Symbol, endpoint, error::Result};
+use crate::{
+    Response, Symbol, endpoint,
+    error::Result,
+    http,
+    trace::{self, RequestLog},
+};
 use serde::de::DeserializeOwned;
 
 use super::api::{
-    ExchangeStatus, Kline, KlineInterval, LatestRate, Snapshot, SymbolRule, TradesList,
+    ExchangeStatus, Kline, KlineDate, KlineInterval, LatestRate, Snapshot, SymbolRule, TradesList,
 };
 
 pub async fn status() -> Result<Response<ExchangeStatus>> {
-    let path = "/v1/status";
-    get_json(format!("{}{}", endpoint::PUBLIC_API, path)).await
+    get_json("/v1/status", &[]).await
 }
 
 pub async fn ticker(symbol: Option<Symbol>) -> Result<Response<Vec<LatestRate>>> {
-    let path = "/v1/ticker";
-    let mut url = format!("{}{}", endpoint::PUBLIC_API, path);
-    if let Some(symbol) = symbol {
-        url = format!("{}?symbol={}", url, symbol);
-    }
-
-    get_json(url).await
+    let query: Vec<_> = symbol
+        .map(|symbol| ("symbol", symbol.to_string()))
+        .into_iter()
+        .collect();
+    get_json("/v1/ticker", &query).await
 }
 
 pub async fn orderbooks(symbol: Symbol) -> Result<Response<Snapshot>> {
-    let path = "/v1/orderbooks";
-    let url = format!("{}{}?symbol={}", endpoint::PUBLIC_API, path, symbol);
-
-    get_json(url).await
+    get_json("/v1/orderbooks", &[("symbol", symbol.to_string())]).await
 }
 
 pub async fn trades(
@@ -32,50 +31,47 @@ pub async fn trades(
     page: Option<usize>,
     count: Option<usize>,
 ) -> Result<Response<TradesList>> {
-    let path = "/v1/trades";
-    let mut query = format!("?symbol={}", symbol);
+    let mut query = vec![("symbol", symbol.to_string())];
     if let Some(page) = page {
-        query = format!("{}&page={}", query, page);
+        query.push(("page", page.to_string()));
     }
 
     if let Some(count) = count {
-        query = format!("{}&count={}", query, count);
+        query.push(("count", count.to_string()));
     }
 
-    let url = format!("{}{}{}", endpoint::PUBLIC_API, path, query);
-
-    get_json(url).await
+    get_json("/v1/trades", &query).await
 }
 
-pub async fn klines(
+pub async fn klines<D: Into<KlineDate>>(
     symbol: Symbol,
     interval: KlineInterval,
-    date: String,
+    date: D,
 ) -> Result<Response<Vec<Kline>>> {
-    let path = "/v1/klines";
-    let url = format!(
-        "{}{}?symbol={}&interval={}&date={}",
-        endpoint::PUBLIC_API,
-        path,
-        symbol,
-        interval,
-        date
-    );
+    let date = date.into();
+    date.validate(interval)?;
 
-    get_json(url).await
+    get_json(
+        "/v1/klines",
+        &[
+            ("symbol", symbol.to_string()),
+            ("interval", interval.to_string()),
+            ("date", date.to_string()),
+        ],
+    )
+    .await
 }
 
 pub async fn symbols() -> Result<Response<Vec<SymbolRule>>> {
-    let path = "/v1/symbols";
-    get_json(format!("{}{}", endpoint::PUBLIC_API, path)).await
+    get_json("/v1/symbols", &[]).await
 }
 
-async fn get_json<T: DeserializeOwned>(url: String) -> Result<T> {
-    Ok(reqwest::Client::new()
-        .get(url)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<T>()
-        .await?)
+async fn get_json<T: DeserializeOwned>(path: &str, query: &[(&str, String)]) -> Result<T> {
+    let symbol = trace::symbol(query.iter().map(|(key, value)| (*key, value.as_str())), "");
+    let log = RequestLog::new("GET", path, symbol.as_deref());
+    let request = http::async_client()?
+        .get(format!("{}{}", endpoint::PUBLIC_API, path))
+        .query(query);
+
+    Ok(trace::send(&log, request).await?.json::<T>().await?)
 }
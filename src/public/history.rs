@@ -0,0 +1,215 @@
+use crate::{
+    GmoCoinClient, JST, Symbol,
+    error::{GmoCoinError, Result},
+};
+use std::fs;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+use time::{Date, OffsetDateTime, PrimitiveDateTime};
+
+use super::api::{DAY_ROLLOVER, Kline, KlineDate, KlineInterval};
+
+/// ## KlineGap
+/// 連続しているはずのローソク足の抜け
+///
+/// `from`と`to`は抜けの前後の足の`openTime`(UNIXミリ秒)。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KlineGap {
+    pub from: u64,
+    pub to: u64,
+}
+
+/// ## KlineDownloader
+/// 期間を指定してローソク足をまとめて取得する
+///
+/// `klines`は1hour以下なら1日ずつ、4hour以上なら1年ずつしか取得できないので、
+/// 間隔に合わせて呼び出しを分割する。
+/// `cache_dir`を指定すると確定済みの期間をファイルに保存し、次回以降はそこから読む。
+#[derive(Debug)]
+pub struct KlineDownloader {
+    symbol: Symbol,
+    interval: KlineInterval,
+    cache_dir: Option<PathBuf>,
+    delay: Duration,
+    allow_gaps: bool,
+}
+
+impl KlineDownloader {
+    pub fn new(symbol: Symbol, interval: KlineInterval) -> Self {
+        Self {
+            symbol,
+            interval,
+            cache_dir: None,
+            delay: Duration::from_millis(200),
+            allow_gaps: true,
+        }
+    }
+
+    /// ### cache_dir
+    /// キャッシュの保存先。`{cache_dir}/{symbol}/{interval}/{date}.json`に保存する
+    pub fn cache_dir<P: Into<PathBuf>>(mut self, cache_dir: P) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// ### delay
+    /// APIを呼ぶ間隔。デフォルトは200ms
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// ### allow_gaps
+    /// 抜けがあってもエラーにしない。デフォルトは`true`
+    ///
+    /// 週次メンテナンス中の足は無いので、普通の期間でも抜けは出る。
+    /// 抜けは`find_gaps`で調べられる。`false`にすると最初の抜けで`KlineGap`エラーを返す。
+    pub fn allow_gaps(mut self, allow_gaps: bool) -> Self {
+        self.allow_gaps = allow_gaps;
+        self
+    }
+
+    /// ### download
+    /// `from`から`to`まで(両端を含む)のローソク足を`client`で取得する
+    ///
+    /// 日単位の間隔では営業日(JST 6時始まり)で数える。
+    /// 年単位の間隔では期間を含む年のローソク足をすべて返す。
+    /// 結果は`openTime`順に並べ、重複を取り除く。
+    pub fn download(&self, client: &GmoCoinClient, from: Date, to: Date) -> Result<Vec<Kline>> {
+        if from > to {
+            return Err(GmoCoinError::InvalidDateRange { from, to });
+        }
+
+        let mut klines = Vec::new();
+        let mut requested = false;
+        for date in self.dates(from, to) {
+            if let Some(cached) = self.read_cache(date)? {
+                klines.extend(cached);
+                continue;
+            }
+
+            if requested {
+                sleep(self.delay);
+            }
+            requested = true;
+
+            let resp = client.klines(self.symbol, self.interval, date)?;
+            if is_complete(date) {
+                self.write_cache(date, &resp.data)?;
+            }
+            klines.extend(resp.data);
+        }
+
+        let klines = merge(klines)?;
+        if !self.allow_gaps
+            && let Some(gap) = find_gaps(&klines, self.interval)?.into_iter().next()
+        {
+            return Err(GmoCoinError::KlineGap {
+                from: gap.from,
+                to: gap.to,
+            });
+        }
+
+        Ok(klines)
+    }
+
+    fn dates(&self, from: Date, to: Date) -> Vec<KlineDate> {
+        if self.interval.is_yearly() {
+            return (from.year()..=to.year()).map(KlineDate::Year).collect();
+        }
+
+        let mut dates = Vec::new();
+        let mut date = from;
+        while date <= to {
+            dates.push(KlineDate::Day(date));
+            match date.next_day() {
+                Some(next) => date = next,
+                None => break,
+            }
+        }
+
+        dates
+    }
+
+    fn cache_path(&self, date: KlineDate) -> Option<PathBuf> {
+        self.cache_dir.as_ref().map(|dir| {
+            dir.join(self.symbol.to_string())
+                .join(self.interval.to_string())
+                .join(format!("{}.json", date))
+        })
+    }
+
+    fn read_cache(&self, date: KlineDate) -> Result<Option<Vec<Kline>>> {
+        match self.cache_path(date) {
+            Some(path) if path.exists() => {
+                let body = fs::read_to_string(path)?;
+                Ok(Some(serde_json::from_str(&body)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn write_cache(&self, date: KlineDate, klines: &[Kline]) -> Result<()> {
+        if let Some(path) = self.cache_path(date) {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, serde_json::to_string(klines)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// その期間のローソク足がもう増えないか
+fn is_complete(date: KlineDate) -> bool {
+    let end = match date {
+        KlineDate::Day(date) => date.next_day(),
+        KlineDate::Year(year) => Date::from_ordinal_date(year + 1, 1).ok(),
+    };
+
+    end.is_some_and(|end| {
+        PrimitiveDateTime::new(end, DAY_ROLLOVER).assume_offset(JST) <= OffsetDateTime::now_utc()
+    })
+}
+
+/// ## find_gaps
+/// `openTime`順に並んだローソク足から抜けを探す
+///
+/// 1monthは長さが一定でないので常に空を返す。
+pub fn find_gaps(klines: &[Kline], interval: KlineInterval) -> Result<Vec<KlineGap>> {
+    let Some(step) = interval.duration() else {
+        return Ok(Vec::new());
+    };
+    let step = step.as_millis() as u64;
+
+    let mut gaps = Vec::new();
+    for pair in klines.windows(2) {
+        let from = open_time(&pair[0])?;
+        let to = open_time(&pair[1])?;
+        if to - from > step {
+            gaps.push(KlineGap { from, to });
+        }
+    }
+
+    Ok(gaps)
+}
+
+fn merge(klines: Vec<Kline>) -> Result<Vec<Kline>> {
+    let mut keyed = klines
+        .into_iter()
+        .map(|kline| Ok((open_time(&kline)?, kline)))
+        .collect::<Result<Vec<_>>>()?;
+    keyed.sort_by_key(|(open_time, _)| *open_time);
+    keyed.dedup_by_key(|(open_time, _)| *open_time);
+
+    Ok(keyed.into_iter().map(|(_, kline)| kline).collect())
+}
+
+fn open_time(kline: &Kline) -> Result<u64> {
+    kline
+        .open_time
+        .parse()
+        .map_err(|_| GmoCoinError::InvalidOpenTime(kline.open_time.clone()))
+}
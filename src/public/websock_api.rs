@@ -0,0 +1,379 @@
+use crate::{
+    GmoCoinClient, Symbol, endpoint,
+    error::{GmoCoinError, Result},
+    http::HttpOptions,
+    metrics::Metrics,
+    orderbook::OrderBookUpdate,
+    public::api::{LatestRate, Trade},
+    stream::{self, Backoff, Monitor, Resync, Socket, StreamEvent, Watchdog},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// ## Channel
+/// Public WebSocket APIのチャンネル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Ticker,
+    Trades,
+    OrderBooks,
+}
+
+impl fmt::Display for self::Channel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Channel::Ticker => write!(f, "ticker"),
+            Channel::Trades => write!(f, "trades"),
+            Channel::OrderBooks => write!(f, "orderbooks"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Command {
+    command: &'static str,
+    channel: String,
+    symbol: String,
+}
+
+/// ## TickerUpdate
+/// `ticker`チャンネルで配信される最新レート
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TickerUpdate {
+    pub channel: String,
+    pub ask: String,
+    pub bid: String,
+    pub high: String,
+    pub last: String,
+    pub low: String,
+    pub symbol: String,
+    pub timestamp: String,
+    pub volume: String,
+}
+
+impl From<TickerUpdate> for LatestRate {
+    fn from(update: TickerUpdate) -> Self {
+        Self {
+            ask: update.ask,
+            bid: update.bid,
+            high: update.high,
+            last: update.last,
+            low: update.low,
+            symbol: update.symbol,
+            timestamp: update.timestamp,
+            volume: update.volume,
+        }
+    }
+}
+
+/// ## TradeUpdate
+/// `trades`チャンネルで配信される約定
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TradeUpdate {
+    pub channel: String,
+    pub price: String,
+    pub side: String,
+    pub size: String,
+    pub timestamp: String,
+    pub symbol: String,
+}
+
+impl From<TradeUpdate> for Trade {
+    fn from(update: TradeUpdate) -> Self {
+        Self {
+            price: update.price,
+            side: update.side,
+            size: update.size,
+            timestamp: update.timestamp,
+        }
+    }
+}
+
+/// ## Message
+/// Public WebSocket APIから受け取るメッセージ
+#[derive(Debug)]
+pub enum Message {
+    Ticker(TickerUpdate),
+    Trade(TradeUpdate),
+    OrderBook(OrderBookUpdate),
+}
+
+impl Message {
+    /// ### parse
+    /// テキストメッセージをチャンネルごとの型にする
+    ///
+    /// 知らないチャンネルのメッセージは`None`を返す。
+    pub fn parse(text: &str) -> Result<Option<Self>> {
+        let value: Value = serde_json::from_str(text)?;
+        if let Some(error) = value.get("error") {
+            return Err(GmoCoinError::WebSocketApi(match error {
+                Value::String(error) => error.clone(),
+                error => error.to_string(),
+            }));
+        }
+
+        let message = match value.get("channel").and_then(Value::as_str) {
+            Some("ticker") => Message::Ticker(serde_json::from_value(value)?),
+            Some("trades") => Message::Trade(serde_json::from_value(value)?),
+            Some("orderbooks") => Message::OrderBook(serde_json::from_value(value)?),
+            _ => return Ok(None),
+        };
+        Ok(Some(message))
+    }
+
+    pub fn channel(&self) -> &str {
+        match self {
+            Message::Ticker(update) => &update.channel,
+            Message::Trade(update) => &update.channel,
+            Message::OrderBook(update) => &update.channel,
+        }
+    }
+
+    pub fn timestamp(&self) -> &str {
+        match self {
+            Message::Ticker(update) => &update.timestamp,
+            Message::Trade(update) => &update.timestamp,
+            Message::OrderBook(update) => &update.timestamp,
+        }
+    }
+}
+
+/// ## PublicStream
+/// Public WebSocket APIの接続
+///
+/// 購読したチャンネルを覚えておき、`reconnect`で同じチャンネルを購読し直す。
+/// `next_event`を使えば切断したときに自動で再接続する。
+pub struct PublicStream {
+    socket: Socket,
+    url: String,
+    http: HttpOptions,
+    subscriptions: Vec<(Symbol, Channel)>,
+    last_command: Option<Instant>,
+    metrics: Option<Arc<Metrics>>,
+    backoff: Backoff,
+    client: Option<Arc<GmoCoinClient>>,
+    pending: VecDeque<StreamEvent<Message>>,
+    disconnected: bool,
+    monitor: Option<Monitor>,
+}
+
+impl PublicStream {
+    pub fn connect() -> Result<Self> {
+        Self::connect_to(endpoint::PUBLIC_WEBSOCK_API)
+    }
+
+    /// ### connect_to
+    /// 接続先を指定して接続する
+    pub fn connect_to(url: &str) -> Result<Self> {
+        Self::connect_with(url, &HttpOptions::default())
+    }
+
+    /// ### connect_with
+    /// `GmoCoinClient`と同じプロキシ・TLSの設定で接続する
+    pub fn connect_with(url: &str, http: &HttpOptions) -> Result<Self> {
+        Ok(Self {
+            socket: stream::open(url, http)?,
+            url: url.to_string(),
+            http: http.clone(),
+            subscriptions: Vec::new(),
+            last_command: None,
+            metrics: None,
+            backoff: Backoff::default(),
+            client: None,
+            pending: VecDeque::new(),
+            disconnected: false,
+            monitor: None,
+        })
+    }
+
+    /// ### metrics
+    /// 再接続の回数とメッセージの遅延を記録する
+    ///
+    /// 指定しなければ`resync`の`client`の`Metrics`に記録する。
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// ### backoff
+    /// `next_event`で再接続するときの待ち時間
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// ### watchdog
+    /// チャンネルの途絶とハートビートを監視する。途絶は`next_event`が返す
+    pub fn watchdog(mut self, watchdog: Watchdog) -> Self {
+        let mut monitor = Monitor::new(watchdog);
+        monitor.attach(&mut self.socket);
+        self.monitor = Some(monitor);
+        self
+    }
+
+    /// ### resync
+    /// 再接続したあと、購読している板を`client`のRESTで取り直す
+    pub fn resync(mut self, client: Arc<GmoCoinClient>) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// ### subscribe
+    /// チャンネルを購読する
+    ///
+    /// 取引所の制限に合わせて、前回のリクエストから1秒経つまで待つ。
+    pub fn subscribe(&mut self, symbol: Symbol, channel: Channel) -> Result<()> {
+        self.send_command("subscribe", symbol, channel)?;
+        if !self.subscriptions.contains(&(symbol, channel)) {
+            self.subscriptions.push((symbol, channel));
+        }
+        Ok(())
+    }
+
+    pub fn unsubscribe(&mut self, symbol: Symbol, channel: Channel) -> Result<()> {
+        self.send_command("unsubscribe", symbol, channel)?;
+        self.subscriptions.retain(|s| *s != (symbol, channel));
+        Ok(())
+    }
+
+    pub fn subscriptions(&self) -> &[(Symbol, Channel)] {
+        &self.subscriptions
+    }
+
+    /// ### read
+    /// 次のメッセージを受け取るまでブロックする
+    ///
+    /// Pingへの応答はtungsteniteが行う。
+    pub fn read(&mut self) -> Result<Message> {
+        loop {
+            if let Some(message) = self.poll()? {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// ### next_event
+    /// `read`と同じだが、切断したら`backoff`に従って再接続する
+    ///
+    /// `Backoff::max_attempts`回続けて失敗したらそのエラーを返す。
+    /// もう一度呼べば再び再接続を試みる。
+    pub fn next_event(&mut self) -> Result<StreamEvent<Message>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+            if self.disconnected {
+                let backoff = self.backoff.clone();
+                let attempts = stream::retry(&backoff, || self.reconnect())?;
+                self.disconnected = false;
+                self.queue_resync();
+                return Ok(StreamEvent::Reconnected { attempts });
+            }
+
+            match self.poll() {
+                Ok(Some(message)) => return Ok(StreamEvent::Message(message)),
+                Ok(None) => {
+                    if let Some(monitor) = &mut self.monitor {
+                        self.disconnected = monitor.watch(&mut self.pending);
+                    }
+                }
+                Err(e) if stream::is_disconnect(&e) => {
+                    self.disconnected = true;
+                    return Ok(StreamEvent::Disconnected(e));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// ### reconnect
+    /// 接続し直して、購読していたチャンネルをすべて購読し直す
+    pub fn reconnect(&mut self) -> Result<()> {
+        if let Some(metrics) = self.recorder() {
+            metrics.ws_reconnect(&self.url);
+        }
+        let _ = self.socket.close(None);
+        self.socket = stream::open(&self.url, &self.http)?;
+        if let Some(monitor) = &mut self.monitor {
+            monitor.attach(&mut self.socket);
+        }
+        for (symbol, channel) in self.subscriptions.clone() {
+            self.send_command("subscribe", symbol, channel)?;
+        }
+        Ok(())
+    }
+
+    pub fn close(mut self) -> Result<()> {
+        self.socket.close(None)?;
+        Ok(())
+    }
+
+    /// 次のメッセージ。`Watchdog`の読み込みのタイムアウトでは`None`を返す
+    fn poll(&mut self) -> Result<Option<Message>> {
+        let Some(text) = stream::read_text(&mut self.socket, self.monitor.as_mut())? else {
+            return Ok(None);
+        };
+        let message = Message::parse(&text)?;
+        if let Some(message) = &message {
+            self.observe(message);
+            if let Some(monitor) = &mut self.monitor {
+                monitor.seen(message.channel());
+            }
+        }
+        Ok(message)
+    }
+
+    fn queue_resync(&mut self) {
+        let Some(client) = &self.client else {
+            return;
+        };
+        for (symbol, channel) in &self.subscriptions {
+            if *channel != Channel::OrderBooks {
+                continue;
+            }
+            self.pending.push_back(match client.orderbooks(*symbol) {
+                Ok(response) => StreamEvent::Resync(Resync::OrderBook(*symbol, response)),
+                Err(e) => StreamEvent::ResyncFailed(e),
+            });
+        }
+    }
+
+    /// 記録先の`Metrics`。`metrics`が無ければ`resync`の`client`のもの
+    fn recorder(&self) -> Option<Arc<Metrics>> {
+        self.metrics
+            .clone()
+            .or_else(|| self.client.as_ref().map(|client| client.get_metrics()))
+    }
+
+    fn observe(&self, message: &Message) {
+        if let Some(metrics) = self.recorder()
+            && let Ok(timestamp) = OffsetDateTime::parse(message.timestamp(), &Rfc3339)
+        {
+            // 時計がずれていて負になる場合は0にする
+            let lag = (OffsetDateTime::now_utc() - timestamp)
+                .try_into()
+                .unwrap_or_default();
+            metrics.ws_message(message.channel(), lag);
+        }
+    }
+
+    fn send_command(
+        &mut self,
+        command: &'static str,
+        symbol: Symbol,
+        channel: Channel,
+    ) -> Result<()> {
+        let command = Command {
+            command,
+            channel: channel.to_string(),
+            symbol: symbol.to_string(),
+        };
+        stream::send_command(&mut self.socket, &mut self.last_command, &command)
+    }
+}
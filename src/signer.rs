@@ -0,0 +1,236 @@
+use crate::credentials::Secret;
+use crate::error::{GmoCoinError, Result};
+use crate::private::api::sign_request;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[cfg(unix)]
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// ## Signer
+/// `API-SIGN`を作る
+///
+/// 既定は`HmacSigner`でプロセス内で署名する。
+/// `UnixSocketSigner`を使えば、シークレットキーは別プロセスにだけ置ける。
+pub trait Signer: Send + Sync {
+    fn sign(&self, timestamp: u64, method: &str, path: &str, body: &str) -> Result<String>;
+}
+
+impl fmt::Debug for dyn Signer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Signer")
+    }
+}
+
+/// ## HmacSigner
+/// シークレットキーでHMAC-SHA256を計算する
+#[derive(Debug, Clone)]
+pub struct HmacSigner {
+    secret_key: Secret,
+}
+
+impl HmacSigner {
+    pub fn new<S: Into<Secret>>(secret_key: S) -> Self {
+        Self {
+            secret_key: secret_key.into(),
+        }
+    }
+}
+
+impl Signer for HmacSigner {
+    fn sign(&self, timestamp: u64, method: &str, path: &str, body: &str) -> Result<String> {
+        Ok(sign_request(
+            timestamp,
+            method,
+            path,
+            body,
+            self.secret_key.expose(),
+        ))
+    }
+}
+
+/// ## SignRequest
+/// 署名プロセスへの要求。1行1JSONで送る
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignRequest {
+    pub timestamp: u64,
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+/// ## SignResponse
+/// 署名プロセスからの応答。`sign`か`error`のどちらかを持つ
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sign: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// ## UnixSocketSigner
+/// Unixドメインソケットの先の署名プロセス(`serve`)に署名させる
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct UnixSocketSigner {
+    path: PathBuf,
+    timeout: Duration,
+}
+
+#[cfg(unix)]
+impl UnixSocketSigner {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// ### timeout
+    /// 応答を待つ時間。既定は5秒
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[cfg(unix)]
+impl Signer for UnixSocketSigner {
+    fn sign(&self, timestamp: u64, method: &str, path: &str, body: &str) -> Result<String> {
+        let unreachable =
+            |e: std::io::Error| GmoCoinError::Signer(format!("{}: {}", self.path.display(), e));
+        let mut stream = UnixStream::connect(&self.path).map_err(unreachable)?;
+        stream
+            .set_read_timeout(Some(self.timeout))
+            .map_err(unreachable)?;
+        stream
+            .set_write_timeout(Some(self.timeout))
+            .map_err(unreachable)?;
+
+        let request = SignRequest {
+            timestamp,
+            method: method.to_string(),
+            path: path.to_string(),
+            body: body.to_string(),
+        };
+        writeln!(stream, "{}", serde_json::to_string(&request)?).map_err(unreachable)?;
+
+        let mut line = String::new();
+        BufReader::new(&stream)
+            .read_line(&mut line)
+            .map_err(unreachable)?;
+        match serde_json::from_str::<SignResponse>(&line)? {
+            SignResponse {
+                sign: Some(sign), ..
+            } => Ok(sign),
+            SignResponse { error, .. } => Err(GmoCoinError::Signer(
+                error.unwrap_or_else(|| "empty response".to_string()),
+            )),
+        }
+    }
+}
+
+/// ## serve
+/// 署名プロセス側。`listener`で受けた要求に`signer`で署名して返す
+///
+/// 接続ごとにスレッドを分け、1つの接続で複数の要求を受け付ける。
+/// 60秒のあいだ何も送ってこない接続は閉じる。
+/// 接続ごとのエラーや受け付けの失敗は応答するか接続を閉じて続ける。
+#[cfg(unix)]
+pub fn serve<S: Signer + ?Sized>(listener: &UnixListener, signer: &S) -> Result<()> {
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            // 1つの接続の失敗で署名プロセスを止めない
+            let Ok(stream) = stream else {
+                // ファイルディスクリプタが尽きたときなどに空回りしない
+                std::thread::sleep(ACCEPT_RETRY);
+                continue;
+            };
+            scope.spawn(move || {
+                let _ = handle(stream, signer);
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+const SERVE_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[cfg(unix)]
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+/// ## bind
+/// ソケットを作り、所有者だけが読み書きできるようにする
+///
+/// 前回のソケットファイルが残っていれば消してから作る。
+/// `path`にソケット以外のファイルがあれば、上書きせずにエラーを返す。
+/// 所有者だけが入れる一時ディレクトリの中で作ってから`path`に移すので、
+/// 権限を絞る前のソケットに他のユーザーが接続することはない。
+#[cfg(unix)]
+pub fn bind<P: AsRef<Path>>(path: P) -> Result<UnixListener> {
+    use std::fs;
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+
+    let path = path.as_ref();
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(GmoCoinError::Signer(format!(
+                "{} exists and is not a socket",
+                path.display()
+            )));
+        }
+        fs::remove_file(path)?;
+    }
+    let mut dir = path.as_os_str().to_owned();
+    dir.push(format!(".{}.tmp", std::process::id()));
+    let dir = PathBuf::from(dir);
+    fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let staged = dir.join("sock");
+    let listener = UnixListener::bind(&staged).and_then(|listener| {
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o600))?;
+        fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_file(&staged);
+    fs::remove_dir(&dir)?;
+    Ok(listener?)
+}
+
+#[cfg(unix)]
+fn handle<S: Signer + ?Sized>(stream: UnixStream, signer: &S) -> Result<()> {
+    stream.set_read_timeout(Some(SERVE_IDLE_TIMEOUT))?;
+    stream.set_write_timeout(Some(SERVE_IDLE_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let response = match serde_json::from_str::<SignRequest>(&line?) {
+            Ok(request) => match signer.sign(
+                request.timestamp,
+                &request.method,
+                &request.path,
+                &request.body,
+            ) {
+                Ok(sign) => SignResponse {
+                    sign: Some(sign),
+                    error: None,
+                },
+                Err(e) => SignResponse {
+                    sign: None,
+                    error: Some(e.to_string()),
+                },
+            },
+            Err(e) => SignResponse {
+                sign: None,
+                error: Some(format!("invalid request: {}", e)),
+            },
+        };
+        writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use crate::GmoCoinClient;
+use crate::error::{GmoCoinError, Result};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// メンテナンス中に返ってくるメッセージコード(Private APIのみの場合を含む)
+pub(crate) const MAINTENANCE_CODES: &[&str] = &["ERR-5201", "ERR-5202"];
+
+/// ## ExchangeState
+/// 取引所の稼動状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExchangeState {
+    Maintenance,
+    PreOpen,
+    Open,
+}
+
+impl fmt::Display for self::ExchangeState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExchangeState::Maintenance => write!(f, "MAINTENANCE"),
+            ExchangeState::PreOpen => write!(f, "PREOPEN"),
+            ExchangeState::Open => write!(f, "OPEN"),
+        }
+    }
+}
+
+impl FromStr for self::ExchangeState {
+    type Err = GmoCoinError;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "MAINTENANCE" => Ok(ExchangeState::Maintenance),
+            "PREOPEN" => Ok(ExchangeState::PreOpen),
+            "OPEN" => Ok(ExchangeState::Open),
+            _ => Err(GmoCoinError::UnknownExchangeState(s.to_string())),
+        }
+    }
+}
+
+/// ## StatusChange
+/// 稼動状態の変化。`from`は最初の取得なら`None`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusChange {
+    pub from: Option<ExchangeState>,
+    pub to: ExchangeState,
+}
+
+/// ## MaintenancePolicy
+/// メンテナンス中のPrivate APIの呼び出しをどうするか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaintenancePolicy {
+    /// 何もせずに送る
+    #[default]
+    Ignore,
+    /// 送らずに`GmoCoinError::Maintenance`を返す
+    FailFast,
+    /// メンテナンスが終わるまで待ってから送る。`timeout`を過ぎたら`GmoCoinError::Maintenance`を返す
+    Wait { timeout: Option<Duration> },
+}
+
+/// ## ExchangeStatusTracker
+/// 最後に分かった稼動状態
+///
+/// `GmoCoinClient::status`の結果と、メンテナンスを示すエラーコードで更新される。
+/// `subscribe`で変化を受け取れる。
+#[derive(Debug, Default)]
+pub struct ExchangeStatusTracker {
+    state: Mutex<Option<ExchangeState>>,
+    changed: Condvar,
+    subscribers: Mutex<Vec<Sender<StatusChange>>>,
+}
+
+impl ExchangeStatusTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// まだ分からなければ`None`
+    pub fn state(&self) -> Option<ExchangeState> {
+        *self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn is_maintenance(&self) -> bool {
+        self.state() == Some(ExchangeState::Maintenance)
+    }
+
+    /// ### update
+    /// 状態を更新する。変わった場合は購読者に送り、その変化を返す
+    pub fn update(&self, to: ExchangeState) -> Option<StatusChange> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if *state == Some(to) {
+            return None;
+        }
+        let change = StatusChange { from: *state, to };
+        *state = Some(to);
+        drop(state);
+
+        self.changed.notify_all();
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|subscriber| subscriber.send(change).is_ok());
+        Some(change)
+    }
+
+    /// ### subscribe
+    /// これ以降の変化を受け取る
+    pub fn subscribe(&self) -> Receiver<StatusChange> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(tx);
+        rx
+    }
+
+    /// ### wait_until_available
+    /// メンテナンス中でなくなるまで待つ。`timeout`を過ぎたら`false`を返す
+    pub fn wait_until_available(&self, timeout: Option<Duration>) -> bool {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let maintenance =
+            |state: &mut Option<ExchangeState>| *state == Some(ExchangeState::Maintenance);
+        match timeout {
+            Some(timeout) => {
+                let (_state, result) = self
+                    .changed
+                    .wait_timeout_while(state, timeout, maintenance)
+                    .unwrap_or_else(PoisonError::into_inner);
+                !result.timed_out()
+            }
+            None => {
+                let _state = self
+                    .changed
+                    .wait_while(state, maintenance)
+                    .unwrap_or_else(PoisonError::into_inner);
+                true
+            }
+        }
+    }
+}
+
+/// ## StatusWatcher
+/// 稼動状態を一定の間隔で取得するスレッド
+///
+/// 取得した状態は`client`の`ExchangeStatusTracker`に入れる。
+/// `stop`するか破棄すると止まる。
+#[derive(Debug)]
+pub struct StatusWatcher {
+    tracker: Arc<ExchangeStatusTracker>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl StatusWatcher {
+    pub fn spawn(client: Arc<GmoCoinClient>, interval: Duration) -> Self {
+        let tracker = client.exchange_status();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let handle = thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                // 失敗しても次の取得で分かるので続ける
+                let _ = client.status();
+                thread::park_timeout(interval);
+            }
+        });
+
+        Self {
+            tracker,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn state(&self) -> Option<ExchangeState> {
+        self.tracker.state()
+    }
+
+    pub fn subscribe(&self) -> Receiver<StatusChange> {
+        self.tracker.subscribe()
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for StatusWatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,428 @@
+//! Public・Private WebSocket APIに共通の接続と再接続
+
+use crate::{
+    GmoCoinClient, Response, Symbol,
+    error::{GmoCoinError, Result},
+    http::{HttpOptions, Proxy, proxy},
+    private::api::{ActiveOrders, LatestExecutions},
+    public::api::Snapshot,
+    status::ExchangeState,
+};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::handshake::HandshakeError;
+use tungstenite::{Connector, Message as WsMessage, WebSocket, stream::MaybeTlsStream};
+
+/// 購読・解除のリクエストは1秒に1回まで
+const COMMAND_INTERVAL: Duration = Duration::from_secs(1);
+/// `Watchdog`が確かめる間隔の上限
+const MAX_TICK: Duration = Duration::from_secs(1);
+/// 読み込みのタイムアウトは0にできないので、これより短くしない
+const MIN_TICK: Duration = Duration::from_millis(10);
+
+pub(crate) type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+/// ## Backoff
+/// 再接続の待ち時間
+///
+/// 1回目は`initial`待ち、失敗するたびに倍にする(`max`まで)。
+/// `max_attempts`回続けて失敗したら諦める。`None`なら諦めない。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            max_attempts: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// ### delay
+    /// `attempt`回目(1から数える)の前に待つ時間
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// ## StreamEvent
+/// `next_event`で受け取る出来事
+///
+/// 切断すると`Disconnected`を返し、次の呼び出しで再接続する。
+/// つながると`Reconnected`に続けて、切れていた間を埋める`Resync`を返す。
+/// `Watchdog`がチャンネルの途絶を見つけると`Stale`を返す
+/// (`StaleAction::Reconnect`なら`Disconnected`を挟まずに再接続する)。
+#[derive(Debug)]
+pub enum StreamEvent<M> {
+    Message(M),
+    Disconnected(GmoCoinError),
+    Reconnected {
+        attempts: u32,
+    },
+    Resync(Resync),
+    /// 切れていた間を埋められなかった
+    ResyncFailed(GmoCoinError),
+    Stale(Stale),
+    /// `StaleAction::CancelAll`の取消に失敗した
+    ActionFailed(GmoCoinError),
+}
+
+/// ## Resync
+/// 再接続のあとにRESTで取り直した状態
+///
+/// 板はレスポンスの時刻で`OrderBook::apply_response`に渡せるよう、レスポンスごと返す。
+#[derive(Debug)]
+pub enum Resync {
+    OrderBook(Symbol, Response<Snapshot>),
+    ActiveOrders(Symbol, Vec<ActiveOrders>),
+    LatestExecutions(Symbol, Vec<LatestExecutions>),
+}
+
+/// ## Stale
+/// `channel`のメッセージが`silent`の間届いていない
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stale {
+    pub channel: String,
+    pub silent: Duration,
+}
+
+/// ## StaleAction
+/// チャンネルが途絶えたときにすること
+#[derive(Clone)]
+pub enum StaleAction {
+    /// 接続し直す
+    Reconnect,
+    /// コールバックを呼ぶ
+    Alert(Arc<dyn Fn(&Stale) + Send + Sync>),
+    /// `cancel_bulk_order`で銘柄の注文をすべて取り消す
+    CancelAll(Vec<Symbol>),
+}
+
+impl fmt::Debug for StaleAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StaleAction::Reconnect => write!(f, "Reconnect"),
+            StaleAction::Alert(_) => write!(f, "Alert"),
+            StaleAction::CancelAll(symbols) => f.debug_tuple("CancelAll").field(symbols).finish(),
+        }
+    }
+}
+
+/// ## Watchdog
+/// チャンネルの途絶とハートビートの監視
+///
+/// `stale_after`のチャンネルのメッセージが途絶えたら`on_stale`の動作をすべて行う。
+/// 取引所がメンテナンス中・取引開始前と分かっている間(`GmoCoinClient::exchange_status`)は数えない。
+/// `ping`を指定すると一定の間隔でPingを送り、Pongが返ってこなければ切断として扱う。
+#[derive(Clone)]
+pub struct Watchdog {
+    client: Arc<GmoCoinClient>,
+    stale_after: Vec<(String, Duration)>,
+    ping: Option<(Duration, Duration)>,
+    actions: Vec<StaleAction>,
+}
+
+impl fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("stale_after", &self.stale_after)
+            .field("ping", &self.ping)
+            .field("actions", &self.actions)
+            .finish()
+    }
+}
+
+impl Watchdog {
+    /// `client`で稼動状態を確かめ、注文を取り消す
+    pub fn new(client: Arc<GmoCoinClient>) -> Self {
+        Self {
+            client,
+            stale_after: Vec::new(),
+            ping: None,
+            actions: Vec::new(),
+        }
+    }
+
+    /// ### stale_after
+    /// `channel`(`Channel`・`PrivateChannel`)のメッセージが`after`の間届かなければ途絶とみなす
+    pub fn stale_after<C: ToString>(mut self, channel: C, after: Duration) -> Self {
+        self.stale_after.push((channel.to_string(), after));
+        self
+    }
+
+    /// ### ping
+    /// `interval`ごとにPingを送り、`timeout`以内にPongが無ければ切断する
+    pub fn ping(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.ping = Some((interval, timeout));
+        self
+    }
+
+    pub fn on_stale(mut self, action: StaleAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    /// ソケットの読み込みを待つ最長の時間
+    fn tick(&self) -> Duration {
+        self.stale_after
+            .iter()
+            .map(|(_, after)| *after)
+            .chain(
+                self.ping
+                    .iter()
+                    .flat_map(|(interval, timeout)| [*interval, *timeout]),
+            )
+            .fold(MAX_TICK, Duration::min)
+            .max(MIN_TICK)
+    }
+}
+
+/// `Watchdog`の監視の状態
+pub(crate) struct Monitor {
+    watchdog: Watchdog,
+    last_seen: HashMap<String, Instant>,
+    last_ping: Instant,
+    awaiting_pong: Option<Instant>,
+}
+
+impl Monitor {
+    pub(crate) fn new(watchdog: Watchdog) -> Self {
+        Self {
+            watchdog,
+            last_seen: HashMap::new(),
+            last_ping: Instant::now(),
+            awaiting_pong: None,
+        }
+    }
+
+    /// ### attach
+    /// (再)接続したソケットで数え直す
+    ///
+    /// 途絶を確かめられるよう、読み込みにタイムアウトを付ける。
+    pub(crate) fn attach(&mut self, socket: &mut Socket) {
+        let now = Instant::now();
+        self.last_seen = self
+            .watchdog
+            .stale_after
+            .iter()
+            .map(|(channel, _)| (channel.clone(), now))
+            .collect();
+        self.last_ping = now;
+        self.awaiting_pong = None;
+        set_read_timeout(socket, self.watchdog.tick());
+    }
+
+    pub(crate) fn seen(&mut self, channel: &str) {
+        if let Some(at) = self.last_seen.get_mut(channel) {
+            *at = Instant::now();
+        }
+    }
+
+    pub(crate) fn pong(&mut self) {
+        self.awaiting_pong = None;
+    }
+
+    /// ### heartbeat
+    /// Pingを送る時間なら送る。Pongが遅れていれば切断のエラーを返す
+    pub(crate) fn heartbeat(&mut self, socket: &mut Socket) -> Result<()> {
+        let Some((interval, timeout)) = self.watchdog.ping else {
+            return Ok(());
+        };
+        if let Some(sent) = self.awaiting_pong {
+            if sent.elapsed() >= timeout {
+                return Err(tungstenite::Error::Io(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no pong from the server",
+                ))
+                .into());
+            }
+        } else if self.last_ping.elapsed() >= interval {
+            socket.send(WsMessage::Ping(Default::default()))?;
+            self.last_ping = Instant::now();
+            self.awaiting_pong = Some(self.last_ping);
+        }
+        Ok(())
+    }
+
+    /// ### watch
+    /// 途絶えたチャンネルについて`Stale`を積み、`on_stale`の動作を行う
+    ///
+    /// 再接続すべきなら`true`を返す。同じチャンネルは次の`stale_after`が過ぎるまで数えない。
+    pub(crate) fn watch<M>(&mut self, pending: &mut VecDeque<StreamEvent<M>>) -> bool {
+        let now = Instant::now();
+        let state = self.watchdog.client.exchange_status().state();
+        if state.is_some_and(|state| state != ExchangeState::Open) {
+            self.last_seen.values_mut().for_each(|at| *at = now);
+            return false;
+        }
+
+        let mut reconnect = false;
+        for (channel, after) in &self.watchdog.stale_after {
+            let Some(at) = self.last_seen.get_mut(channel) else {
+                continue;
+            };
+            let silent = now - *at;
+            if silent < *after {
+                continue;
+            }
+            *at = now;
+
+            let stale = Stale {
+                channel: channel.clone(),
+                silent,
+            };
+            for action in &self.watchdog.actions {
+                match action {
+                    StaleAction::Reconnect => reconnect = true,
+                    StaleAction::Alert(alert) => alert(&stale),
+                    StaleAction::CancelAll(symbols) => {
+                        if let Err(e) = self.watchdog.client.cancel_bulk_order(symbols.clone()) {
+                            pending.push_back(StreamEvent::ActionFailed(e));
+                        }
+                    }
+                }
+            }
+            pending.push_back(StreamEvent::Stale(stale));
+        }
+        reconnect
+    }
+}
+
+/// 接続が切れたことによるエラーか。APIのエラーやパースの失敗は含まない
+pub(crate) fn is_disconnect(error: &GmoCoinError) -> bool {
+    matches!(error, GmoCoinError::WebSocket(_))
+}
+
+/// `backoff`の待ち時間を挟みながら`connect`を繰り返す。成功したら試した回数を返す
+pub(crate) fn retry<F>(backoff: &Backoff, mut connect: F) -> Result<u32>
+where
+    F: FnMut() -> Result<()>,
+{
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        thread::sleep(backoff.delay(attempt));
+        match connect() {
+            Ok(()) => return Ok(attempt),
+            Err(e) if backoff.max_attempts.is_some_and(|max| attempt >= max) => return Err(e),
+            Err(_) => {}
+        }
+    }
+}
+
+/// 購読・解除のリクエストを送る
+///
+/// 取引所の制限に合わせて、前回のリクエストから1秒経つまで待つ。
+pub(crate) fn send_command<T: Serialize>(
+    socket: &mut Socket,
+    last_command: &mut Option<Instant>,
+    command: &T,
+) -> Result<()> {
+    if let Some(elapsed) = last_command.map(|at| at.elapsed())
+        && elapsed < COMMAND_INTERVAL
+    {
+        thread::sleep(COMMAND_INTERVAL - elapsed);
+    }
+
+    socket.send(WsMessage::text(serde_json::to_string(command)?))?;
+    *last_command = Some(Instant::now());
+    Ok(())
+}
+
+/// ソケットの読み込みにタイムアウトを付ける
+pub(crate) fn set_read_timeout(socket: &mut Socket, timeout: Duration) {
+    let tcp = match socket.get_mut() {
+        MaybeTlsStream::Plain(stream) => stream,
+        MaybeTlsStream::Rustls(stream) => stream.get_mut(),
+        _ => return,
+    };
+    // 0でなければ失敗しない
+    let _ = tcp.set_read_timeout(Some(timeout.max(MIN_TICK)));
+}
+
+/// ### read_text
+/// 次のテキストメッセージを受け取る。Pingへの応答はtungsteniteが行う
+///
+/// `monitor`があればハートビートを送る。読み込みのタイムアウトでは`None`を返す。
+pub(crate) fn read_text(
+    socket: &mut Socket,
+    mut monitor: Option<&mut Monitor>,
+) -> Result<Option<String>> {
+    loop {
+        if let Some(monitor) = monitor.as_deref_mut() {
+            monitor.heartbeat(socket)?;
+        }
+        match socket.read() {
+            Ok(WsMessage::Text(text)) => return Ok(Some(text.as_str().to_string())),
+            Ok(WsMessage::Pong(_)) => {
+                if let Some(monitor) = monitor.as_deref_mut() {
+                    monitor.pong();
+                }
+            }
+            Ok(WsMessage::Close(_)) => {
+                return Err(tungstenite::Error::ConnectionClosed.into());
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// プロキシが無ければ環境変数のものを使う(ureq・reqwestと同じ)
+pub(crate) fn open(url: &str, http: &HttpOptions) -> Result<Socket> {
+    let request = url.into_client_request()?;
+    let uri = request.uri();
+    let host = uri.host().unwrap_or_default().to_string();
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("wss") {
+            443
+        } else {
+            80
+        });
+
+    let proxy = http
+        .proxy
+        .clone()
+        .or_else(Proxy::from_env)
+        .filter(|p| !p.inner().is_no_proxy(uri));
+    let stream = proxy::connect(
+        proxy.as_ref().map(Proxy::inner),
+        &host,
+        port,
+        http.connect_timeout,
+    )
+    .map_err(tungstenite::Error::Io)?;
+    let connector = http.tls.client_config().map(Connector::Rustls);
+    let (socket, _) = tungstenite::client_tls_with_config(request, stream, None, connector)
+        .map_err(|e| match e {
+            HandshakeError::Failure(e) => e,
+            HandshakeError::Interrupted(_) => {
+                tungstenite::Error::Io(io::ErrorKind::WouldBlock.into())
+            }
+        })?;
+    Ok(socket)
+}
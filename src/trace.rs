@@ -0,0 +1,220 @@
+//! リクエストごとの`tracing`のspanとevent
+//!
+//! `tracing`フィーチャが無効なら何もしない。
+//! `API-KEY`・`API-SIGN`の値は出力しない。
+
+use crate::error::Result;
+use crate::private::api::SignedRequest;
+use serde_json::Value;
+
+#[cfg(feature = "tracing")]
+use crate::error::GmoCoinError;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+/// ## RequestLog
+/// 1回のリクエストのspan。作ってからの経過時間をlatencyとして記録する
+pub(crate) struct RequestLog {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    start: Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl RequestLog {
+    pub(crate) fn new(method: &str, path: &str, symbol: Option<&str>) -> Self {
+        let span = tracing::debug_span!(
+            "gmo_coin.request",
+            method,
+            path,
+            symbol = tracing::field::Empty
+        );
+        if let Some(symbol) = symbol {
+            span.record("symbol", symbol);
+        }
+        Self {
+            span,
+            start: Instant::now(),
+        }
+    }
+
+    /// ### signed
+    /// Private APIのリクエスト。ヘッダは`API-TIMESTAMP`だけを値のまま出力する
+    pub(crate) fn signed(request: &SignedRequest) -> Self {
+        let query = request
+            .query
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()));
+        let log = Self::new(
+            request.method,
+            request.path,
+            symbol(query, &request.body).as_deref(),
+        );
+        tracing::debug!(
+            parent: &log.span,
+            api_key = "***",
+            api_timestamp = request.timestamp,
+            api_sign = "***",
+            "request"
+        );
+        log
+    }
+
+    /// ### response
+    /// レスポンスを読んだ結果。`status`が0でなければwarnにする
+    pub(crate) fn response(&self, http_status: u16, bytes: usize, body: &Value) {
+        let latency_ms = self.latency_ms();
+        let gmo_status = body["status"].as_i64().unwrap_or(-1);
+        if gmo_status == 0 {
+            tracing::debug!(
+                parent: &self.span,
+                http_status,
+                gmo_status,
+                latency_ms,
+                bytes,
+                "response"
+            );
+        } else {
+            tracing::warn!(
+                parent: &self.span,
+                http_status,
+                gmo_status,
+                message_codes = %message_codes(&body["messages"]),
+                latency_ms,
+                bytes,
+                "response"
+            );
+        }
+    }
+
+    /// ### http_response
+    /// 本文を読まない場合(async)のレスポンス
+    #[cfg(feature = "async")]
+    pub(crate) fn http_response(&self, http_status: u16, bytes: Option<u64>) {
+        tracing::debug!(
+            parent: &self.span,
+            http_status,
+            latency_ms = self.latency_ms(),
+            bytes,
+            "response"
+        );
+    }
+
+    /// ### finish
+    /// エラーならwarnにする。`GmoCoinError::Api`は`response`で出力済み
+    pub(crate) fn finish<T>(&self, result: Result<T>) -> Result<T> {
+        if let Err(error) = &result
+            && !matches!(error, GmoCoinError::Api { .. })
+        {
+            tracing::warn!(
+                parent: &self.span,
+                http_status = http_status(error),
+                latency_ms = self.latency_ms(),
+                error = %error,
+                "request failed"
+            );
+        }
+        result
+    }
+
+    fn latency_ms(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl RequestLog {
+    pub(crate) fn new(_method: &str, _path: &str, _symbol: Option<&str>) -> Self {
+        Self {}
+    }
+
+    pub(crate) fn signed(_request: &SignedRequest) -> Self {
+        Self {}
+    }
+
+    pub(crate) fn response(&self, _http_status: u16, _bytes: usize, _body: &Value) {}
+
+    #[cfg(feature = "async")]
+    pub(crate) fn http_response(&self, _http_status: u16, _bytes: Option<u64>) {}
+
+    pub(crate) fn finish<T>(&self, result: Result<T>) -> Result<T> {
+        result
+    }
+}
+
+/// ### clock_skew
+/// サーバーの時計とのずれが閾値を超えた
+#[cfg(feature = "tracing")]
+pub(crate) fn clock_skew(offset_ms: i64) {
+    tracing::warn!(
+        offset_ms,
+        "clock skew against the exchange exceeds the threshold"
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn clock_skew(_offset_ms: i64) {}
+
+/// ### send
+/// asyncのリクエストを送り、HTTPのステータスがエラーならエラーにする
+#[cfg(feature = "async")]
+pub(crate) async fn send(
+    log: &RequestLog,
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response> {
+    let result = match request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        Ok(response) => {
+            log.http_response(response.status().as_u16(), response.content_length());
+            Ok(response)
+        }
+        Err(e) => Err(e.into()),
+    };
+    log.finish(result)
+}
+
+/// クエリかJSONの本文から`symbol`を探す
+pub(crate) fn symbol<'a, I: IntoIterator<Item = (&'a str, &'a str)>>(
+    query: I,
+    body: &str,
+) -> Option<String> {
+    if let Some((_, symbol)) = query.into_iter().find(|(key, _)| *key == "symbol") {
+        return Some(symbol.to_string());
+    }
+    if body.is_empty() {
+        return None;
+    }
+    serde_json::from_str::<Value>(body)
+        .ok()?
+        .get("symbol")?
+        .as_str()
+        .map(str::to_string)
+}
+
+#[cfg(feature = "tracing")]
+fn message_codes(messages: &Value) -> String {
+    messages
+        .as_array()
+        .map(|messages| {
+            messages
+                .iter()
+                .filter_map(|message| message["message_code"].as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "tracing")]
+fn http_status(error: &GmoCoinError) -> Option<u16> {
+    match error {
+        GmoCoinError::Ureq(ureq::Error::StatusCode(status)) => Some(*status),
+        #[cfg(feature = "async")]
+        GmoCoinError::Reqwest(error) => error.status().map(|status| status.as_u16()),
+        _ => None,
+    }
+}
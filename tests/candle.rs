@@ -0,0 +1,162 @@
+use gmo_coin::candle::{CandleAggregator, CandleEvent};
+use gmo_coin::public::api::Trade;
+use rust_decimal::Decimal;
+use std::time::Duration;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+fn trade(timestamp: &str, price: &str, size: &str) -> Trade {
+    Trade {
+        price: price.to_string(),
+        side: "BUY".to_string(),
+        size: size.to_string(),
+        timestamp: timestamp.to_string(),
+    }
+}
+
+fn dec(s: &str) -> Decimal {
+    s.parse().unwrap()
+}
+
+fn datetime(s: &str) -> OffsetDateTime {
+    OffsetDateTime::parse(s, &Rfc3339).unwrap()
+}
+
+#[test]
+fn push_emits_updates_and_closes() {
+    let mut aggregator = CandleAggregator::new(Duration::from_secs(3));
+
+    let events = aggregator
+        .push(&trade("2024-01-01T00:00:00.500Z", "100", "1"))
+        .unwrap();
+    assert!(matches!(events.as_slice(), [CandleEvent::Updated(_)]));
+
+    aggregator
+        .push(&trade("2024-01-01T00:00:02.999Z", "105", "2"))
+        .unwrap();
+    let events = aggregator
+        .push(&trade("2024-01-01T00:00:03.000Z", "99", "1"))
+        .unwrap();
+
+    match events.as_slice() {
+        [CandleEvent::Closed(closed), CandleEvent::Updated(current)] => {
+            assert_eq!(closed.open_time, datetime("2024-01-01T00:00:00Z"));
+            assert_eq!(closed.close_time, datetime("2024-01-01T00:00:03Z"));
+            assert_eq!(closed.open, dec("100"));
+            assert_eq!(closed.high, dec("105"));
+            assert_eq!(closed.low, dec("100"));
+            assert_eq!(closed.close, dec("105"));
+            assert_eq!(closed.volume, dec("3"));
+            assert_eq!(closed.trades, 2);
+            assert_eq!(current.open, dec("99"));
+        }
+        other => panic!("unexpected events: {:?}", other),
+    }
+}
+
+#[test]
+fn bars_do_not_cross_the_business_day() {
+    // 7分足は1日を割り切れないので、JSTの6時(21:00Z)直前の足は6時で打ち切られる
+    let candles = CandleAggregator::aggregate(
+        Duration::from_secs(7 * 60),
+        &[
+            trade("2024-01-01T20:59:00Z", "100", "1"),
+            trade("2024-01-01T21:01:00Z", "101", "1"),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(candles.len(), 2);
+    assert_eq!(candles[0].close_time, datetime("2024-01-01T21:00:00Z"));
+    assert_eq!(candles[1].open_time, datetime("2024-01-01T21:00:00Z"));
+    assert_eq!(candles[1].close_time, datetime("2024-01-01T21:07:00Z"));
+}
+
+#[test]
+fn sub_day_bars_share_the_daily_origin() {
+    // 4時間足は6時・10時・…・2時(JST)に始まり、日足と同じ6時で切り替わる
+    let candles = CandleAggregator::aggregate(
+        Duration::from_secs(4 * 60 * 60),
+        &[
+            trade("2024-01-01T20:59:59Z", "100", "1"),
+            trade("2024-01-01T21:00:00Z", "101", "1"),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(candles[0].open_time, datetime("2024-01-01T17:00:00Z"));
+    assert_eq!(candles[0].close_time, datetime("2024-01-01T21:00:00Z"));
+    assert_eq!(candles[1].open_time, datetime("2024-01-01T21:00:00Z"));
+}
+
+#[test]
+fn late_trade_does_not_replace_close() {
+    let mut aggregator = CandleAggregator::new(Duration::from_secs(60));
+    for (timestamp, price) in [
+        ("2024-01-01T00:00:10Z", "100"),
+        ("2024-01-01T00:00:30Z", "105"),
+        ("2024-01-01T00:00:20Z", "90"),
+    ] {
+        aggregator.push(&trade(timestamp, price, "1")).unwrap();
+    }
+
+    let candle = aggregator.current().unwrap();
+    assert_eq!(candle.close, dec("105"));
+    assert_eq!(candle.low, dec("90"));
+    assert_eq!(candle.volume, dec("3"));
+    assert_eq!(candle.trades, 3);
+}
+
+#[test]
+fn daily_bars_roll_over_at_six_jst() {
+    let candles = CandleAggregator::aggregate(
+        Duration::from_secs(24 * 60 * 60),
+        &[
+            trade("2024-01-01T20:59:59Z", "100", "1"),
+            trade("2024-01-01T21:00:00Z", "101", "1"),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(candles.len(), 2);
+    assert_eq!(candles[0].open_time, datetime("2023-12-31T21:00:00Z"));
+    assert_eq!(candles[0].close_time, datetime("2024-01-01T21:00:00Z"));
+    assert_eq!(candles[1].open_time, datetime("2024-01-01T21:00:00Z"));
+}
+
+#[test]
+fn aggregate_sorts_rest_trades() {
+    let candles = CandleAggregator::aggregate(
+        Duration::from_secs(120),
+        &[
+            trade("2024-01-01T00:03:00Z", "103", "1"),
+            trade("2024-01-01T00:01:00Z", "101", "1"),
+            trade("2024-01-01T00:00:00Z", "100", "1"),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(candles.len(), 2);
+    assert_eq!(candles[0].open, dec("100"));
+    assert_eq!(candles[0].close, dec("101"));
+    assert_eq!(candles[1].open, dec("103"));
+}
+
+#[test]
+fn close_until_closes_idle_bar() {
+    let mut aggregator = CandleAggregator::new(Duration::from_secs(60));
+    aggregator
+        .push(&trade("2024-01-01T00:00:10Z", "100", "1"))
+        .unwrap();
+
+    assert!(
+        aggregator
+            .close_until(datetime("2024-01-01T00:00:59Z"))
+            .is_none()
+    );
+    assert!(matches!(
+        aggregator.close_until(datetime("2024-01-01T00:01:00Z")),
+        Some(CandleEvent::Closed(_))
+    ));
+    assert!(aggregator.current().is_none());
+}
@@ -0,0 +1,167 @@
+use gmo_coin::GmoCoinClient;
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::private::api::margin_request;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::{Duration, OffsetDateTime};
+
+/// 1リクエストごとに`respond(i)`の(追加のヘッダ, 本文)を返すサーバー。受け取ったリクエストを送る
+fn serve<F>(count: usize, respond: F) -> (String, Receiver<String>)
+where
+    F: Fn(usize) -> (String, String) + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for i in 0..count {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let (headers, body) = respond(i);
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                headers,
+                body.len(),
+                body
+            )
+            .unwrap();
+            tx.send(request).unwrap();
+        }
+    });
+    (url, rx)
+}
+
+fn ahead(offset: Duration) -> String {
+    (OffsetDateTime::now_utc() + offset)
+        .format(&Rfc3339)
+        .unwrap()
+}
+
+fn api_timestamp(request: &str) -> i64 {
+    request
+        .lines()
+        .find_map(|line| {
+            line.to_ascii_lowercase()
+                .strip_prefix("api-timestamp:")
+                .map(|value| value.trim().parse().unwrap())
+        })
+        .unwrap()
+}
+
+fn now_ms() -> i64 {
+    (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+fn timestamp_error(code: &str, responsetime: String) -> String {
+    format!(
+        r#"{{"status":1,"messages":[{{"message_code":"{}","message_string":"The API-TIMESTAMP is out of range"}}],"responsetime":"{}"}}"#,
+        code, responsetime
+    )
+}
+
+const MARGIN: &str = r#"{"status":0,"data":{"actualProfitLoss":"0","availableAmount":"0","margin":"0","profitLoss":"0"},"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+
+#[test]
+fn offset_from_responsetime() {
+    let (url, _) = serve(1, |_| {
+        let body = format!(
+            r#"{{"status":0,"data":{{"status":"OPEN"}},"responsetime":"{}"}}"#,
+            ahead(Duration::seconds(10))
+        );
+        (String::new(), body)
+    });
+    let client = GmoCoinClient::from_env().public_api(&url);
+    assert_eq!(client.clock_offset_ms(), 0);
+
+    let offset = client.sync_clock().unwrap();
+    assert!((offset - 10_000).abs() < 500, "offset {}", offset);
+    assert_eq!(client.clock_offset_ms(), offset);
+}
+
+#[test]
+fn offset_from_date_header() {
+    let (url, _) = serve(1, |_| {
+        let date = (OffsetDateTime::now_utc() + Duration::hours(1))
+            .format(&Rfc2822)
+            .unwrap()
+            .replace("+0000", "GMT");
+        (format!("Date: {}\r\n", date), MARGIN.to_string())
+    });
+    let client = GmoCoinClient::from_env()
+        .private_api(&url)
+        .credentials("key", "secret");
+
+    let signed = client.sign(margin_request()).unwrap();
+    client.send(&signed).unwrap();
+
+    let offset = client.clock_offset_ms();
+    assert!((offset - 3_600_000).abs() < 1_500, "offset {}", offset);
+}
+
+#[test]
+fn retry_once_after_timestamp_error() {
+    let (url, requests) = serve(2, |i| match i {
+        0 => (
+            String::new(),
+            timestamp_error("ERR-5009", ahead(Duration::seconds(5))),
+        ),
+        _ => (String::new(), MARGIN.to_string()),
+    });
+    let client = GmoCoinClient::from_env()
+        .private_api(&url)
+        .credentials("key", "secret");
+
+    let margin: Value = client.call(margin_request()).unwrap();
+    assert_eq!(margin["status"], 0);
+
+    let first = api_timestamp(&requests.recv().unwrap());
+    let second = api_timestamp(&requests.recv().unwrap());
+    assert!((first - now_ms()).abs() < 1_000);
+    assert!(
+        (second - first - 5_000).abs() < 1_000,
+        "{} {}",
+        first,
+        second
+    );
+    assert!(
+        client
+            .get_metrics()
+            .render()
+            .contains("gmo_coin_retries_total{path=\"/v1/account/margin\"} 1\n")
+    );
+}
+
+#[test]
+fn retry_only_once() {
+    let (url, requests) = serve(2, |_| {
+        (
+            String::new(),
+            timestamp_error("ERR-5008", ahead(Duration::ZERO)),
+        )
+    });
+    let client = GmoCoinClient::from_env()
+        .private_api(&url)
+        .credentials("key", "secret");
+
+    match client.call::<Value>(margin_request()) {
+        Err(GmoCoinError::Api { messages, .. }) => {
+            assert_eq!(messages[0].message_code, "ERR-5008")
+        }
+        other => panic!("expected Api error, got {:?}", other),
+    }
+    assert_eq!(requests.iter().take(3).count(), 2);
+}
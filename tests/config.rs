@@ -0,0 +1,150 @@
+use gmo_coin::client::RateLimitTier;
+use gmo_coin::config::{Config, SecretSource};
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::{GmoCoinClient, Symbol};
+use rust_decimal::Decimal;
+use std::env;
+use std::str::FromStr;
+
+const SAMPLE: &str = r#"
+default_profile = "main"
+
+[profiles.main]
+api_key = "main-key"
+secret_key = { env = "PATH" }
+rate_limit_tier = 2
+default_symbol = "BTC_JPY"
+timeout = 10
+
+[profiles.main.limits]
+max_order_size = "0.1"
+max_notional = "1000000"
+
+[profiles.sandbox]
+public_api = "http://localhost:8080/public"
+private_api = "http://localhost:8080/private"
+"#;
+
+#[test]
+fn parse_profiles() {
+    let config = Config::parse(SAMPLE).unwrap();
+
+    assert_eq!(config.default_profile.as_deref(), Some("main"));
+    assert_eq!(config.profiles.len(), 2);
+
+    let main = &config.profiles["main"];
+    assert_eq!(main.api_key, Some(SecretSource::Value("main-key".into())));
+    assert_eq!(
+        main.secret_key,
+        Some(SecretSource::Env {
+            env: "PATH".to_string()
+        })
+    );
+    assert_eq!(main.rate_limit_tier, Some(2));
+    assert_eq!(main.timeout, Some(10));
+    assert_eq!(
+        main.limits.max_order_size,
+        Some(Decimal::from_str("0.1").unwrap())
+    );
+    assert_eq!(main.limits.max_notional, Some(Decimal::from(1_000_000)));
+}
+
+#[test]
+fn unknown_field_is_rejected() {
+    let result = Config::parse("[profiles.main]\napi_secret = \"x\"\n");
+
+    assert!(matches!(result, Err(GmoCoinError::ConfigParse(_))));
+}
+
+#[test]
+fn parse_error_points_at_the_line() {
+    // 行頭から始まるエラー
+    let result = Config::parse("[profiles.main]\napi_secret = \"x\"\n");
+    match result {
+        Err(GmoCoinError::ConfigParse(message)) => assert!(message.starts_with("line 2: ")),
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+
+    let result = Config::parse("default_profile = \"main\"\n[profiles.main]\nbogus = 1\n");
+    match result {
+        Err(GmoCoinError::ConfigParse(message)) => assert!(message.starts_with("line 3: ")),
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn select_profile() {
+    let config = Config::parse(SAMPLE).unwrap();
+
+    let sandbox = config.profile(Some("sandbox")).unwrap();
+    assert_eq!(
+        sandbox.public_api.as_deref(),
+        Some("http://localhost:8080/public")
+    );
+
+    match config.profile(Some("missing")) {
+        Err(GmoCoinError::ProfileNotFound(name)) => assert_eq!(name, "missing"),
+        other => panic!("expected ProfileNotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn empty_config_uses_defaults() {
+    let profile = Config::default().profile(Some("default"));
+    assert!(matches!(profile, Err(GmoCoinError::ProfileNotFound(_))));
+
+    let client = GmoCoinClient::from_config(&Config::default(), None).unwrap();
+    assert_eq!(client.get_rate_limit_tier(), RateLimitTier::Tier1);
+    assert_eq!(client.get_default_symbol(), None);
+}
+
+#[test]
+fn resolve_secret_sources() {
+    assert_eq!(
+        SecretSource::Value("key".into())
+            .resolve()
+            .unwrap()
+            .expose(),
+        "key"
+    );
+    assert_eq!(
+        SecretSource::Env {
+            env: "PATH".to_string()
+        }
+        .resolve()
+        .unwrap()
+        .expose(),
+        env::var("PATH").unwrap()
+    );
+
+    assert!(SecretSource::Command { command: vec![] }.resolve().is_err());
+}
+
+#[test]
+fn secret_is_not_in_debug_output() {
+    let config = Config::parse(SAMPLE).unwrap();
+
+    assert!(!format!("{:?}", config).contains("main-key"));
+}
+
+#[test]
+fn client_from_profile() {
+    let config = Config::parse(SAMPLE).unwrap();
+    let client = GmoCoinClient::from_config(&config, Some("main")).unwrap();
+
+    assert_eq!(client.get_rate_limit_tier(), RateLimitTier::Tier2);
+    assert_eq!(client.get_default_symbol(), Some(Symbol::BTC_JPY));
+    assert_eq!(
+        client.get_limits().max_order_size,
+        Some(Decimal::from_str("0.1").unwrap())
+    );
+}
+
+#[test]
+fn invalid_profile_values_are_rejected() {
+    let config = Config::parse("[profiles.main]\nrate_limit_tier = 3\n").unwrap();
+    assert!(GmoCoinClient::from_config(&config, Some("main")).is_err());
+
+    let config = Config::parse("[profiles.main]\ndefault_symbol = \"XXX\"\n").unwrap();
+    assert!(GmoCoinClient::from_config(&config, Some("main")).is_err());
+}
@@ -0,0 +1,127 @@
+use gmo_coin::GmoCoinClient;
+use gmo_coin::config::Config;
+use gmo_coin::credentials::{
+    CommandProvider, CredentialProvider, Credentials, EnvProvider, FileProvider, Secret,
+};
+use gmo_coin::private::api;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// テストごとに別の一時ファイルを作る
+fn secret_file(name: &str, content: &str, mode: u32) -> PathBuf {
+    let path = env::temp_dir().join(format!("gmo_coin_{}_{}", name, std::process::id()));
+    fs::write(&path, content).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    path
+}
+
+#[test]
+fn secret_is_redacted() {
+    let secret = Secret::new("very-secret");
+
+    assert_eq!(format!("{}", secret), "***");
+    assert_eq!(format!("{:?}", secret), "***");
+    assert_eq!(secret.expose(), "very-secret");
+
+    let credentials = Credentials::new("api-key-value", "secret-key-value");
+    let debug = format!("{:?}", credentials);
+    assert!(!debug.contains("api-key-value"));
+    assert!(!debug.contains("secret-key-value"));
+}
+
+#[test]
+fn file_provider_reads_private_files() {
+    let api_key = secret_file("api_key", "file-api-key\n", 0o600);
+    let secret_key = secret_file("secret_key", "  file-secret-key  \n", 0o600);
+    let credentials = FileProvider::new(&api_key, &secret_key).credentials();
+    fs::remove_file(&api_key).unwrap();
+    fs::remove_file(&secret_key).unwrap();
+
+    let credentials = credentials.unwrap();
+    assert_eq!(credentials.api_key.expose(), "file-api-key");
+    assert_eq!(credentials.secret_key.expose(), "file-secret-key");
+}
+
+#[cfg(unix)]
+#[test]
+fn file_provider_refuses_world_readable_files() {
+    let api_key = secret_file("public_api_key", "leaked-api-key", 0o644);
+    let secret_key = secret_file("public_secret_key", "leaked-secret-key", 0o600);
+    let result = FileProvider::new(&api_key, &secret_key).credentials();
+    fs::remove_file(&api_key).unwrap();
+    fs::remove_file(&secret_key).unwrap();
+
+    let message = result.unwrap_err().to_string();
+    assert!(message.contains("world-readable"), "{}", message);
+    assert!(!message.contains("leaked-api-key"));
+}
+
+#[cfg(unix)]
+#[test]
+fn command_provider_reads_stdout() {
+    let provider = CommandProvider::new(
+        vec!["echo".to_string(), "command-api-key".to_string()],
+        vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo command-secret-key; echo noise >&2".to_string(),
+        ],
+    );
+    let credentials = provider.credentials().unwrap();
+
+    assert_eq!(credentials.api_key.expose(), "command-api-key");
+    assert_eq!(credentials.secret_key.expose(), "command-secret-key");
+
+    let failing = CommandProvider::new(
+        vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo leaked-output; echo leaked-stderr >&2; exit 3".to_string(),
+        ],
+        vec![],
+    );
+    let message = failing.credentials().unwrap_err().to_string();
+    assert!(!message.contains("leaked"), "{}", message);
+}
+
+#[test]
+fn missing_env_names_only_the_variable() {
+    let provider = EnvProvider::new(
+        "GMO_COIN_TEST_MISSING_API_KEY",
+        "GMO_COIN_TEST_MISSING_SECRET_KEY",
+    );
+    let message = provider.credentials().unwrap_err().to_string();
+
+    assert!(
+        message.contains("GMO_COIN_TEST_MISSING_API_KEY"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn config_parse_error_does_not_echo_values() {
+    let error =
+        Config::parse("[profiles.main]\napi_key = \"leaked-key\"\ntimeout = \"leaked-secret\"\n")
+            .unwrap_err();
+
+    assert!(!error.to_string().contains("leaked"), "{}", error);
+    assert!(!format!("{:?}", error).contains("leaked"), "{:?}", error);
+}
+
+#[test]
+fn client_uses_provider() {
+    let client = GmoCoinClient::from_env().credentials("provided-api-key", "provided-secret-key");
+    let signed = client.sign(api::margin_request()).unwrap();
+
+    assert_eq!(signed.api_key.expose(), "provided-api-key");
+    assert!(!format!("{:?}", signed).contains("provided-api-key"));
+    assert!(!format!("{:?}", client).contains("provided-secret-key"));
+}
@@ -0,0 +1,147 @@
+use gmo_coin::GmoCoinClient;
+use gmo_coin::config::Config;
+use gmo_coin::http::{HttpOptions, USER_AGENT};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const STATUS: &str =
+    r#"{"status":0,"data":{"status":"OPEN"},"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+
+/// keep-aliveに対応したサーバー。受け付けた接続の数と、受け取ったリクエストを返す
+fn serve() -> (String, Arc<AtomicUsize>, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            accepted.fetch_add(1, Ordering::SeqCst);
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut request = String::new();
+                    loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        request.push_str(&line);
+                        if line == "\r\n" {
+                            break;
+                        }
+                    }
+                    let _ = tx.send(request);
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        STATUS.len(),
+                        STATUS
+                    )
+                    .unwrap();
+                }
+            });
+        }
+    });
+    (url, connections, rx)
+}
+
+fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+    request.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+#[test]
+fn connections_are_reused() {
+    let (url, connections, _) = serve();
+    let client = GmoCoinClient::from_env().public_api(&url);
+
+    for _ in 0..3 {
+        client.status().unwrap();
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn keep_alive_can_be_disabled() {
+    let (url, connections, _) = serve();
+    let client = GmoCoinClient::from_env()
+        .public_api(&url)
+        .http_options(HttpOptions {
+            keep_alive: false,
+            ..Default::default()
+        });
+
+    for _ in 0..3 {
+        client.status().unwrap();
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn user_agent() {
+    let (url, _, requests) = serve();
+
+    GmoCoinClient::from_env().public_api(&url).status().unwrap();
+    assert_eq!(
+        header(&requests.recv().unwrap(), "user-agent"),
+        Some(USER_AGENT)
+    );
+
+    GmoCoinClient::from_env()
+        .public_api(&url)
+        .user_agent("my-bot/1.0")
+        .status()
+        .unwrap();
+    assert_eq!(
+        header(&requests.recv().unwrap(), "user-agent"),
+        Some("my-bot/1.0")
+    );
+}
+
+#[test]
+fn read_timeout() {
+    // 受け付けるだけで応答しない
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let _streams: Vec<_> = listener.incoming().collect();
+    });
+
+    let client = GmoCoinClient::from_env()
+        .public_api(&url)
+        .read_timeout(Duration::from_millis(100));
+    let start = Instant::now();
+    assert!(client.status().is_err());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn timeouts_from_profile() {
+    let config = Config::parse(
+        "[profiles.main]\ntimeout = 20\nconnect_timeout = 3\nread_timeout = 5\nuser_agent = \"my-bot/1.0\"\n",
+    )
+    .unwrap();
+    let client = GmoCoinClient::from_config(&config, Some("main")).unwrap();
+
+    let options = client.get_http_options();
+    assert_eq!(options.timeout, Some(Duration::from_secs(20)));
+    assert_eq!(options.connect_timeout, Some(Duration::from_secs(3)));
+    assert_eq!(options.read_timeout, Some(Duration::from_secs(5)));
+    assert_eq!(options.user_agent, "my-bot/1.0");
+    assert!(options.keep_alive);
+
+    assert_eq!(
+        GmoCoinClient::from_env().get_http_options(),
+        &HttpOptions::default()
+    );
+}
@@ -0,0 +1,71 @@
+use gmo_coin::Symbol;
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::public::api::{self, KlineDate, KlineInterval};
+use time::macros::{date, datetime};
+
+#[test]
+fn dates_are_formatted_per_interval() {
+    assert_eq!(
+        KlineDate::from(date!(2024 - 03 - 05)).to_string(),
+        "20240305"
+    );
+    assert_eq!(KlineDate::from(2024).to_string(), "2024");
+}
+
+#[test]
+fn containing_respects_six_oclock_rollover() {
+    // 2024-03-05 05:59 JST は前日の営業日
+    assert_eq!(
+        KlineDate::containing(datetime!(2024-03-04 20:59 UTC), KlineInterval::OneMin),
+        KlineDate::Day(date!(2024 - 03 - 04))
+    );
+    // 2024-03-05 06:00 JST
+    assert_eq!(
+        KlineDate::containing(datetime!(2024-03-04 21:00 UTC), KlineInterval::OneMin),
+        KlineDate::Day(date!(2024 - 03 - 05))
+    );
+    // 2024-01-01 08:00 JST
+    assert_eq!(
+        KlineDate::containing(datetime!(2023-12-31 23:00 UTC), KlineInterval::OneDay),
+        KlineDate::Year(2024)
+    );
+}
+
+#[test]
+fn impossible_combinations_are_rejected() {
+    assert!(
+        KlineDate::from(date!(2024 - 03 - 05))
+            .validate(KlineInterval::OneHour)
+            .is_ok()
+    );
+    assert!(
+        KlineDate::from(2024)
+            .validate(KlineInterval::FourHour)
+            .is_ok()
+    );
+
+    for (date, interval) in [
+        (
+            KlineDate::from(date!(2024 - 03 - 05)),
+            KlineInterval::OneDay,
+        ),
+        (KlineDate::from(2024), KlineInterval::OneMin),
+        (
+            KlineDate::from(date!(2021 - 04 - 14)),
+            KlineInterval::OneMin,
+        ),
+    ] {
+        assert!(matches!(
+            date.validate(interval),
+            Err(GmoCoinError::InvalidKlineDate { .. })
+        ));
+    }
+}
+
+#[test]
+fn klines_rejects_before_sending() {
+    assert!(matches!(
+        api::klines(Symbol::BTC, KlineInterval::OneDay, date!(2024 - 03 - 05)),
+        Err(GmoCoinError::InvalidKlineDate { .. })
+    ));
+}
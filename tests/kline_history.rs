@@ -0,0 +1,118 @@
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::public::api::{Kline, KlineInterval};
+use gmo_coin::public::history::{KlineDownloader, KlineGap, find_gaps};
+use gmo_coin::{GmoCoinClient, Symbol};
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::macros::date;
+
+fn kline(open_time: u64) -> Kline {
+    Kline {
+        open_time: open_time.to_string(),
+        open: "100".to_string(),
+        high: "100".to_string(),
+        low: "100".to_string(),
+        close: "100".to_string(),
+        volume: "1".to_string(),
+    }
+}
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gmo_coin_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn write_cache(dir: &Path, date: &str, klines: &[Kline]) {
+    let path = dir.join("BTC").join("1min");
+    fs::create_dir_all(&path).unwrap();
+    fs::write(
+        path.join(format!("{}.json", date)),
+        serde_json::to_string(klines).unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn find_gaps_reports_missing_candles() {
+    let klines = [kline(0), kline(60_000), kline(240_000)];
+
+    assert_eq!(
+        find_gaps(&klines, KlineInterval::OneMin).unwrap(),
+        vec![KlineGap {
+            from: 60_000,
+            to: 240_000
+        }]
+    );
+    assert!(
+        find_gaps(&klines, KlineInterval::OneMonth)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn download_merges_cached_days() {
+    let dir = cache_dir("merge");
+    write_cache(&dir, "20240101", &[kline(60_000), kline(0)]);
+    write_cache(&dir, "20240102", &[kline(60_000), kline(120_000)]);
+
+    let klines = KlineDownloader::new(Symbol::BTC, KlineInterval::OneMin)
+        .cache_dir(&dir)
+        .download(
+            &GmoCoinClient::from_env(),
+            date!(2024 - 01 - 01),
+            date!(2024 - 01 - 02),
+        )
+        .unwrap();
+
+    let open_times: Vec<&str> = klines.iter().map(|k| k.open_time.as_str()).collect();
+    assert_eq!(open_times, ["0", "60000", "120000"]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn download_tolerates_gaps_unless_disallowed() {
+    let dir = cache_dir("gaps");
+    write_cache(&dir, "20240101", &[kline(0), kline(180_000)]);
+    let client = GmoCoinClient::from_env();
+
+    let downloader = KlineDownloader::new(Symbol::BTC, KlineInterval::OneMin).cache_dir(&dir);
+    let klines = downloader
+        .download(&client, date!(2024 - 01 - 01), date!(2024 - 01 - 01))
+        .unwrap();
+    assert_eq!(klines.len(), 2);
+    assert_eq!(
+        find_gaps(&klines, KlineInterval::OneMin).unwrap(),
+        [KlineGap {
+            from: 0,
+            to: 180_000
+        }]
+    );
+
+    assert!(matches!(
+        downloader.allow_gaps(false).download(
+            &client,
+            date!(2024 - 01 - 01),
+            date!(2024 - 01 - 01)
+        ),
+        Err(GmoCoinError::KlineGap { .. })
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn download_rejects_reversed_range() {
+    let downloader = KlineDownloader::new(Symbol::BTC, KlineInterval::OneMin);
+
+    assert!(matches!(
+        downloader.download(
+            &GmoCoinClient::from_env(),
+            date!(2024 - 01 - 02),
+            date!(2024 - 01 - 01)
+        ),
+        Err(GmoCoinError::InvalidDateRange { .. })
+    ));
+}
@@ -0,0 +1,168 @@
+use gmo_coin::GmoCoinClient;
+use gmo_coin::metrics::{self, Metrics};
+use gmo_coin::private::api::margin_request;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const MAINTENANCE: &str = r#"{"status":5,"messages":[{"message_code":"ERR-5201","message_string":"MAINTENANCE. Please wait for a while"}],"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+const STATUS: &str =
+    r#"{"status":0,"data":{"status":"OPEN"},"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+
+/// `count`回だけ`body`を返すサーバー
+fn serve(count: usize, status: u16, body: &'static str) -> (String, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        for _ in 0..count {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut content = vec![0; length];
+            reader.read_exact(&mut content).unwrap();
+
+            write!(
+                stream,
+                "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+    (url, handle)
+}
+
+#[test]
+fn render_counters_and_histograms() {
+    let metrics = Metrics::new();
+    metrics.request("GET", "/v1/ticker", Duration::from_millis(70), None);
+    metrics.request("GET", "/v1/ticker", Duration::from_millis(300), None);
+    metrics.rate_limit_wait("GET");
+    metrics.retry("/v1/order");
+    metrics.ws_reconnect("wss://example.com/ws");
+    metrics.ws_message("trades", Duration::from_millis(40));
+
+    let text = metrics.render();
+    assert!(text.contains("# TYPE gmo_coin_requests_total counter\n"));
+    assert!(text.contains("gmo_coin_requests_total{method=\"GET\",path=\"/v1/ticker\"} 2\n"));
+    assert!(text.contains("# TYPE gmo_coin_request_duration_seconds histogram\n"));
+    assert!(
+        text.contains(
+            "gmo_coin_request_duration_seconds_bucket{path=\"/v1/ticker\",le=\"0.1\"} 1\n"
+        )
+    );
+    assert!(
+        text.contains(
+            "gmo_coin_request_duration_seconds_bucket{path=\"/v1/ticker\",le=\"0.5\"} 2\n"
+        )
+    );
+    assert!(
+        text.contains(
+            "gmo_coin_request_duration_seconds_bucket{path=\"/v1/ticker\",le=\"+Inf\"} 2\n"
+        )
+    );
+    assert!(text.contains("gmo_coin_request_duration_seconds_count{path=\"/v1/ticker\"} 2\n"));
+    assert!(text.contains("gmo_coin_rate_limit_waits_total{method=\"GET\"} 1\n"));
+    assert!(text.contains("gmo_coin_retries_total{path=\"/v1/order\"} 1\n"));
+    assert!(text.contains("gmo_coin_ws_reconnects_total{url=\"wss://example.com/ws\"} 1\n"));
+    assert!(
+        text.contains("gmo_coin_ws_message_lag_seconds_bucket{channel=\"trades\",le=\"0.05\"} 1\n")
+    );
+}
+
+#[test]
+fn api_errors_are_counted_by_message_code() {
+    let (url, server) = serve(1, 200, MAINTENANCE);
+    let client = GmoCoinClient::from_env()
+        .private_api(&url)
+        .credentials("key", "secret");
+
+    assert!(client.call::<Value>(margin_request()).is_err());
+    server.join().unwrap();
+
+    let text = client.get_metrics().render();
+    assert!(
+        text.contains("gmo_coin_requests_total{method=\"GET\",path=\"/v1/account/margin\"} 1\n")
+    );
+    assert!(text.contains(
+        "gmo_coin_request_errors_total{path=\"/v1/account/margin\",code=\"ERR-5201\"} 1\n"
+    ));
+}
+
+#[test]
+fn http_errors_and_rate_limit_waits_are_counted() {
+    let (url, server) = serve(2, 503, "{}");
+    let metrics = Arc::new(Metrics::new());
+    let client = GmoCoinClient::from_env()
+        .public_api(&url)
+        .metrics(metrics.clone());
+
+    // Tier1は50msに1回なので、続けて呼ぶと2回目は待たされる
+    assert!(client.status().is_err());
+    assert!(client.status().is_err());
+    server.join().unwrap();
+
+    let text = metrics.render();
+    assert!(
+        text.contains("gmo_coin_request_errors_total{path=\"/v1/status\",code=\"http_503\"} 2\n")
+    );
+    assert!(text.contains("gmo_coin_rate_limit_waits_total{method=\"GET\"} 1\n"));
+}
+
+#[test]
+fn shared_metrics_across_clients() {
+    let (url, server) = serve(2, 200, STATUS);
+    let metrics = Arc::new(Metrics::new());
+    let a = GmoCoinClient::from_env()
+        .public_api(&url)
+        .metrics(metrics.clone());
+    let b = GmoCoinClient::from_env()
+        .public_api(&url)
+        .metrics(metrics.clone());
+
+    a.status().unwrap();
+    b.status().unwrap();
+    server.join().unwrap();
+
+    assert!(
+        metrics
+            .render()
+            .contains("gmo_coin_requests_total{method=\"GET\",path=\"/v1/status\"} 2\n")
+    );
+}
+
+#[test]
+fn serve_metrics_over_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let metrics = Arc::new(Metrics::new());
+    metrics.retry("/v1/order");
+    let served = metrics.clone();
+    thread::spawn(move || metrics::serve(&listener, &served));
+
+    // 何も送らない接続があっても他の接続に応答する
+    let _idle = TcpStream::connect(addr).unwrap();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+    assert!(response.ends_with(&metrics.render()));
+}
@@ -0,0 +1,333 @@
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::private::api::{ActiveOrders, Execution};
+use gmo_coin::private::tracker::{OrderStatus, OrderTracker, TrackerError};
+use gmo_coin::{GmoCoinClient, Symbol};
+use rust_decimal::Decimal;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+fn dec(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+fn active_order(order_id: usize, status: &str, executed_size: &str) -> ActiveOrders {
+    serde_json::from_value(serde_json::json!({
+        "rootOrderId": order_id,
+        "orderId": order_id,
+        "symbol": "BTC_JPY",
+        "side": "BUY",
+        "orderType": "NORMAL",
+        "executionType": "LIMIT",
+        "settleType": "OPEN",
+        "size": "1",
+        "executedSize": executed_size,
+        "price": "5000000",
+        "losscutPrice": "0",
+        "status": status,
+        "timeInForce": "FAS",
+        "timestamp": "2024-01-01T00:00:00.000Z"
+    }))
+    .unwrap()
+}
+
+fn execution(execution_id: usize, order_id: usize, price: &str, size: &str) -> Execution {
+    serde_json::from_value(serde_json::json!({
+        "executionId": execution_id,
+        "orderId": order_id,
+        "symbol": "BTC_JPY",
+        "side": "BUY",
+        "settleType": "OPEN",
+        "size": size,
+        "price": price,
+        "lossGain": "0",
+        "fee": "10",
+        "timestamp": "2024-01-01T00:00:01.000Z"
+    }))
+    .unwrap()
+}
+
+fn tracker_error(result: gmo_coin::GmoCoinResult<()>) -> TrackerError {
+    match result {
+        Err(GmoCoinError::Tracker(TrackerError::Rejected(mut errors))) if errors.len() == 1 => {
+            errors.remove(0)
+        }
+        Err(GmoCoinError::Tracker(error)) => error,
+        other => panic!("expected a tracker error, got {:?}", other),
+    }
+}
+
+#[test]
+fn fills_accumulate_into_average_price_and_fees() {
+    let fills = Arc::new(Mutex::new(Vec::new()));
+    let seen = fills.clone();
+    let mut tracker = OrderTracker::new().on_fill(move |order, fill| {
+        seen.lock()
+            .unwrap()
+            .push((order.order_id, fill.execution_id));
+    });
+    tracker
+        .apply_active_orders(&[active_order(1, "ORDERED", "0")])
+        .unwrap();
+
+    tracker
+        .apply_execution(&execution(10, 1, "5000000", "0.4"))
+        .unwrap();
+    tracker
+        .apply_execution(&execution(11, 1, "4990000", "0.6"))
+        .unwrap();
+    // 同じ約定は数えない
+    tracker
+        .apply_execution(&execution(11, 1, "4990000", "0.6"))
+        .unwrap();
+    // 知らない注文の約定は無視する
+    tracker
+        .apply_execution(&execution(12, 2, "4990000", "0.6"))
+        .unwrap();
+
+    let order = tracker.get(1).unwrap();
+    assert_eq!(order.executed_size, dec("1"));
+    assert_eq!(order.filled_size, dec("1"));
+    assert_eq!(order.average_price(), Some(dec("4994000")));
+    assert_eq!(order.fee, dec("20"));
+    assert_eq!(order.remaining_size(), Decimal::ZERO);
+    assert_eq!(*fills.lock().unwrap(), [(1, 10), (1, 11)]);
+    // 注文数量まで約定したら状態の通知を待たずに終わらせる
+    assert_eq!(order.status, OrderStatus::EXECUTED);
+    assert_eq!(tracker.open_orders().count(), 0);
+
+    tracker
+        .apply_active_orders(&[active_order(1, "EXECUTED", "1")])
+        .unwrap();
+    assert_eq!(tracker.get(1).unwrap().status, OrderStatus::EXECUTED);
+}
+
+#[test]
+fn late_fills_of_a_finished_order_are_counted_once() {
+    let mut tracker = OrderTracker::new();
+    tracker
+        .apply_active_orders(&[active_order(2, "ORDERED", "0")])
+        .unwrap();
+    // 一部約定したまま取り消され、約定は後から届く
+    tracker
+        .apply_active_orders(&[active_order(2, "CANCELED", "0.4")])
+        .unwrap();
+    for _ in 0..2 {
+        tracker
+            .apply_execution(&execution(20, 2, "5000000", "0.4"))
+            .unwrap();
+    }
+
+    let order = tracker.get(2).unwrap();
+    assert_eq!(order.status, OrderStatus::CANCELED);
+    assert_eq!(order.filled_size, dec("0.4"));
+    assert_eq!(order.fee, dec("10"));
+}
+
+#[test]
+fn batch_applies_valid_orders_and_collects_errors() {
+    let mut tracker = OrderTracker::new();
+    tracker
+        .apply_active_orders(&[active_order(1, "ORDERED", "0.5")])
+        .unwrap();
+
+    let result = tracker.apply_active_orders(&[
+        active_order(1, "ORDERED", "0.2"),
+        active_order(2, "ORDERED", "0"),
+        active_order(3, "UNKNOWN", "0"),
+        active_order(4, "ORDERED", "0"),
+    ]);
+    match result {
+        Err(GmoCoinError::Tracker(TrackerError::Rejected(errors))) => {
+            assert_eq!(errors.len(), 2);
+            assert!(matches!(
+                errors[0],
+                TrackerError::ExecutedSizeDecreased { order_id: 1, .. }
+            ));
+            assert!(matches!(errors[1], TrackerError::UnknownStatus(_)));
+        }
+        other => panic!("expected rejected orders, got {:?}", other),
+    }
+    assert!(tracker.get(2).is_some());
+    assert!(tracker.get(3).is_none());
+    assert!(tracker.get(4).is_some());
+}
+
+#[test]
+fn cancel_and_expire_callbacks() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let (canceled, expired) = (events.clone(), events.clone());
+    let mut tracker = OrderTracker::new()
+        .on_cancel(move |order| canceled.lock().unwrap().push(("cancel", order.order_id)))
+        .on_expire(move |order| expired.lock().unwrap().push(("expire", order.order_id)));
+    tracker
+        .apply_active_orders(&[
+            active_order(1, "ORDERED", "0"),
+            active_order(2, "WAITING", "0"),
+        ])
+        .unwrap();
+
+    tracker
+        .apply_active_orders(&[active_order(1, "CANCELLING", "0")])
+        .unwrap();
+    tracker
+        .apply_active_orders(&[
+            active_order(1, "CANCELED", "0"),
+            active_order(2, "EXPIRED", "0"),
+        ])
+        .unwrap();
+    // 終わった注文の同じ通知では呼ばない
+    tracker
+        .apply_active_orders(&[active_order(1, "CANCELED", "0")])
+        .unwrap();
+
+    assert_eq!(*events.lock().unwrap(), [("cancel", 1), ("expire", 2)]);
+}
+
+#[test]
+fn impossible_changes_are_rejected() {
+    let mut tracker = OrderTracker::new();
+    tracker
+        .apply_active_orders(&[active_order(1, "ORDERED", "0.5")])
+        .unwrap();
+
+    assert_eq!(
+        tracker_error(tracker.apply_active_orders(&[active_order(1, "ORDERED", "0.2")])),
+        TrackerError::ExecutedSizeDecreased {
+            order_id: 1,
+            from: dec("0.5"),
+            to: dec("0.2"),
+        }
+    );
+    assert_eq!(
+        tracker_error(tracker.apply_active_orders(&[active_order(1, "WAITING", "0.5")])),
+        TrackerError::Transition {
+            order_id: 1,
+            from: OrderStatus::ORDERED,
+            to: OrderStatus::WAITING,
+        }
+    );
+    assert!(matches!(
+        tracker_error(tracker.apply_execution(&execution(10, 1, "5000000", "1.5"))),
+        TrackerError::Overfilled { order_id: 1, .. }
+    ));
+
+    tracker
+        .apply_active_orders(&[active_order(1, "EXECUTED", "1")])
+        .unwrap();
+    assert_eq!(
+        tracker_error(tracker.apply_active_orders(&[active_order(1, "ORDERED", "1")])),
+        TrackerError::Transition {
+            order_id: 1,
+            from: OrderStatus::EXECUTED,
+            to: OrderStatus::ORDERED,
+        }
+    );
+    assert!(matches!(
+        tracker_error(tracker.apply_active_orders(&[active_order(1, "UNKNOWN", "1")])),
+        TrackerError::UnknownStatus(_)
+    ));
+    // 取り込まなかった変化は状態に残らない
+    assert_eq!(tracker.get(1).unwrap().status, OrderStatus::EXECUTED);
+    assert_eq!(tracker.get(1).unwrap().filled_size, Decimal::ZERO);
+}
+
+#[cfg(feature = "websocket")]
+#[test]
+fn private_events() {
+    use gmo_coin::private::websock_api::PrivateMessage;
+
+    let messages = [
+        r#"{"channel":"orderEvents","orderId":5,"symbol":"BTC_JPY","settleType":"OPEN","executionType":"LIMIT","side":"SELL","orderStatus":"ORDERED","orderTimestamp":"2024-01-01T00:00:00.000Z","orderPrice":"5000000","orderSize":"0.2","orderExecutedSize":"0","losscutPrice":"0","timeInForce":"FAS","msgType":"NOR"}"#,
+        r#"{"channel":"executionEvents","orderId":5,"executionId":50,"symbol":"BTC_JPY","settleType":"OPEN","executionType":"LIMIT","side":"SELL","executionPrice":"5000000","executionSize":"0.1","positionId":7,"orderTimestamp":"2024-01-01T00:00:00.000Z","executionTimestamp":"2024-01-01T00:00:01.000Z","lossGain":"0","fee":"5","orderPrice":"5000000","orderSize":"0.2","orderExecutedSize":"0.1","timeInForce":"FAS","msgType":"ER"}"#,
+        r#"{"channel":"executionEvents","orderId":5,"executionId":51,"symbol":"BTC_JPY","settleType":"OPEN","executionType":"LIMIT","side":"SELL","executionPrice":"5010000","executionSize":"0.10","positionId":7,"orderTimestamp":"2024-01-01T00:00:00.000Z","executionTimestamp":"2024-01-01T00:00:02.000Z","lossGain":"0","fee":"5","orderPrice":"5000000","orderSize":"0.2","orderExecutedSize":"0.20","timeInForce":"FAS","msgType":"ER"}"#,
+    ];
+    let mut tracker = OrderTracker::new();
+    for text in messages {
+        let message = PrivateMessage::parse(text).unwrap().unwrap();
+        tracker.apply_message(&message).unwrap();
+    }
+
+    let order = tracker.get(5).unwrap();
+    assert_eq!(order.status, OrderStatus::EXECUTED);
+    assert_eq!(order.executed_size, dec("0.2"));
+    assert_eq!(order.average_price(), Some(dec("5005000")));
+    assert_eq!(order.fee, dec("10"));
+}
+
+/// `orders`と`executions`に決まった応答を返すRESTのサーバー
+fn rest() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/private", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut rest = String::new();
+            while reader.read_line(&mut rest).unwrap_or(0) > 2 {
+                rest.clear();
+            }
+
+            let body = if line.contains("/v1/orders") {
+                r#"{"status":0,"data":{"list":[{"rootOrderId":1,"orderId":1,"symbol":"BTC_JPY","side":"BUY","orderType":"NORMAL","executionType":"LIMIT","settleType":"OPEN","size":"1","executedSize":"1","price":"5000000","losscutPrice":"0","status":"EXECUTED","timeInForce":"FAS","timestamp":"2024-01-01T00:00:00.000Z"}]},"responsetime":"2024-01-01T00:00:00.000Z"}"#
+            } else {
+                r#"{"status":0,"data":{"list":[{"executionId":10,"orderId":1,"symbol":"BTC_JPY","side":"BUY","settleType":"OPEN","size":"1","price":"4999000","lossGain":"0","fee":"50","timestamp":"2024-01-01T00:00:01.000Z"}]},"responsetime":"2024-01-01T00:00:00.000Z"}"#
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+    url
+}
+
+#[test]
+fn reconcile_queries_orders_missing_from_the_snapshot() {
+    let canceled = Arc::new(Mutex::new(Vec::new()));
+    let seen = canceled.clone();
+    let client = GmoCoinClient::from_env()
+        .credentials("api-key", "secret-key")
+        .private_api(&rest());
+    let mut tracker =
+        OrderTracker::new().on_cancel(move |order| seen.lock().unwrap().push(order.order_id));
+    tracker
+        .apply_active_orders(&[
+            active_order(1, "ORDERED", "0"),
+            active_order(2, "ORDERED", "0"),
+        ])
+        .unwrap();
+
+    // 切断中に注文1が約定して一覧から消えた
+    tracker
+        .reconcile(&client, Symbol::BTC_JPY, &[active_order(2, "ORDERED", "0")])
+        .unwrap();
+    let order = tracker.get(1).unwrap();
+    assert_eq!(order.status, OrderStatus::EXECUTED);
+    assert_eq!(order.fee, dec("50"));
+    assert_eq!(tracker.get(2).unwrap().status, OrderStatus::ORDERED);
+    assert!(canceled.lock().unwrap().is_empty());
+}
+
+#[test]
+fn refresh_polls_orders_and_executions() {
+    let client = GmoCoinClient::from_env()
+        .credentials("api-key", "secret-key")
+        .private_api(&rest());
+    let mut tracker = OrderTracker::new();
+    tracker
+        .apply_active_orders(&[active_order(1, "ORDERED", "0")])
+        .unwrap();
+
+    tracker.refresh(&client).unwrap();
+    let order = tracker.get(1).unwrap();
+    assert_eq!(order.status, OrderStatus::EXECUTED);
+    assert_eq!(order.average_price(), Some(dec("4999000")));
+    assert_eq!(order.fee, dec("50"));
+}
@@ -0,0 +1,112 @@
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::private::api::ExecutionType;
+use gmo_coin::private::validation::{OrderValidationError, OrderValidator, Rounding};
+use gmo_coin::public::api::SymbolRule;
+use gmo_coin::{GmoCoinClient, Side, Symbol};
+
+fn rules() -> Vec<SymbolRule> {
+    vec![SymbolRule {
+        symbol: "BTC_JPY".to_string(),
+        min_order_size: "0.01".to_string(),
+        max_order_size: "5".to_string(),
+        size_step: "0.01".to_string(),
+        tick_size: "1".to_string(),
+        taker_fee: "0".to_string(),
+        maker_fee: "0".to_string(),
+    }]
+}
+
+fn validation_error(result: gmo_coin::GmoCoinResult<String>) -> OrderValidationError {
+    match result {
+        Err(GmoCoinError::OrderValidation(err)) => err,
+        other => panic!("expected a validation error, got {:?}", other),
+    }
+}
+
+#[test]
+fn aligned_values_pass_through() {
+    let validator = OrderValidator::from_rules(&rules()).unwrap();
+
+    assert_eq!(
+        validator.check_price(Symbol::BTC_JPY, "5000000").unwrap(),
+        "5000000"
+    );
+    assert_eq!(
+        validator.check_size(Symbol::BTC_JPY, "0.10").unwrap(),
+        "0.1"
+    );
+}
+
+#[test]
+fn misaligned_values_are_rejected_without_rounding() {
+    let validator = OrderValidator::from_rules(&rules()).unwrap();
+
+    assert!(matches!(
+        validation_error(validator.check_price(Symbol::BTC_JPY, "5000000.5")),
+        OrderValidationError::PriceTick { .. }
+    ));
+    assert!(matches!(
+        validation_error(validator.check_size(Symbol::BTC_JPY, "0.015")),
+        OrderValidationError::SizeStep { .. }
+    ));
+}
+
+#[test]
+fn values_are_rounded_when_configured() {
+    let validator = OrderValidator::from_rules(&rules())
+        .unwrap()
+        .price_rounding(Rounding::Nearest)
+        .size_rounding(Rounding::Floor);
+
+    assert_eq!(
+        validator.check_price(Symbol::BTC_JPY, "5000000.5").unwrap(),
+        "5000001"
+    );
+    assert_eq!(
+        validator.check_size(Symbol::BTC_JPY, "0.019").unwrap(),
+        "0.01"
+    );
+    assert!(matches!(
+        validation_error(validator.check_size(Symbol::BTC_JPY, "0.009")),
+        OrderValidationError::SizeTooSmall { .. }
+    ));
+}
+
+#[test]
+fn limits_and_unknown_symbols_are_rejected() {
+    let validator = OrderValidator::from_rules(&rules()).unwrap();
+
+    assert!(matches!(
+        validation_error(validator.check_size(Symbol::BTC_JPY, "5.01")),
+        OrderValidationError::SizeTooLarge { .. }
+    ));
+    assert!(matches!(
+        validation_error(validator.check_size(Symbol::BTC_JPY, "-1")),
+        OrderValidationError::NotPositive { .. }
+    ));
+    assert!(matches!(
+        validation_error(validator.check_size(Symbol::ETH, "1")),
+        OrderValidationError::UnknownSymbol(_)
+    ));
+}
+
+#[test]
+fn orders_go_through_the_given_client() {
+    let validator = OrderValidator::from_rules(&rules()).unwrap();
+    let client = GmoCoinClient::from_env()
+        .credentials("api-key", "secret-key")
+        .read_only(true);
+
+    let result = validator.order(
+        &client,
+        Symbol::BTC_JPY,
+        Side::BUY,
+        ExecutionType::LIMIT,
+        Some("5000000".to_string()),
+        "0.01".to_string(),
+    );
+    assert!(matches!(result, Err(GmoCoinError::ReadOnly(path)) if path == "/v1/order"));
+
+    let result = validator.change_order(&client, Symbol::BTC_JPY, 1, "5000000".to_string(), None);
+    assert!(matches!(result, Err(GmoCoinError::ReadOnly(path)) if path == "/v1/changeOrder"));
+}
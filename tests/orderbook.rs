@@ -0,0 +1,120 @@
+use gmo_coin::Side;
+use gmo_coin::orderbook::{OrderBook, OrderBookUpdate};
+use gmo_coin::public::api::{Ask, Bid};
+use rust_decimal::Decimal;
+use std::time::Duration;
+
+fn dec(s: &str) -> Decimal {
+    s.parse().unwrap()
+}
+
+fn update(timestamp: &str, asks: &[(&str, &str)], bids: &[(&str, &str)]) -> OrderBookUpdate {
+    OrderBookUpdate {
+        channel: "orderbooks".to_string(),
+        asks: asks
+            .iter()
+            .map(|(price, size)| Ask {
+                price: price.to_string(),
+                size: size.to_string(),
+            })
+            .collect(),
+        bids: bids
+            .iter()
+            .map(|(price, size)| Bid {
+                price: price.to_string(),
+                size: size.to_string(),
+            })
+            .collect(),
+        symbol: "BTC".to_string(),
+        timestamp: timestamp.to_string(),
+    }
+}
+
+fn sample_book() -> OrderBook {
+    let mut book = OrderBook::new();
+    let applied = book
+        .apply_update(&update(
+            "2024-01-01T00:00:00.000Z",
+            &[("101", "1"), ("103", "3"), ("102", "2")],
+            &[("99", "4"), ("100", "1"), ("98", "5")],
+        ))
+        .unwrap();
+    assert!(applied);
+    book
+}
+
+#[test]
+fn levels_are_sorted_numerically() {
+    let mut book = OrderBook::new();
+    book.apply_update(&update(
+        "2024-01-01T00:00:00.000Z",
+        &[("1000", "1"), ("999.5", "1")],
+        &[("99", "1"), ("100", "1")],
+    ))
+    .unwrap();
+
+    assert_eq!(book.best_ask().unwrap().price, dec("999.5"));
+    assert_eq!(book.best_bid().unwrap().price, dec("100"));
+}
+
+#[test]
+fn top_of_book_statistics() {
+    let book = sample_book();
+
+    assert_eq!(book.spread(), Some(dec("1")));
+    assert_eq!(book.mid(), Some(dec("100.5")));
+    // (100 * 1 + 101 * 1) / 2
+    assert_eq!(book.microprice(), Some(dec("100.5")));
+    assert!(!book.is_crossed());
+}
+
+#[test]
+fn cumulative_depth_and_ticks() {
+    let book = sample_book();
+
+    assert_eq!(book.depth_to(Side::SELL, dec("102")), dec("3"));
+    assert_eq!(book.depth_to(Side::BUY, dec("99")), dec("5"));
+    assert_eq!(book.size_within_ticks(Side::SELL, 0, dec("1")), dec("1"));
+    assert_eq!(book.size_within_ticks(Side::BUY, 2, dec("1")), dec("10"));
+}
+
+#[test]
+fn older_updates_are_ignored() {
+    let mut book = sample_book();
+    let applied = book
+        .apply_update(&update(
+            "2023-12-31T23:59:59.000Z",
+            &[("200", "1")],
+            &[("199", "1")],
+        ))
+        .unwrap();
+
+    assert!(!applied);
+    assert_eq!(book.best_ask().unwrap().price, dec("101"));
+}
+
+#[test]
+fn crossed_and_stale_books_are_detected() {
+    let mut book = OrderBook::new();
+    assert!(book.is_stale(Duration::from_secs(60)));
+
+    book.apply_update(&update(
+        "2024-01-01T00:00:00.000Z",
+        &[("100", "1")],
+        &[("101", "1")],
+    ))
+    .unwrap();
+
+    assert!(book.is_crossed());
+    assert!(!book.is_stale(Duration::from_secs(60)));
+    assert!(book.is_stale(Duration::ZERO));
+}
+
+#[test]
+fn other_symbols_are_rejected() {
+    let mut book = sample_book();
+    let mut eth = update("2024-01-01T00:00:01.000Z", &[], &[]);
+    eth.symbol = "ETH".to_string();
+
+    assert!(book.apply_update(&eth).is_err());
+}
@@ -0,0 +1,245 @@
+use gmo_coin::pnl::PnlEngine;
+use gmo_coin::private::api::{Execution, LatestExecutions, OpenPositions};
+use gmo_coin::public::api::LatestRate;
+use gmo_coin::{GmoCoinError, Symbol};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+fn dec(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+fn at(timestamp: &str) -> OffsetDateTime {
+    OffsetDateTime::parse(timestamp, &Rfc3339).unwrap()
+}
+
+fn execution(
+    execution_id: usize,
+    symbol: &str,
+    side: &str,
+    price: &str,
+    size: &str,
+    fee: &str,
+    timestamp: &str,
+) -> Execution {
+    serde_json::from_value(serde_json::json!({
+        "executionId": execution_id,
+        "orderId": execution_id,
+        "symbol": symbol,
+        "side": side,
+        "settleType": "OPEN",
+        "size": size,
+        "price": price,
+        "lossGain": "0",
+        "fee": fee,
+        "timestamp": timestamp
+    }))
+    .unwrap()
+}
+
+fn position(side: &str, price: &str, size: &str, loss_gain: &str) -> OpenPositions {
+    serde_json::from_value(serde_json::json!({
+        "positionId": 1,
+        "symbol": "BTC_JPY",
+        "side": side,
+        "size": size,
+        "orderSize": "0",
+        "price": price,
+        "lossGain": loss_gain,
+        "leverage": "2",
+        "losscutPrice": "0",
+        "timestamp": "2024-01-01T00:00:00.000Z"
+    }))
+    .unwrap()
+}
+
+fn rate(symbol: &str, bid: &str, ask: &str) -> LatestRate {
+    serde_json::from_value(serde_json::json!({
+        "ask": ask,
+        "bid": bid,
+        "high": ask,
+        "last": bid,
+        "low": bid,
+        "symbol": symbol,
+        "timestamp": "2024-01-02T00:00:00.000Z",
+        "volume": "1"
+    }))
+    .unwrap()
+}
+
+/// 100円で1、200円で1買い、180円で1売る
+fn spot() -> PnlEngine {
+    let mut engine = PnlEngine::new();
+    for execution in [
+        execution(1, "BTC", "BUY", "100", "1", "1", "2024-01-01T00:00:00.000Z"),
+        execution(2, "BTC", "BUY", "200", "1", "2", "2024-01-01T01:00:00.000Z"),
+        execution(
+            3,
+            "BTC",
+            "SELL",
+            "180",
+            "1",
+            "3",
+            "2024-01-01T02:00:00.000Z",
+        ),
+    ] {
+        engine.add_execution(&execution).unwrap();
+    }
+    engine
+}
+
+#[test]
+fn spot_uses_average_cost() {
+    let mut engine = spot();
+    engine.update_price(&rate("BTC", "170", "171")).unwrap();
+    let report = engine.report(None, None).unwrap();
+
+    let btc = &report.symbols["BTC"];
+    assert_eq!(btc.realized, dec("30"));
+    assert_eq!(btc.fees, dec("6"));
+    assert_eq!(btc.position, dec("1"));
+    assert_eq!(btc.average_cost, Some(dec("150")));
+    assert_eq!(btc.unrealized, dec("20"));
+    assert_eq!(btc.net(), dec("44"));
+    assert_eq!(btc.executions, 3);
+    assert!(report.unpriced.is_empty());
+}
+
+#[test]
+fn selling_everything_realizes_against_cost() {
+    let mut engine = PnlEngine::new();
+    for execution in [
+        execution(1, "BTC", "BUY", "100", "1", "0", "2024-01-01T00:00:00.000Z"),
+        execution(
+            2,
+            "BTC",
+            "SELL",
+            "180",
+            "1",
+            "0",
+            "2024-01-01T01:00:00.000Z",
+        ),
+        // 売り切ったあとの買いは新しい取得単価から始める
+        execution(3, "BTC", "BUY", "300", "2", "0", "2024-01-01T02:00:00.000Z"),
+        execution(
+            4,
+            "BTC",
+            "SELL",
+            "250",
+            "2",
+            "0",
+            "2024-01-01T03:00:00.000Z",
+        ),
+    ] {
+        engine.add_execution(&execution).unwrap();
+    }
+    let report = engine.report(None, None).unwrap();
+
+    let btc = &report.symbols["BTC"];
+    assert_eq!(btc.realized, dec("-20"));
+    assert_eq!(btc.position, Decimal::ZERO);
+    assert_eq!(btc.average_cost, None);
+    assert!(report.unpriced.is_empty());
+}
+
+#[test]
+fn window_limits_realized_and_fees() {
+    let engine = spot();
+    let report = engine
+        .report(Some(at("2024-01-01T01:30:00Z")), None)
+        .unwrap();
+    // 期間の前の買いも取得単価には使う
+    let btc = &report.symbols["BTC"];
+    assert_eq!(btc.realized, dec("30"));
+    assert_eq!(btc.fees, dec("3"));
+    assert_eq!(btc.executions, 1);
+    // 価格が無ければ含み損益は出せない
+    assert_eq!(btc.unrealized, Decimal::ZERO);
+    assert_eq!(report.unpriced, ["BTC"]);
+
+    let report = engine
+        .report(None, Some(at("2024-01-01T02:00:00Z")))
+        .unwrap();
+    assert_eq!(report.symbols["BTC"].realized, Decimal::ZERO);
+    assert_eq!(report.symbols["BTC"].fees, dec("3"));
+}
+
+#[test]
+fn leverage_uses_loss_gain_and_positions() {
+    let mut engine = PnlEngine::new();
+    let executions: Vec<LatestExecutions> = serde_json::from_value(serde_json::json!([{
+        "executionId": 10,
+        "orderId": 10,
+        "symbol": "BTC_JPY",
+        "side": "SELL",
+        "settleType": "CLOSE",
+        "size": "0.1",
+        "price": "5050000",
+        "lossGain": "500",
+        "fee": "0",
+        "timestamp": "2024-01-01T00:00:00.000Z"
+    }]))
+    .unwrap();
+    engine.add_latest_executions(&executions).unwrap();
+    // 同じ約定は1回と数える
+    engine.add_latest_executions(&executions).unwrap();
+    engine
+        .add_execution(&execution(
+            11,
+            "ETH",
+            "BUY",
+            "300000",
+            "1",
+            "0",
+            "2024-01-01T00:00:00.000Z",
+        ))
+        .unwrap();
+
+    let positions = [
+        position("BUY", "5000000", "0.1", "111"),
+        position("SELL", "5100000", "0.1", "222"),
+    ];
+    engine.set_open_positions(Symbol::BTC_JPY, &positions);
+    let report = engine.report(None, None).unwrap();
+    // 価格が無ければ取引所の評価損益を使う
+    assert_eq!(report.symbols["BTC_JPY"].unrealized, dec("333"));
+    assert_eq!(report.unpriced, ["ETH", "BTC_JPY"]);
+
+    engine
+        .update_price(&rate("BTC_JPY", "5050000", "5060000"))
+        .unwrap();
+    engine
+        .update_price(&rate("ETH", "310000", "310100"))
+        .unwrap();
+    let report = engine.report(None, None).unwrap();
+    let btc = &report.symbols["BTC_JPY"];
+    assert_eq!(btc.realized, dec("500"));
+    assert_eq!(btc.executions, 1);
+    assert_eq!(btc.position, Decimal::ZERO);
+    assert_eq!(btc.average_cost, None);
+    // 買いはbidの5000、売りはaskの4000
+    assert_eq!(btc.unrealized, dec("9000"));
+
+    let total = report.total();
+    assert_eq!(total.realized, dec("500"));
+    assert_eq!(total.unrealized, dec("19000"));
+    assert_eq!(total.net(), dec("19500"));
+    assert!(report.unpriced.is_empty());
+}
+
+#[test]
+fn invalid_side_is_an_error() {
+    let mut engine = PnlEngine::new();
+    let result = engine.add_execution(&execution(
+        1,
+        "BTC",
+        "HOLD",
+        "100",
+        "1",
+        "0",
+        "2024-01-01T00:00:00.000Z",
+    ));
+    assert!(matches!(result, Err(GmoCoinError::Side(_))));
+}
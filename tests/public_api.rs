@@ -3,6 +3,7 @@ use gmo_coin::error::Result;
 use gmo_coin::public::api::{self, KlineInterval};
 use std::thread::sleep;
 use std::time::Duration;
+use time::macros::date;
 
 fn call_with_retry<T, F>(name: &str, mut f: F) -> T
 where
@@ -89,7 +90,7 @@ fn symbols_contains_core_symbols() {
 #[test]
 fn klines_returns_candles_for_sample_date() {
     let resp = call_with_retry("public::api::klines", || {
-        api::klines(Symbol::BTC, KlineInterval::OneMin, "20210417".to_string())
+        api::klines(Symbol::BTC, KlineInterval::OneMin, date!(2021 - 04 - 17))
     });
 
     assert_eq!(resp.status, 0);
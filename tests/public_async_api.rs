@@ -6,6 +6,7 @@ use gmo_coin::public::api::KlineInterval;
 use gmo_coin::public::async_api;
 use std::future::Future;
 use std::time::Duration;
+use time::macros::date;
 
 async fn call_with_retry<T, F, Fut>(name: &str, mut f: F) -> T
 where
@@ -100,7 +101,7 @@ async fn symbols_contains_core_symbols() {
 #[tokio::test]
 async fn klines_returns_candles_for_sample_date() {
     let resp = call_with_retry("public::async_api::klines", || async {
-        async_api::klines(Symbol::BTC, KlineInterval::OneMin, "20210417".to_string()).await
+        async_api::klines(Symbol::BTC, KlineInterval::OneMin, date!(2021 - 04 - 17)).await
     })
     .await;
 
@@ -0,0 +1,104 @@
+use gmo_coin::config::Config;
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::private::api::{self, ExecutionType, SettlePosition};
+use gmo_coin::{GmoCoinClient, LeverageSymbol, Side, Symbol};
+
+fn read_only_client() -> GmoCoinClient {
+    GmoCoinClient::from_env()
+        .credentials("api-key", "secret-key")
+        .read_only(true)
+}
+
+fn assert_refused<T: std::fmt::Debug>(result: gmo_coin::GmoCoinResult<T>, path: &str) {
+    match result {
+        Err(GmoCoinError::ReadOnly(refused)) => assert_eq!(refused, path),
+        other => panic!("expected {} to be refused, got {:?}", path, other),
+    }
+}
+
+#[test]
+fn refuses_state_changing_requests_before_signing() {
+    let client = read_only_client();
+
+    assert_refused(
+        client.sign(api::order_request(
+            Symbol::BTC_JPY,
+            Side::BUY,
+            ExecutionType::LIMIT,
+            Some("5000000".to_string()),
+            "0.01".to_string(),
+        )),
+        "/v1/order",
+    );
+    assert_refused(
+        client.sign(api::change_order_request(1, "5000000".to_string(), None)),
+        "/v1/changeOrder",
+    );
+    assert_refused(
+        client.sign(api::cancel_orders_request(vec![1, 2])),
+        "/v1/cancelOrders",
+    );
+    assert_refused(
+        client.sign(api::cancel_bulk_order_request(vec![Symbol::BTC])),
+        "/v1/cancelBulkOrder",
+    );
+    assert_refused(
+        client.sign(api::change_losscut_price_request(1, "4000000".to_string())),
+        "/v1/changeLosscutPrice",
+    );
+}
+
+#[test]
+fn refuses_calls_without_sending() {
+    // 接続先に届く前に拒否されるので、存在しないURLでもReadOnlyになる
+    let client = read_only_client().private_api("http://127.0.0.1:1");
+
+    assert_refused(client.cancel_order(1), "/v1/cancelOrder");
+    assert_refused(
+        client.close_order(
+            LeverageSymbol::BTC_JPY,
+            Side::SELL,
+            ExecutionType::MARKET,
+            None,
+            SettlePosition {
+                position_id: 1,
+                size: "0.01".to_string(),
+            },
+        ),
+        "/v1/closeOrder",
+    );
+}
+
+#[test]
+fn refuses_requests_signed_elsewhere() {
+    let signed = GmoCoinClient::from_env()
+        .credentials("api-key", "secret-key")
+        .sign(api::cancel_order_request(1))
+        .unwrap();
+
+    assert_refused(read_only_client().send(&signed), "/v1/cancelOrder");
+}
+
+#[test]
+fn allows_reads() {
+    let signed = read_only_client().sign(api::margin_request()).unwrap();
+
+    assert_eq!(signed.method, "GET");
+}
+
+#[test]
+fn profile_sets_read_only() {
+    let config =
+        Config::parse("[profiles.analyst]\nread_only = true\n\n[profiles.main]\n").unwrap();
+
+    assert!(
+        GmoCoinClient::from_config(&config, Some("analyst"))
+            .unwrap()
+            .is_read_only()
+    );
+    assert!(
+        !GmoCoinClient::from_config(&config, Some("main"))
+            .unwrap()
+            .is_read_only()
+    );
+}
@@ -0,0 +1,383 @@
+#![cfg(feature = "websocket")]
+
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::private::websock_api::{PrivateChannel, PrivateMessage, PrivateStream};
+use gmo_coin::public::websock_api::{Channel, Message, PublicStream};
+use gmo_coin::stream::{Backoff, Resync, StreamEvent};
+use gmo_coin::{GmoCoinClient, Symbol};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::handshake::server::{Request, Response};
+
+const ORDERBOOK: &str = r#"{"channel":"orderbooks","asks":[{"price":"455659","size":"0.1"}],"bids":[{"price":"455600","size":"0.1"}],"symbol":"BTC","timestamp":"2024-01-01T00:00:00.000Z"}"#;
+const EXECUTION: &str = r#"{"channel":"executionEvents","orderId":123,"executionId":72123911,"symbol":"BTC_JPY","settleType":"OPEN","executionType":"LIMIT","side":"BUY","executionPrice":"877404","executionSize":"0.5","positionId":1234567,"orderTimestamp":"2019-03-19T02:15:06.081Z","executionTimestamp":"2019-03-19T02:15:06.081Z","lossGain":"0","fee":"323","orderPrice":"877200","orderSize":"0.8","orderExecutedSize":"0.5","timeInForce":"FAS","msgType":"ER"}"#;
+const ORDER: &str = r#"{"channel":"orderEvents","orderId":124,"symbol":"BTC_JPY","settleType":"OPEN","executionType":"LIMIT","side":"BUY","orderStatus":"ORDERED","orderTimestamp":"2019-03-19T02:15:06.081Z","orderPrice":"876045","orderSize":"0.8","orderExecutedSize":"0","losscutPrice":"0","timeInForce":"FAS","msgType":"NOR"}"#;
+const ACTIVE_ORDERS: &str = r#"{"status":0,"data":{"pagination":{"currentPage":1,"count":1},"list":[{"rootOrderId":124,"orderId":124,"symbol":"BTC_JPY","side":"BUY","orderType":"NORMAL","executionType":"LIMIT","settleType":"OPEN","size":"0.8","executedSize":"0","price":"876045","losscutPrice":"0","status":"ORDERED","timeInForce":"FAS","timestamp":"2019-03-19T02:15:06.081Z"}]},"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+const LATEST_EXECUTIONS: &str = r#"{"status":0,"data":{"pagination":{"currentPage":1,"count":1},"list":[{"executionId":72123911,"orderId":123,"symbol":"BTC_JPY","side":"BUY","settleType":"OPEN","size":"0.5","price":"877404","lossGain":"0","fee":"323","timestamp":"2019-03-19T02:15:06.081Z"}]},"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+
+fn fast() -> Backoff {
+    Backoff {
+        initial: Duration::from_millis(10),
+        max: Duration::from_millis(50),
+        max_attempts: Some(3),
+    }
+}
+
+/// リクエストを1つずつ読み、`"METHOD path body"`を送って`route`の応答を返すRESTのサーバー
+fn rest(route: fn(&str) -> String) -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut head = Vec::new();
+                    loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        if line == "\r\n" {
+                            break;
+                        }
+                        head.push(line);
+                    }
+                    let length = head
+                        .iter()
+                        .find_map(|line| {
+                            let (key, value) = line.split_once(':')?;
+                            key.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).unwrap();
+
+                    let mut line = head[0].split_whitespace();
+                    let request = format!(
+                        "{} {} {}",
+                        line.next().unwrap(),
+                        line.next().unwrap().split('?').next().unwrap(),
+                        String::from_utf8(body).unwrap()
+                    );
+                    let response = route(&request);
+                    tx.send(request).unwrap();
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        response.len(),
+                        response
+                    )
+                    .unwrap();
+                }
+            });
+        }
+    });
+    (url, rx)
+}
+
+/// 接続ごとに、パスと最初のコマンドを送ってから`messages`の1つを送る
+///
+/// 最後の接続以外は送ったあとすぐに切断する。
+fn websocket(messages: Vec<&'static str>) -> (String, Receiver<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let last = messages.len() - 1;
+        for (i, message) in messages.into_iter().enumerate() {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream, message, i == last, tx.clone());
+        }
+    });
+    (url, rx)
+}
+
+// ハンドシェイクのコールバックの型はtungsteniteが決めている
+#[allow(clippy::result_large_err)]
+fn serve(stream: TcpStream, message: &str, keep: bool, tx: Sender<(String, String)>) {
+    let mut path = String::new();
+    let mut socket = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+        path = request.uri().path().to_string();
+        Ok(response)
+    })
+    .unwrap();
+    let command = socket.read().unwrap().into_text().unwrap().to_string();
+    tx.send((path, command)).unwrap();
+    socket.send(tungstenite::Message::text(message)).unwrap();
+    if keep {
+        while socket.read().is_ok() {}
+    }
+}
+
+#[test]
+fn public_stream_reconnects_and_resyncs() {
+    let (ws, commands) = websocket(vec![ORDERBOOK, ORDERBOOK]);
+    let (api, requests) = rest(|_| {
+        r#"{"status":0,"data":{"asks":[{"price":"455700","size":"1"}],"bids":[],"symbol":"BTC"},"responsetime":"2024-01-01T00:00:01.000Z"}"#.to_string()
+    });
+    let client = Arc::new(GmoCoinClient::from_env().public_api(&api));
+    let mut stream = PublicStream::connect_to(&ws)
+        .unwrap()
+        .backoff(fast())
+        .resync(client.clone());
+    stream.subscribe(Symbol::BTC, Channel::OrderBooks).unwrap();
+
+    let (_, first) = commands.recv().unwrap();
+    let subscribed = Instant::now();
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Message(Message::OrderBook(_))
+    ));
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Disconnected(_)
+    ));
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Reconnected { attempts: 1 }
+    ));
+    match stream.next_event().unwrap() {
+        StreamEvent::Resync(Resync::OrderBook(symbol, resp)) => {
+            assert_eq!(symbol, Symbol::BTC);
+            assert_eq!(resp.data.asks[0].price, "455700");
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    assert_eq!(requests.recv().unwrap(), "GET /v1/orderbooks ");
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Message(Message::OrderBook(_))
+    ));
+
+    // 購読し直すときも1秒に1回の制限を守る
+    let (_, second) = commands.recv().unwrap();
+    assert_eq!(first, second);
+    assert!(subscribed.elapsed() >= Duration::from_millis(900));
+
+    // `metrics`を指定しなければ`resync`のクライアントに記録する
+    let reconnects = format!("gmo_coin_ws_reconnects_total{{url=\"{}\"}} 1\n", ws);
+    assert!(client.get_metrics().render().contains(&reconnects));
+}
+
+#[test]
+fn gives_up_after_max_attempts() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        // 1回だけ受け付けてすぐに切断し、以降は接続を拒否する
+        let (stream, _) = listener.accept().unwrap();
+        drop(tungstenite::accept(stream).unwrap());
+    });
+
+    let mut stream = PublicStream::connect_to(&url).unwrap().backoff(fast());
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Disconnected(_)
+    ));
+    assert!(stream.next_event().is_err());
+}
+
+#[test]
+fn private_stream_refreshes_token_while_quiet() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let ws = format!("ws://{}/ws", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        // 購読のあと、しばらく何も送らない
+        let (stream, _) = listener.accept().unwrap();
+        let mut socket = tungstenite::accept(stream).unwrap();
+        socket.read().unwrap();
+        thread::sleep(Duration::from_millis(700));
+        socket.send(tungstenite::Message::text(EXECUTION)).unwrap();
+        while socket.read().is_ok() {}
+    });
+    let (api, requests) = rest(|_| {
+        r#"{"status":0,"data":"token-1","responsetime":"2024-01-01T00:00:00.000Z"}"#.to_string()
+    });
+    let client = GmoCoinClient::from_env()
+        .credentials("api-key", "secret-key")
+        .private_api(&format!("{}/private", api))
+        .private_ws(&ws);
+
+    let mut stream = PrivateStream::connect(Arc::new(client))
+        .unwrap()
+        .token_refresh(Duration::from_millis(200));
+    assert_eq!(requests.recv().unwrap(), "POST /private/v1/ws-auth {}");
+    stream.subscribe(PrivateChannel::ExecutionEvents).unwrap();
+
+    assert!(matches!(
+        stream.read().unwrap(),
+        PrivateMessage::Execution(_)
+    ));
+    assert_eq!(
+        requests.try_recv().unwrap(),
+        r#"PUT /private/v1/ws-auth {"token":"token-1"}"#
+    );
+}
+
+#[test]
+fn private_stream_refreshes_token_and_resyncs() {
+    let (ws, commands) = websocket(vec![EXECUTION, ORDER]);
+    let (api, requests) = rest(|request| {
+        let ack = r#"{"status":0,"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+        match request.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+            ["POST", "/private/v1/ws-auth"] => {
+                r#"{"status":0,"data":"token-1","responsetime":"2024-01-01T00:00:00.000Z"}"#
+            }
+            ["GET", "/private/v1/activeOrders"] => ACTIVE_ORDERS,
+            ["GET", "/private/v1/latestExecutions"] => LATEST_EXECUTIONS,
+            _ => ack,
+        }
+        .to_string()
+    });
+    // 読み取り専用でもトークンは取得できる
+    let client = GmoCoinClient::from_env()
+        .credentials("api-key", "secret-key")
+        .read_only(true)
+        .private_api(&format!("{}/private", api))
+        .private_ws(&ws);
+
+    let mut stream = PrivateStream::connect(Arc::new(client))
+        .unwrap()
+        .backoff(fast())
+        .resync_symbols(&[Symbol::BTC_JPY]);
+    assert_eq!(stream.get_token(), "token-1");
+    assert_eq!(requests.recv().unwrap(), "POST /private/v1/ws-auth {}");
+    stream.subscribe(PrivateChannel::ExecutionEvents).unwrap();
+
+    let (path, command) = commands.recv().unwrap();
+    assert_eq!(path, "/ws/token-1");
+    assert_eq!(
+        command,
+        r#"{"command":"subscribe","channel":"executionEvents"}"#
+    );
+    match stream.next_event().unwrap() {
+        StreamEvent::Message(PrivateMessage::Execution(event)) => {
+            assert_eq!(event.execution_id, 72123911);
+            assert_eq!(event.position_id, Some(1234567));
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Disconnected(_)
+    ));
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Reconnected { attempts: 1 }
+    ));
+    assert_eq!(
+        requests.recv().unwrap(),
+        r#"PUT /private/v1/ws-auth {"token":"token-1"}"#
+    );
+    assert_eq!(commands.recv().unwrap().1, command);
+
+    match stream.next_event().unwrap() {
+        StreamEvent::Resync(Resync::ActiveOrders(Symbol::BTC_JPY, orders)) => {
+            assert_eq!(orders[0].order_id, 124);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    match stream.next_event().unwrap() {
+        StreamEvent::Resync(Resync::LatestExecutions(Symbol::BTC_JPY, executions)) => {
+            assert_eq!(executions[0].execution_id, 72123911);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Message(PrivateMessage::Order(_))
+    ));
+
+    stream.close().unwrap();
+    let deleted = requests
+        .iter()
+        .find(|request| request.starts_with("DELETE"))
+        .unwrap();
+    assert_eq!(deleted, r#"DELETE /private/v1/ws-auth {"token":"token-1"}"#);
+}
+
+#[test]
+fn failed_extend_reauths_and_resync_reads_every_page() {
+    static AUTHS: AtomicUsize = AtomicUsize::new(0);
+    static PAGES: AtomicUsize = AtomicUsize::new(0);
+
+    let (ws, commands) = websocket(vec![EXECUTION, ORDER]);
+    let (api, requests) = rest(|request| {
+        match request.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+            ["POST", "/private/v1/ws-auth"] => format!(
+                r#"{{"status":0,"data":"token-{}","responsetime":"2024-01-01T00:00:00.000Z"}}"#,
+                AUTHS.fetch_add(1, Ordering::SeqCst) + 1
+            ),
+            ["PUT", "/private/v1/ws-auth"] => r#"{"status":1,"messages":[{"message_code":"ERR-5106","message_string":"Invalid token."}],"responsetime":"2024-01-01T00:00:00.000Z"}"#.to_string(),
+            ["GET", "/private/v1/activeOrders"] => {
+                // 1ページ目は上限の100件、2ページ目は1件
+                let size = match PAGES.fetch_add(1, Ordering::SeqCst) {
+                    0 => 100,
+                    _ => 1,
+                };
+                let order = serde_json::from_str::<serde_json::Value>(ACTIVE_ORDERS).unwrap()
+                    ["data"]["list"][0]
+                    .clone();
+                serde_json::json!({
+                    "status": 0,
+                    "data": {
+                        "pagination": {"currentPage": 1, "count": size},
+                        "list": vec![order; size]
+                    },
+                    "responsetime": "2024-01-01T00:00:00.000Z"
+                })
+                .to_string()
+            }
+            _ => LATEST_EXECUTIONS.to_string(),
+        }
+    });
+    let client = GmoCoinClient::from_env()
+        .credentials("api-key", "secret-key")
+        .private_api(&format!("{}/private", api))
+        .private_ws(&ws);
+
+    let mut stream = PrivateStream::connect(Arc::new(client))
+        .unwrap()
+        .backoff(fast())
+        .token_refresh(Duration::from_millis(300))
+        .resync_symbols(&[Symbol::BTC_JPY]);
+    stream.subscribe(PrivateChannel::ExecutionEvents).unwrap();
+    assert_eq!(commands.recv().unwrap().0, "/ws/token-1");
+    thread::sleep(Duration::from_millis(400));
+
+    // 延長に失敗したら切断として扱い、トークンを取得し直して接続する
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Disconnected(GmoCoinError::Api { .. })
+    ));
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Reconnected { attempts: 1 }
+    ));
+    assert_eq!(stream.get_token(), "token-2");
+    assert_eq!(commands.recv().unwrap().0, "/ws/token-2");
+
+    match stream.next_event().unwrap() {
+        StreamEvent::Resync(Resync::ActiveOrders(Symbol::BTC_JPY, orders)) => {
+            assert_eq!(orders.len(), 101);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    assert_eq!(
+        requests
+            .try_iter()
+            .filter(|request| request.contains("activeOrders"))
+            .count(),
+        2
+    );
+}
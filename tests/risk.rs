@@ -0,0 +1,171 @@
+use gmo_coin::config::Config;
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::private::api::ExecutionType;
+use gmo_coin::private::risk::{Limits, OrderCheck, RiskViolation, SymbolLimits};
+use gmo_coin::{GmoCoinClient, Side, Symbol};
+use rust_decimal::Decimal;
+use std::env;
+use std::fs;
+use std::str::FromStr;
+
+fn dec(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+fn order(price: Option<&str>, size: &str) -> OrderCheck {
+    OrderCheck {
+        symbol: "BTC_JPY".to_string(),
+        side: Side::BUY,
+        price: price.map(dec),
+        size: dec(size),
+        opens_position: true,
+        reference: Some(dec("5000000")),
+        position: Some(dec("0.3")),
+    }
+}
+
+#[test]
+fn size_and_notional_caps() {
+    let limits = SymbolLimits {
+        max_order_size: Some(dec("0.1")),
+        max_notional: Some(dec("400000")),
+        ..Default::default()
+    };
+
+    assert!(limits.check(&order(Some("3000000"), "0.1")).is_ok());
+    assert!(matches!(
+        limits.check(&order(Some("3000000"), "0.2")),
+        Err(RiskViolation::SizeTooLarge { .. })
+    ));
+    // 成行は最新の約定価格で約定代金を計算する
+    assert_eq!(
+        limits.check(&order(None, "0.1")),
+        Err(RiskViolation::NotionalTooLarge {
+            symbol: "BTC_JPY".to_string(),
+            notional: dec("500000"),
+            max: dec("400000"),
+        })
+    );
+}
+
+#[test]
+fn position_cap_only_applies_to_new_orders() {
+    let limits = SymbolLimits {
+        max_position: Some(dec("0.5")),
+        ..Default::default()
+    };
+
+    assert!(limits.check(&order(Some("5000000"), "0.2")).is_ok());
+    assert_eq!(
+        limits.check(&order(Some("5000000"), "0.3")),
+        Err(RiskViolation::PositionTooLarge {
+            symbol: "BTC_JPY".to_string(),
+            side: Side::BUY,
+            position: dec("0.6"),
+            max: dec("0.5"),
+        })
+    );
+
+    let close = OrderCheck {
+        opens_position: false,
+        ..order(Some("5000000"), "0.3")
+    };
+    assert!(limits.check(&close).is_ok());
+}
+
+#[test]
+fn price_deviation() {
+    let limits = SymbolLimits {
+        max_price_deviation: Some(dec("5")),
+        ..Default::default()
+    };
+
+    assert!(limits.check(&order(Some("4750000"), "0.01")).is_ok());
+    assert!(limits.check(&order(Some("5250000"), "0.01")).is_ok());
+    assert_eq!(
+        limits.check(&order(Some("500000"), "0.01")),
+        Err(RiskViolation::PriceDeviation {
+            symbol: "BTC_JPY".to_string(),
+            price: dec("500000"),
+            reference: dec("5000000"),
+            deviation: dec("90"),
+            max: dec("5"),
+        })
+    );
+    // 成行は乖離を見ない
+    assert!(limits.check(&order(None, "0.01")).is_ok());
+
+    let no_reference = OrderCheck {
+        reference: None,
+        ..order(Some("5000000"), "0.01")
+    };
+    assert!(matches!(
+        limits.check(&no_reference),
+        Err(RiskViolation::NoReference(_))
+    ));
+}
+
+#[test]
+fn per_symbol_limits_override_defaults() {
+    let config = Config::parse(
+        r#"
+[profiles.main.limits]
+max_order_size = "1"
+max_price_deviation = "5"
+
+[profiles.main.limits.symbols.BTC_JPY]
+max_order_size = "0.1"
+max_position = "0.5"
+"#,
+    )
+    .unwrap();
+    let limits = config.profile(Some("main")).unwrap().limits;
+
+    let btc = limits.for_symbol("BTC_JPY");
+    assert_eq!(btc.max_order_size, Some(dec("0.1")));
+    assert_eq!(btc.max_position, Some(dec("0.5")));
+    assert_eq!(btc.max_price_deviation, Some(dec("5")));
+
+    let eth = limits.for_symbol("ETH_JPY");
+    assert_eq!(eth.max_order_size, Some(dec("1")));
+    assert_eq!(eth.max_position, None);
+}
+
+#[test]
+fn client_rejects_and_audits_before_sending() {
+    let audit_log = env::temp_dir().join(format!("gmo_coin_risk_{}.ndjson", std::process::id()));
+    let _ = fs::remove_file(&audit_log);
+
+    // 上限を超えた注文は送信しないので、存在しない接続先でもRiskになる
+    let client = GmoCoinClient::from_env()
+        .credentials("api-key", "secret-key")
+        .private_api("http://127.0.0.1:1")
+        .limits(Limits {
+            max_order_size: Some(dec("0.1")),
+            audit_log: Some(audit_log.clone()),
+            ..Default::default()
+        });
+
+    let result = client.order(
+        Symbol::BTC_JPY,
+        Side::BUY,
+        ExecutionType::LIMIT,
+        Some("5000000".to_string()),
+        "1".to_string(),
+    );
+    assert!(matches!(
+        result,
+        Err(GmoCoinError::Risk(RiskViolation::SizeTooLarge { .. }))
+    ));
+
+    let rejections = client.rejections();
+    assert_eq!(rejections.len(), 1);
+    assert_eq!(rejections[0].symbol, "BTC_JPY");
+    assert_eq!(rejections[0].side, "BUY");
+    assert_eq!(rejections[0].size, "1");
+
+    let logged = fs::read_to_string(&audit_log).unwrap();
+    fs::remove_file(&audit_log).unwrap();
+    assert_eq!(logged.lines().count(), 1);
+    assert!(logged.contains("max_order_size"));
+}
@@ -0,0 +1,138 @@
+use gmo_coin::GmoCoinClient;
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::private::api;
+use gmo_coin::signer::{HmacSigner, Signer};
+use ring::hmac;
+
+struct Refuse;
+
+impl Signer for Refuse {
+    fn sign(&self, _: u64, method: &str, _: &str, _: &str) -> gmo_coin::GmoCoinResult<String> {
+        Err(GmoCoinError::Signer(format!("{} is not allowed", method)))
+    }
+}
+
+#[test]
+fn hmac_signer_signs_timestamp_method_path_body() {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+    let expected = hex::encode(hmac::sign(&key, b"1700000000000POST/v1/order{}").as_ref());
+
+    let sign = HmacSigner::new("secret")
+        .sign(1700000000000, "POST", "/v1/order", "{}")
+        .unwrap();
+
+    assert_eq!(sign, expected);
+}
+
+#[test]
+fn client_uses_signer_without_secret_key() {
+    let client = GmoCoinClient::from_env()
+        .credentials("api-key", "")
+        .signer(HmacSigner::new("external-secret"));
+    let signed = client.sign(api::margin_request()).unwrap();
+
+    let expected = HmacSigner::new("external-secret")
+        .sign(signed.timestamp, "GET", "/v1/account/margin", "")
+        .unwrap();
+    assert_eq!(signed.sign, expected);
+    assert_eq!(signed.api_key.expose(), "api-key");
+}
+
+#[test]
+fn signer_error_is_returned() {
+    let client = GmoCoinClient::from_env()
+        .credentials("api-key", "")
+        .signer(Refuse);
+
+    match client.sign(api::cancel_order_request(1)) {
+        Err(GmoCoinError::Signer(message)) => assert_eq!(message, "POST is not allowed"),
+        other => panic!("expected a signer error, got {:?}", other),
+    }
+}
+
+#[cfg(unix)]
+mod unix_socket {
+    use super::*;
+    use gmo_coin::signer::{self, UnixSocketSigner};
+    use std::env;
+    use std::path::PathBuf;
+    use std::thread;
+
+    fn socket_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("gmo_coin_{}_{}.sock", name, std::process::id()))
+    }
+
+    fn spawn<S: Signer + 'static>(path: &PathBuf, signer: S) {
+        let listener = signer::bind(path).unwrap();
+        thread::spawn(move || signer::serve(&listener, &signer));
+    }
+
+    #[test]
+    fn signs_over_socket() {
+        let path = socket_path("signer");
+        spawn(&path, HmacSigner::new("socket-secret"));
+
+        let remote = UnixSocketSigner::new(&path);
+        let local = HmacSigner::new("socket-secret");
+        for (method, path, body) in [
+            ("GET", "/v1/account/margin", ""),
+            ("POST", "/v1/order", "{\"symbol\":\"BTC\"}"),
+        ] {
+            assert_eq!(
+                remote.sign(1700000000000, method, path, body).unwrap(),
+                local.sign(1700000000000, method, path, body).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn relays_signer_errors() {
+        let path = socket_path("refuse");
+        spawn(&path, Refuse);
+
+        match UnixSocketSigner::new(&path).sign(0, "POST", "/v1/order", "{}") {
+            Err(GmoCoinError::Signer(message)) => assert!(message.contains("POST is not allowed")),
+            other => panic!("expected a signer error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn idle_connection_does_not_block_others() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::net::UnixStream;
+
+        let path = socket_path("idle");
+        spawn(&path, HmacSigner::new("socket-secret"));
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        // 接続したまま何も送らない
+        let _idle = UnixStream::connect(&path).unwrap();
+        let remote = UnixSocketSigner::new(&path);
+        assert_eq!(
+            remote.sign(0, "GET", "/v1/assets", "").unwrap(),
+            HmacSigner::new("socket-secret")
+                .sign(0, "GET", "/v1/assets", "")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn bind_leaves_other_files_alone() {
+        let path = socket_path("regular");
+        std::fs::write(&path, "keep me").unwrap();
+
+        assert!(matches!(signer::bind(&path), Err(GmoCoinError::Signer(_))));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn missing_socket_is_an_error() {
+        let result = UnixSocketSigner::new(socket_path("missing")).sign(0, "GET", "/", "");
+
+        assert!(matches!(result, Err(GmoCoinError::Signer(_))));
+    }
+}
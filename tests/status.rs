@@ -0,0 +1,177 @@
+use gmo_coin::GmoCoinClient;
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::private::api::margin_request;
+use gmo_coin::public::api::ExchangeStatus;
+use gmo_coin::status::{ExchangeState, MaintenancePolicy, StatusChange, StatusWatcher};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const MAINTENANCE: &str = r#"{"status":5,"messages":[{"message_code":"ERR-5201","message_string":"MAINTENANCE. Please wait for a while"}],"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+const MARGIN: &str = r#"{"status":0,"data":{"actualProfitLoss":"0","availableAmount":"0","margin":"0","profitLoss":"0"},"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+
+fn status_body(status: &str) -> String {
+    format!(
+        r#"{{"status":0,"data":{{"status":"{}"}},"responsetime":"2024-01-01T00:00:00.000Z"}}"#,
+        status
+    )
+}
+
+/// `bodies`を順に返すサーバー。最後のものは何度でも返す
+fn serve(bodies: Vec<String>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for (i, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                line.clear();
+            }
+            let body = &bodies[i.min(bodies.len() - 1)];
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+    url
+}
+
+#[test]
+fn parse_exchange_state() {
+    let status = ExchangeStatus {
+        status: "PREOPEN".to_string(),
+    };
+    assert_eq!(status.state().unwrap(), ExchangeState::PreOpen);
+    assert_eq!(
+        "MAINTENANCE".parse::<ExchangeState>().unwrap().to_string(),
+        "MAINTENANCE"
+    );
+    assert!(matches!(
+        "CLOSED".parse::<ExchangeState>(),
+        Err(GmoCoinError::UnknownExchangeState(_))
+    ));
+}
+
+#[test]
+fn tracker_reports_only_changes() {
+    let client = GmoCoinClient::from_env();
+    let tracker = client.exchange_status();
+    let changes = tracker.subscribe();
+
+    assert_eq!(tracker.state(), None);
+    assert_eq!(
+        tracker.update(ExchangeState::Open),
+        Some(StatusChange {
+            from: None,
+            to: ExchangeState::Open
+        })
+    );
+    assert_eq!(tracker.update(ExchangeState::Open), None);
+    tracker.update(ExchangeState::Maintenance);
+
+    let received: Vec<StatusChange> = changes.try_iter().collect();
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[1].from, Some(ExchangeState::Open));
+    assert_eq!(received[1].to, ExchangeState::Maintenance);
+}
+
+#[test]
+fn fail_fast_during_maintenance() {
+    let client = GmoCoinClient::from_env()
+        .private_api("http://127.0.0.1:1")
+        .credentials("key", "secret")
+        .maintenance(MaintenancePolicy::FailFast);
+    client.exchange_status().update(ExchangeState::Maintenance);
+
+    assert!(matches!(
+        client.call::<Value>(margin_request()),
+        Err(GmoCoinError::Maintenance)
+    ));
+}
+
+#[test]
+fn wait_until_the_exchange_reopens() {
+    let url = serve(vec![MARGIN.to_string()]);
+    let client = GmoCoinClient::from_env()
+        .private_api(&url)
+        .credentials("key", "secret")
+        .maintenance(MaintenancePolicy::Wait {
+            timeout: Some(Duration::from_millis(50)),
+        });
+    let tracker = client.exchange_status();
+    tracker.update(ExchangeState::Maintenance);
+
+    assert!(matches!(
+        client.call::<Value>(margin_request()),
+        Err(GmoCoinError::Maintenance)
+    ));
+
+    let reopen = tracker.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        reopen.update(ExchangeState::Open);
+    });
+    let client = client.maintenance(MaintenancePolicy::Wait { timeout: None });
+    let margin: Value = client.call(margin_request()).unwrap();
+    assert_eq!(margin["status"], 0);
+}
+
+#[test]
+fn wait_polls_status_without_a_watcher() {
+    let url = serve(vec![
+        status_body("MAINTENANCE"),
+        status_body("OPEN"),
+        MARGIN.to_string(),
+    ]);
+    let client = GmoCoinClient::from_env()
+        .public_api(&url)
+        .private_api(&url)
+        .credentials("key", "secret")
+        .maintenance(MaintenancePolicy::Wait { timeout: None })
+        .maintenance_poll(Duration::from_millis(20));
+    client.exchange_status().update(ExchangeState::Maintenance);
+
+    let margin: Value = client.call(margin_request()).unwrap();
+    assert_eq!(margin["status"], 0);
+    assert_eq!(client.exchange_status().state(), Some(ExchangeState::Open));
+}
+
+#[test]
+fn maintenance_error_code_marks_the_tracker() {
+    let url = serve(vec![MAINTENANCE.to_string()]);
+    let client = GmoCoinClient::from_env()
+        .private_api(&url)
+        .credentials("key", "secret");
+
+    assert!(client.call::<Value>(margin_request()).is_err());
+    assert!(client.exchange_status().is_maintenance());
+}
+
+#[test]
+fn watcher_polls_status() {
+    let url = serve(vec![status_body("MAINTENANCE"), status_body("OPEN")]);
+    let client = Arc::new(GmoCoinClient::from_env().public_api(&url));
+    let changes = client.exchange_status().subscribe();
+
+    let watcher = StatusWatcher::spawn(client, Duration::from_millis(10));
+    let first = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+    let second = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+    watcher.stop();
+
+    assert_eq!(first.to, ExchangeState::Maintenance);
+    assert_eq!(
+        second,
+        StatusChange {
+            from: Some(ExchangeState::Maintenance),
+            to: ExchangeState::Open
+        }
+    );
+}
@@ -0,0 +1,190 @@
+#![cfg(feature = "tracing")]
+
+use gmo_coin::error::GmoCoinError;
+use gmo_coin::private::api::{ExecutionType, margin_request, order_request};
+use gmo_coin::{GmoCoinClient, Side, Symbol};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::util::SubscriberInitExt;
+
+const API_KEY: &str = "test-api-key";
+const SECRET_KEY: &str = "test-secret-key";
+const MAINTENANCE: &str = r#"{"status":5,"messages":[{"message_code":"ERR-5201","message_string":"MAINTENANCE. Please wait for a while"}],"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Capture {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+/// 1回だけ`body`を返すサーバー。受け取ったリクエストを返す
+fn serve(status: u16, body: &'static str) -> (String, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request = String::new();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                length = value.trim().parse().unwrap();
+            }
+            request.push_str(&line);
+            if line == "\r\n" {
+                break;
+            }
+        }
+        let mut content = vec![0; length];
+        reader.read_exact(&mut content).unwrap();
+        request.push_str(&String::from_utf8(content).unwrap());
+
+        write!(
+            stream,
+            "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+        .unwrap();
+        request
+    });
+    (url, handle)
+}
+
+fn traced<T>(f: impl FnOnce() -> T) -> (T, String) {
+    let capture = Capture::default();
+    let writer = capture.clone();
+    let _guard = tracing_subscriber::fmt()
+        .with_max_level(LevelFilter::DEBUG)
+        .with_writer(move || writer.clone())
+        .finish()
+        .set_default();
+    let result = f();
+    (result, capture.text())
+}
+
+fn sign_header(request: &str) -> String {
+    request
+        .lines()
+        .find_map(|line| {
+            line.to_ascii_lowercase()
+                .strip_prefix("api-sign:")
+                .map(str::to_string)
+        })
+        .unwrap()
+        .trim()
+        .to_string()
+}
+
+#[test]
+fn signed_request_is_logged_without_credentials() {
+    let (url, server) = serve(200, MAINTENANCE);
+    let client = GmoCoinClient::from_env()
+        .private_api(&url)
+        .credentials(API_KEY, SECRET_KEY);
+
+    let (result, logs) = traced(|| client.call::<Value>(margin_request()));
+    let request = server.join().unwrap();
+
+    assert!(matches!(result, Err(GmoCoinError::Api { status: 5, .. })));
+    assert!(logs.contains("/v1/account/margin"), "{}", logs);
+    assert!(logs.contains("http_status=200"), "{}", logs);
+    assert!(logs.contains("gmo_status=5"), "{}", logs);
+    assert!(logs.contains("ERR-5201"), "{}", logs);
+    assert!(logs.contains("latency_ms="), "{}", logs);
+    assert!(
+        logs.contains(&format!("bytes={}", MAINTENANCE.len())),
+        "{}",
+        logs
+    );
+
+    // ヘッダは送っているが、ログには出さない
+    let sign = sign_header(&request);
+    assert!(request.contains(API_KEY));
+    assert!(!logs.contains(API_KEY), "{}", logs);
+    assert!(!logs.contains(SECRET_KEY), "{}", logs);
+    assert!(!logs.contains(&sign), "{}", logs);
+}
+
+#[test]
+fn symbol_is_taken_from_the_body() {
+    let (url, server) = serve(200, MAINTENANCE);
+    let client = GmoCoinClient::from_env()
+        .private_api(&url)
+        .credentials(API_KEY, SECRET_KEY);
+
+    let request = order_request(
+        Symbol::BTC_JPY,
+        Side::BUY,
+        ExecutionType::LIMIT,
+        Some("5000000".to_string()),
+        "0.01".to_string(),
+    );
+    let (result, logs) = traced(|| client.call::<Value>(request));
+    server.join().unwrap();
+
+    assert!(result.is_err());
+    assert!(logs.contains("method=\"POST\""), "{}", logs);
+    assert!(logs.contains("/v1/order"), "{}", logs);
+    assert!(logs.contains("BTC_JPY"), "{}", logs);
+}
+
+#[test]
+fn public_request_is_logged() {
+    let body = r#"{"status":0,"data":[{"ask":"1","bid":"1","high":"1","last":"1","low":"1","symbol":"BTC","timestamp":"2024-01-01T00:00:00.000Z","volume":"1"}],"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+    let (url, server) = serve(200, body);
+    let client = GmoCoinClient::from_env().public_api(&url);
+
+    let (result, logs) = traced(|| client.ticker(Some(Symbol::BTC)));
+    let request = server.join().unwrap();
+
+    assert_eq!(result.unwrap().data[0].symbol, "BTC");
+    assert!(request.starts_with("GET /v1/ticker?symbol=BTC "));
+    assert!(logs.contains("/v1/ticker"), "{}", logs);
+    assert!(logs.contains("symbol=\"BTC\""), "{}", logs);
+    assert!(logs.contains("gmo_status=0"), "{}", logs);
+}
+
+#[test]
+fn http_error_is_logged() {
+    let (url, server) = serve(503, "{}");
+    let client = GmoCoinClient::from_env().public_api(&url);
+
+    let (result, logs) = traced(|| client.status());
+    server.join().unwrap();
+
+    assert!(result.is_err());
+    assert!(logs.contains("request failed"), "{}", logs);
+    assert!(logs.contains("http_status=503"), "{}", logs);
+}
+
+#[test]
+fn signed_request_debug_is_redacted() {
+    let client = GmoCoinClient::from_env().credentials(API_KEY, SECRET_KEY);
+    let signed = client.sign(margin_request()).unwrap();
+
+    let debug = format!("{:?}", signed);
+    assert!(!debug.contains(API_KEY));
+    assert!(!debug.contains(&signed.sign));
+}
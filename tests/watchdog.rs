@@ -0,0 +1,224 @@
+#![cfg(feature = "websocket")]
+
+use gmo_coin::public::websock_api::{Channel, Message, PublicStream};
+use gmo_coin::status::ExchangeState;
+use gmo_coin::stream::{Backoff, StaleAction, StreamEvent, Watchdog};
+use gmo_coin::{GmoCoinClient, GmoCoinError, Symbol};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::WebSocket;
+
+const TICKER: &str = r#"{"channel":"ticker","ask":"1","bid":"1","high":"1","last":"1","low":"1","symbol":"BTC","timestamp":"2024-01-01T00:00:00.000Z","volume":"1"}"#;
+
+/// 接続ごとに別のスレッドで`serve`を呼ぶ
+fn websocket<F>(serve: F) -> String
+where
+    F: Fn(WebSocket<TcpStream>) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+    let serve = Arc::new(serve);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let socket = tungstenite::accept(stream.unwrap()).unwrap();
+            let serve = serve.clone();
+            thread::spawn(move || serve(socket));
+        }
+    });
+    url
+}
+
+/// 購読のコマンドを読んでティッカーを1つ送り、あとは黙って読み続ける(Pingには応答する)
+fn one_ticker(mut socket: WebSocket<TcpStream>) {
+    let _ = socket.read();
+    socket.send(tungstenite::Message::text(TICKER)).unwrap();
+    while socket.read().is_ok() {}
+}
+
+/// リクエストの最初の行を送って空の応答を返すRESTのサーバー
+fn rest() -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = Vec::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+                head.push(line);
+            }
+            let length = head
+                .iter()
+                .find_map(|line| line.strip_prefix("content-length: "))
+                .map_or(0, |value| value.trim().parse().unwrap());
+            reader.read_exact(&mut vec![0; length]).unwrap();
+
+            tx.send(head[0].trim().to_string()).unwrap();
+            let body = r#"{"status":0,"data":[],"responsetime":"2024-01-01T00:00:00.000Z"}"#;
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+    (url, rx)
+}
+
+#[test]
+fn stale_channel_alerts_and_reconnects() {
+    let url = websocket(one_ticker);
+    let alerts = Arc::new(AtomicUsize::new(0));
+    let counted = alerts.clone();
+    let watchdog = Watchdog::new(Arc::new(GmoCoinClient::from_env()))
+        .stale_after(Channel::Ticker, Duration::from_millis(200))
+        .on_stale(StaleAction::Alert(Arc::new(move |stale| {
+            assert_eq!(stale.channel, "ticker");
+            counted.fetch_add(1, Ordering::SeqCst);
+        })))
+        .on_stale(StaleAction::Reconnect);
+    let mut stream = PublicStream::connect_to(&url)
+        .unwrap()
+        .backoff(Backoff {
+            initial: Duration::from_millis(10),
+            ..Default::default()
+        })
+        .watchdog(watchdog);
+    stream.subscribe(Symbol::BTC, Channel::Ticker).unwrap();
+
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Message(Message::Ticker(_))
+    ));
+    let start = Instant::now();
+    match stream.next_event().unwrap() {
+        StreamEvent::Stale(stale) => {
+            assert_eq!(stale.channel, "ticker");
+            assert!(stale.silent >= Duration::from_millis(200));
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(alerts.load(Ordering::SeqCst), 1);
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Reconnected { attempts: 1 }
+    ));
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Message(Message::Ticker(_))
+    ));
+}
+
+#[test]
+fn cancels_all_only_while_open() {
+    let url = websocket(one_ticker);
+    let (api, requests) = rest();
+    let client = Arc::new(
+        GmoCoinClient::from_env()
+            .credentials("api-key", "secret-key")
+            .private_api(&format!("{}/private", api)),
+    );
+    let status = client.exchange_status();
+    status.update(ExchangeState::Maintenance);
+
+    let watchdog = Watchdog::new(client)
+        .stale_after(Channel::Ticker, Duration::from_millis(100))
+        .on_stale(StaleAction::CancelAll(vec![Symbol::BTC]));
+    let mut stream = PublicStream::connect_to(&url).unwrap().watchdog(watchdog);
+    stream.subscribe(Symbol::BTC, Channel::Ticker).unwrap();
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Message(_)
+    ));
+
+    // メンテナンスが終わってから数え始める
+    let start = Instant::now();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        status.update(ExchangeState::Open);
+    });
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Stale(_)
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert_eq!(
+        requests.recv().unwrap(),
+        "POST /private/v1/cancelBulkOrder HTTP/1.1"
+    );
+}
+
+#[test]
+fn cancel_failure_is_reported() {
+    let url = websocket(one_ticker);
+    let client = Arc::new(
+        GmoCoinClient::from_env()
+            .credentials("api-key", "secret-key")
+            .read_only(true),
+    );
+    let watchdog = Watchdog::new(client)
+        .stale_after(Channel::Ticker, Duration::from_millis(100))
+        .on_stale(StaleAction::CancelAll(vec![Symbol::BTC]));
+    let mut stream = PublicStream::connect_to(&url).unwrap().watchdog(watchdog);
+    stream.subscribe(Symbol::BTC, Channel::Ticker).unwrap();
+    stream.next_event().unwrap();
+
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::ActionFailed(GmoCoinError::ReadOnly("/v1/cancelBulkOrder"))
+    ));
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Stale(_)
+    ));
+}
+
+#[test]
+fn missing_pong_disconnects() {
+    // Pingを読まないので応答もしない
+    let url = websocket(|socket| {
+        thread::sleep(Duration::from_secs(5));
+        drop(socket);
+    });
+    let watchdog = Watchdog::new(Arc::new(GmoCoinClient::from_env()))
+        .ping(Duration::from_millis(50), Duration::from_millis(100));
+    let mut stream = PublicStream::connect_to(&url).unwrap().watchdog(watchdog);
+
+    let start = Instant::now();
+    match stream.next_event().unwrap() {
+        StreamEvent::Disconnected(GmoCoinError::WebSocket(tungstenite::Error::Io(e))) => {
+            assert_eq!(e.kind(), std::io::ErrorKind::TimedOut);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn pongs_keep_the_connection() {
+    let url = websocket(one_ticker);
+    let watchdog = Watchdog::new(Arc::new(GmoCoinClient::from_env()))
+        .ping(Duration::from_millis(20), Duration::from_millis(100))
+        .stale_after(Channel::Ticker, Duration::from_millis(500));
+    let mut stream = PublicStream::connect_to(&url).unwrap().watchdog(watchdog);
+    stream.subscribe(Symbol::BTC, Channel::Ticker).unwrap();
+    stream.next_event().unwrap();
+
+    // ティッカーは途絶えるが、その間もPongは返ってくる
+    assert!(matches!(
+        stream.next_event().unwrap(),
+        StreamEvent::Stale(_)
+    ));
+}
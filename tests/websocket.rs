@@ -0,0 +1,85 @@
+#![cfg(feature = "websocket")]
+
+use gmo_coin::GmoCoinError;
+use gmo_coin::orderbook::OrderBook;
+use gmo_coin::public::api::Trade;
+use gmo_coin::public::websock_api::Message;
+
+#[test]
+fn parse_ticker() {
+    let text = r#"{"channel":"ticker","ask":"750760","bid":"750600","high":"762302","last":"756662","low":"704874","symbol":"BTC","timestamp":"2018-03-30T12:34:56.789Z","volume":"194785.8484"}"#;
+    match Message::parse(text).unwrap() {
+        Some(Message::Ticker(ticker)) => {
+            assert_eq!(ticker.symbol, "BTC");
+            assert_eq!(ticker.last, "756662");
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[test]
+fn parse_trade_into_rest_trade() {
+    let text = r#"{"channel":"trades","price":"750760","side":"BUY","size":"0.1","timestamp":"2018-03-30T12:34:56.789Z","symbol":"BTC"}"#;
+    let Some(Message::Trade(update)) = Message::parse(text).unwrap() else {
+        panic!("not a trade");
+    };
+
+    let trade = Trade::from(update);
+    assert_eq!(trade.side, "BUY");
+    assert_eq!(trade.size, "0.1");
+}
+
+#[test]
+fn parse_orderbooks_into_book() {
+    let text = r#"{"channel":"orderbooks","asks":[{"price":"455659","size":"0.1"},{"price":"455658","size":"0.2"}],"bids":[{"price":"455665","size":"0.1"}],"symbol":"BTC","timestamp":"2018-03-30T12:34:56.789Z"}"#;
+    let Some(Message::OrderBook(update)) = Message::parse(text).unwrap() else {
+        panic!("not an order book");
+    };
+
+    let mut book = OrderBook::new();
+    assert!(book.apply_update(&update).unwrap());
+    assert_eq!(book.best_ask().unwrap().price.to_string(), "455658");
+}
+
+#[test]
+fn parse_error_and_unknown_channel() {
+    assert!(matches!(
+        Message::parse(r#"{"error":"ERR-5003 Request too many."}"#),
+        Err(GmoCoinError::WebSocketApi(message)) if message.starts_with("ERR-5003")
+    ));
+    assert!(
+        Message::parse(r#"{"channel":"unknown"}"#)
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn parse_private_events() {
+    use gmo_coin::private::websock_api::PrivateMessage;
+
+    let text = r#"{"channel":"positionEvents","positionId":1234567,"symbol":"BTC_JPY","side":"BUY","size":"0.22","orderdSize":"0","price":"876045","lossGain":"14","leverage":"4","losscutPrice":"766540","timestamp":"2019-03-19T02:15:06.094Z","msgType":"OPR"}"#;
+    let Some(PrivateMessage::Position(event)) = PrivateMessage::parse(text).unwrap() else {
+        panic!("not a position event");
+    };
+    assert_eq!(event.order_size, "0");
+
+    let text = r#"{"channel":"orderEvents","orderId":123456789,"symbol":"BTC_JPY","settleType":"OPEN","executionType":"LIMIT","side":"BUY","orderStatus":"CANCELED","cancelType":"USER","orderTimestamp":"2019-03-19T02:15:06.059Z","orderPrice":"876045","orderSize":"0.8","orderExecutedSize":"0","losscutPrice":"0","timeInForce":"FAS","msgType":"COR"}"#;
+    let Some(PrivateMessage::Order(event)) = PrivateMessage::parse(text).unwrap() else {
+        panic!("not an order event");
+    };
+    assert_eq!(event.cancel_type.as_deref(), Some("USER"));
+}
+
+#[test]
+fn backoff_doubles_up_to_max() {
+    use gmo_coin::stream::Backoff;
+    use std::time::Duration;
+
+    let backoff = Backoff::default();
+    let delays: Vec<_> = (1..=8)
+        .map(|attempt| backoff.delay(attempt).as_secs())
+        .collect();
+    assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+    assert_eq!(backoff.delay(100), Duration::from_secs(60));
+}